@@ -1,8 +1,13 @@
 use std::cell::RefCell;
 use std::rc::Rc;
+use crate::gui::combat_log::LogCategoryEnum;
 use crate::gui::menu::Menu;
 use crate::inventory::item::Spell;
-use crate::pawn::pawn::Pawn;
+use crate::environment::tile::TILES;
+use crate::environment::world::Place;
+use crate::interact::actions::Actions;
+use crate::pawn::faction::FACTIONS;
+use crate::pawn::pawn::{Pawn, Position};
 
 #[derive(Debug, Clone)]
 pub struct AI {
@@ -21,23 +26,69 @@ pub fn let_ai_or_human_play<Fh, Fa, T>(pawn: Rc<RefCell<Pawn>>, human_action: Fh
 }
 
 impl AI {
+    /// Called for each step of the AI turn: look around, come closer to the target, then attack it.
     pub fn get_action(&mut self, self_ai: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<usize> {
-        menu.write_line(format!("{} select target", self.seen_target.len()).as_str())?;
+        let budget = self_ai.borrow().turn_budget.clone();
+        self.seen_target.retain(|target| !target.borrow().is_dead());
+        if self.selected_target.as_ref().map(|target| target.borrow().is_dead()).unwrap_or(false) {
+            self.selected_target = None;
+        }
 
         if self.seen_target.is_empty() {
-            return Ok(3); // Watch action
+            return Ok(if budget.bonus_action { Actions::WATCH } else { Actions::END_TURN } as usize);
         }
 
         if let None = self.selected_target {
             self.select_target(self_ai.clone(), menu)?;
         }
         //TODO Check if needs to equip weapon
-        if let Some(_selected_target) = self.selected_target.clone() {
-            return Ok(1); //Attack
+        let target = match self.selected_target.clone() {
+            Some(target) => target,
+            None => return Ok(Actions::END_TURN as usize)
+        };
+
+        let distance = Self::distance(&self_ai.borrow().position, &target.borrow().position);
+        let reach = self_ai.borrow().equipped.right_hand.as_ref()
+            .and_then(|weapon| weapon.range)
+            .unwrap_or(1);
+
+        if distance <= reach && budget.action {
+            return Ok(Actions::ATTACK as usize);
         }
+        if distance > reach && budget.movement > 0 {
+            return Ok(Actions::WALK_TO as usize);
+        }
+
+        menu.write_line(format!("{} ends its turn.", self.name).as_str())?;
+        Ok(Actions::END_TURN as usize)
+    }
+
+    /// The reachable tile closest to the selected target, none when the AI cannot get any closer.
+    pub fn choose_destination(&self, self_ai: &Pawn, range: &Vec<Vec<bool>>, place: &Place) -> Option<(u16, u16)> {
+        let target = self.selected_target.as_ref()?.borrow().position.clone();
+        let current_distance = Self::distance(&self_ai.position, &target);
+
+        let occupied = place.pawns.iter()
+            .filter(|p| p.borrow().id != self_ai.id && !p.borrow().is_dead())
+            .map(|p| p.borrow().position.clone())
+            .collect::<Vec<Position>>();
+
+        range.iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter()
+                .enumerate()
+                .filter(|(_, &reachable)| reachable)
+                .map(move |(x, _)| Position { x: x as u16, y: y as u16 }))
+            .filter(|tile| !occupied.contains(tile) && place.exit_at(tile).is_none())
+            .filter(|tile| TILES.is_walkable(place.room[tile.y as usize][tile.x as usize]))
+            .map(|tile| (Self::distance(&tile, &target), Self::distance(&tile, &self_ai.position), tile))
+            .filter(|(distance, _, _)| *distance < current_distance)
+            .min_by_key(|(distance, walked, _)| (*distance, *walked))
+            .map(|(_, _, tile)| (tile.x, tile.y))
+    }
 
-        menu.write_line(format!("{} choose what to do next.", self.name).as_str())?;
-        Ok(1)
+    fn distance(from: &Position, to: &Position) -> u16 {
+        (from.x as i32 - to.x as i32).abs().max((from.y as i32 - to.y as i32).abs()) as u16
     }
 
     pub fn select_weapon_or_spell(&self, self_ai: Rc<RefCell<Pawn>>) -> usize {
@@ -45,7 +96,7 @@ impl AI {
         if !clona_ai.borrow()
             .spell
             .iter()
-            .filter(|s| s.mana < clona_ai.borrow().mana)
+            .filter(|s| s.reaction.is_none() && s.mana < clona_ai.borrow().mana)
             .map(|spell| spell.clone())
             .collect::<Vec<Rc<Spell>>>()
             .is_empty() {
@@ -60,21 +111,129 @@ impl AI {
 
     pub fn select_target(&mut self, self_ai: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<()> {
         //TODO Select best target
-        let id = self_ai.clone().borrow().id.clone();
         let local_self = &self;
         let selected_target = local_self.seen_target.iter()
             .filter(|target| {
                 let target_clone = target.clone();
-                target_clone.borrow().id != id && target_clone.borrow().playable
+                FACTIONS.is_hostile(&self_ai.borrow(), &target_clone.borrow()) && !target_clone.borrow().is_dead()
             })
             .take(1)
             .map(|el| el.clone())
             .collect::<Vec<Rc<RefCell<Pawn>>>>();
 
-        let selected_target = selected_target.get(0).unwrap();
+        let selected_target = match selected_target.get(0) {
+            Some(selected_target) => selected_target,
+            None => return Ok(())
+        };
         self.selected_target = Some(selected_target.clone());
 
-        menu.write_line(format!("{} focus on {}", self.name, selected_target.clone().borrow().name).as_str())?;
+        menu.log(LogCategoryEnum::ATTACK, format!("{} focus on {}", self.name, selected_target.clone().borrow().name).as_str())?;
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pawn::party::PartySettings;
+    use crate::pawn::turn::TurnBudget;
+    use crate::services::initializer::Initializer;
+    use crate::services::messaging::Messaging;
+
+    fn place_of(pawns: &Vec<Rc<RefCell<Pawn>>>, room: Vec<Vec<u8>>) -> Place {
+        Place {
+            id: 1,
+            name: "Test room".to_string(),
+            weather: Initializer::init_weather().remove(0),
+            time: "Day".to_string(),
+            light: 255,
+            adjacent_places: vec![],
+            exits: vec![],
+            pawns: pawns.clone(),
+            objects: vec![],
+            triggers: vec![],
+            gas_clouds: vec![],
+            visited: true,
+            room,
+        }
+    }
+
+    fn goblin_at(position: Position) -> Rc<RefCell<Pawn>> {
+        let mut goblin = Initializer::generate_goblin(Initializer::item_named("Basic iron sword").unwrap(), position);
+        goblin.turn_budget = TurnBudget::new(3);
+        Rc::new(RefCell::new(goblin))
+    }
+
+    fn member_at(position: Position) -> Rc<RefCell<Pawn>> {
+        let member = PartySettings::load("party.ron").unwrap().create_members().pop().unwrap();
+        member.borrow_mut().position = position;
+        member
+    }
+
+    fn next_action(goblin: &Rc<RefCell<Pawn>>, menu: &Menu) -> usize {
+        let ai = goblin.borrow().ai.clone();
+        let action = ai.borrow_mut().as_mut().unwrap().get_action(goblin.clone(), menu).unwrap();
+        action
+    }
+
+    #[test]
+    fn without_anything_seen_the_ai_watches_then_ends_its_turn() {
+        let mut messaging = Messaging::init();
+        let (menu, _, _) = crate::gameplay_topics(&mut messaging);
+        let goblin = goblin_at(Position { x: 0, y: 0 });
+
+        assert_eq!(next_action(&goblin, &menu), Actions::WATCH as usize);
+        goblin.borrow_mut().turn_budget.bonus_action = false;
+        assert_eq!(next_action(&goblin, &menu), Actions::END_TURN as usize);
+    }
+
+    #[test]
+    fn the_ai_walks_to_its_target_attacks_it_then_ends_its_turn() {
+        let mut messaging = Messaging::init();
+        let (menu, _, _) = crate::gameplay_topics(&mut messaging);
+        let goblin = goblin_at(Position { x: 0, y: 0 });
+        let target = member_at(Position { x: 3, y: 0 });
+        goblin.borrow().ai.borrow_mut().as_mut().unwrap().add_target_to_watched_target(target.clone());
+
+        assert_eq!(next_action(&goblin, &menu), Actions::WALK_TO as usize);
+        let focused = goblin.borrow().ai.borrow().as_ref().and_then(|ai| ai.selected_target.clone());
+        assert!(focused.map(|focused| Rc::ptr_eq(&focused, &target)).unwrap_or(false));
+
+        goblin.borrow_mut().position = Position { x: 2, y: 0 };
+        assert_eq!(next_action(&goblin, &menu), Actions::ATTACK as usize);
+
+        goblin.borrow_mut().turn_budget.action = false;
+        assert_eq!(next_action(&goblin, &menu), Actions::END_TURN as usize);
+
+        //A dead target is forgotten
+        target.borrow_mut().life = 0;
+        assert_eq!(next_action(&goblin, &menu), Actions::WATCH as usize);
+        assert!(goblin.borrow().ai.borrow().as_ref().unwrap().selected_target.is_none());
+    }
+
+    #[test]
+    fn the_destination_is_the_reachable_tile_closest_to_the_target() {
+        let goblin = goblin_at(Position { x: 0, y: 0 });
+        let target = member_at(Position { x: 4, y: 0 });
+        let place = place_of(&vec![goblin.clone(), target.clone()], vec![vec![10u8; 5]]);
+        let mut ai = goblin.borrow().ai.borrow().clone().unwrap();
+        let everywhere = vec![vec![true; 5]];
+
+        assert_eq!(ai.choose_destination(&goblin.borrow(), &everywhere, &place), None);
+
+        ai.selected_target = Some(target.clone());
+        //range, destination
+        let reference = [
+            (everywhere.clone(), Some((3, 0))),
+            (vec![vec![true, true, false, false, false]], Some((1, 0))),
+            (vec![vec![true, false, false, false, false]], None),
+        ];
+        for (range, destination) in reference {
+            assert_eq!(ai.choose_destination(&goblin.borrow(), &range, &place), destination, "in {:?}", range);
+        }
+
+        //Already next to the target, there is nowhere closer to go
+        goblin.borrow_mut().position = Position { x: 3, y: 0 };
+        assert_eq!(ai.choose_destination(&goblin.borrow(), &everywhere, &place), None);
+    }
 }
\ No newline at end of file
@@ -1 +1,2 @@
-pub mod world;
\ No newline at end of file
+pub mod world;
+pub mod tile;
\ No newline at end of file
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use crate::inventory::item::DamageTypeEnum;
+use crate::pawn::pawn::Position;
+use crate::services::resources::load_ron;
+
+lazy_static! {
+    pub static ref TILES: TileRegistry = TileRegistry::load("tiles.ron").unwrap();
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TileTriggerEnum {
+    DAMAGE(DamageTypeEnum, u8),
+    HEAL(u8),
+    POISON_GAS { radius: u16, turns: u8, damage: u8 },
+    PIT(u8),
+    TELEPORT(Position),
+}
+
+impl TileTriggerEnum {
+    /// Pressure plates and gas vents are spent once they went off, other triggers stay.
+    pub fn is_single_use(&self) -> bool {
+        matches!(self, TileTriggerEnum::DAMAGE(..) | TileTriggerEnum::POISON_GAS { .. })
+    }
+
+    pub fn is_disarmable(&self) -> bool {
+        matches!(self, TileTriggerEnum::DAMAGE(..) | TileTriggerEnum::POISON_GAS { .. })
+    }
+
+    /// Harmless triggers are not avoided by pathfinding.
+    pub fn is_hazard(&self) -> bool {
+        !matches!(self, TileTriggerEnum::HEAL(_))
+    }
+
+    /// The pawn does not keep walking after falling in a pit or being teleported.
+    pub fn stops_movement(&self) -> bool {
+        matches!(self, TileTriggerEnum::PIT(_) | TileTriggerEnum::TELEPORT(_))
+    }
+}
+
+impl Display for TileTriggerEnum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileTriggerEnum::DAMAGE(damage_type, _) => write!(f, "{:?} pressure plate", damage_type),
+            TileTriggerEnum::HEAL(_) => write!(f, "healing fountain"),
+            TileTriggerEnum::POISON_GAS { .. } => write!(f, "poison gas vent"),
+            TileTriggerEnum::PIT(_) => write!(f, "pit"),
+            TileTriggerEnum::TELEPORT(_) => write!(f, "teleporter"),
+        }
+    }
+}
+
+/// How a disarm attempt ends, missing the difficulty by more than 5 sets the trap off.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DisarmOutcomeEnum {
+    DISARMED,
+    FAILED,
+    SET_OFF,
+}
+
+/// Trigger put on a tile of a place. A trap with a detection difficulty of 0 is seen by everyone,
+/// otherwise pawns have to spot it with a perception check first.
+#[derive(Debug, Clone)]
+pub struct TriggerTile {
+    pub position: Position,
+    pub effect: TileTriggerEnum,
+    pub detection: u8,
+    pub disarm: u8,
+    pub armed: bool,
+    pub discovered_by: Vec<i64>,
+}
+
+impl TriggerTile {
+    pub fn new(position: Position, effect: TileTriggerEnum, detection: u8, disarm: u8) -> Self {
+        Self {
+            position,
+            effect,
+            detection,
+            disarm,
+            armed: true,
+            discovered_by: vec![],
+        }
+    }
+
+    pub fn is_known_by(&self, pawn_id: i64) -> bool {
+        self.detection == 0 || self.discovered_by.contains(&pawn_id)
+    }
+
+    /// Stepping on it is a sure way to know it is there, single use triggers are spent.
+    pub fn step_on(&mut self, pawn_id: i64) -> TileTriggerEnum {
+        if self.effect.is_single_use() {
+            self.armed = false;
+        }
+        if !self.discovered_by.contains(&pawn_id) {
+            self.discovered_by.push(pawn_id);
+        }
+        self.effect.clone()
+    }
+
+    /// Dexterity roll against the disarm difficulty, a trap set off stays armed unless it is single use.
+    pub fn try_disarm(&mut self, roll: u8) -> DisarmOutcomeEnum {
+        if roll >= self.disarm {
+            self.armed = false;
+            DisarmOutcomeEnum::DISARMED
+        } else if roll.saturating_add(5) < self.disarm {
+            self.armed = !self.effect.is_single_use();
+            DisarmOutcomeEnum::SET_OFF
+        } else {
+            DisarmOutcomeEnum::FAILED
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GasCloud {
+    pub position: Position,
+    pub radius: u16,
+    pub turns: u8,
+    pub damage: u8,
+}
+
+impl GasCloud {
+    pub fn covers(&self, position: &Position) -> bool {
+        (self.position.x as i32 - position.x as i32).unsigned_abs() <= self.radius as u32 &&
+            (self.position.y as i32 - position.y as i32).unsigned_abs() <= self.radius as u32
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileDefinition {
+    pub id: u8,
+    pub name: String,
+    /// Name of the sprite in the sprite manifest.
+    pub texture: String,
+    pub glyph: char,
+    pub walkable: bool,
+    pub transparent: bool,
+    pub movement_cost: u8,
+    pub description: String,
+    #[serde(default)]
+    pub trigger: Option<TileTriggerEnum>,
+}
+
+/// Every tile id used in rooms is declared here, tile ids not found are handled as opaque walls.
+#[derive(Debug)]
+pub struct TileRegistry {
+    tiles: BTreeMap<u8, TileDefinition>,
+    unknown: TileDefinition,
+}
+
+impl TileRegistry {
+    pub fn load(file: &str) -> std::io::Result<Self> {
+        let definitions: Vec<TileDefinition> = load_ron(file)?;
+        Ok(Self::new(definitions))
+    }
+
+    pub fn new(definitions: Vec<TileDefinition>) -> Self {
+        Self {
+            tiles: definitions.into_iter()
+                .map(|tile| (tile.id, tile))
+                .collect(),
+            unknown: TileDefinition {
+                id: u8::MAX,
+                name: "Unknown".to_string(),
+                texture: String::new(),
+                glyph: '?',
+                walkable: false,
+                transparent: false,
+                movement_cost: u8::MAX,
+                description: String::new(),
+                trigger: None,
+            },
+        }
+    }
+
+    pub fn get(&self, id: u8) -> &TileDefinition {
+        self.tiles.get(&id).unwrap_or(&self.unknown)
+    }
+
+    pub fn is_walkable(&self, id: u8) -> bool {
+        self.get(id).walkable
+    }
+
+    pub fn is_transparent(&self, id: u8) -> bool {
+        self.get(id).transparent
+    }
+
+    pub fn movement_cost(&self, id: u8) -> u8 {
+        self.get(id).movement_cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trap(effect: TileTriggerEnum, disarm: u8) -> TriggerTile {
+        TriggerTile::new(Position { x: 1, y: 1 }, effect, 10, disarm)
+    }
+
+    #[test]
+    fn triggers_follow_reference_table() {
+        //trigger, single use, disarmable, hazard, stops movement
+        let reference = [
+            (TileTriggerEnum::DAMAGE(DamageTypeEnum::PIERCING, 5), true, true, true, false),
+            (TileTriggerEnum::POISON_GAS { radius: 1, turns: 3, damage: 2 }, true, true, true, false),
+            (TileTriggerEnum::PIT(5), false, false, true, true),
+            (TileTriggerEnum::HEAL(10), false, false, false, false),
+            (TileTriggerEnum::TELEPORT(Position { x: 0, y: 0 }), false, false, true, true),
+        ];
+        for (trigger, single_use, disarmable, hazard, stops) in reference {
+            assert_eq!(trigger.is_single_use(), single_use, "{} single use", trigger);
+            assert_eq!(trigger.is_disarmable(), disarmable, "{} disarmable", trigger);
+            assert_eq!(trigger.is_hazard(), hazard, "{} hazard", trigger);
+            assert_eq!(trigger.stops_movement(), stops, "{} stops movement", trigger);
+        }
+    }
+
+    #[test]
+    fn single_use_triggers_are_spent_once_stepped_on() {
+        let mut plate = trap(TileTriggerEnum::DAMAGE(DamageTypeEnum::PIERCING, 5), 10);
+        assert!(!plate.is_known_by(7));
+        assert!(matches!(plate.step_on(7), TileTriggerEnum::DAMAGE(_, 5)));
+        assert!(!plate.armed);
+        assert!(plate.is_known_by(7));
+
+        let mut pit = trap(TileTriggerEnum::PIT(5), 0);
+        pit.step_on(7);
+        pit.step_on(7);
+        assert!(pit.armed);
+        assert_eq!(pit.discovered_by, vec![7]);
+    }
+
+    #[test]
+    fn disarm_follows_reference_table() {
+        use DisarmOutcomeEnum::*;
+        //roll, outcome, still armed
+        let reference = [(15, DISARMED, false), (20, DISARMED, false), (14, FAILED, true), (10, FAILED, true), (9, SET_OFF, false), (0, SET_OFF, false)];
+        for (roll, outcome, armed) in reference {
+            let mut plate = trap(TileTriggerEnum::DAMAGE(DamageTypeEnum::PIERCING, 5), 15);
+            assert_eq!(plate.try_disarm(roll), outcome, "roll {}", roll);
+            assert_eq!(plate.armed, armed, "roll {}", roll);
+        }
+
+        //A trap that is not spent when it goes off stays there
+        let mut pit = trap(TileTriggerEnum::PIT(5), 15);
+        assert_eq!(pit.try_disarm(0), SET_OFF);
+        assert!(pit.armed);
+    }
+}
@@ -1,19 +1,254 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::rc::Rc;
+use crate::gui::combat_log::LogCategoryEnum;
 use crate::gui::graphical::sprite::{Layer, ObjectToSprite, Sprite};
-use crate::pawn::pawn::Pawn;
+use crate::gui::menu::Menu;
+use crate::interact::dialogue::DialogueBook;
+use crate::interact::fumble::FumbleTable;
+use crate::logic::quest::{Journal, QuestBook, QuestEventEnum};
+use crate::inventory::item::Item;
+use crate::pawn::faction::{FactionRegistry, Reputation, PLAYERS};
+use crate::pawn::party::Party;
+use crate::logic::hot_seat::{FogEnum, HotSeat};
+use crate::pawn::pawn::{Pawn, Position};
+use crate::environment::tile::{GasCloud, TriggerTile, TILES};
+use crate::services::a_star::reachable_tiles;
 
 #[derive(Debug)]
 pub struct World{
     pub name: String,
     pub places: Vec<Place>,
     pub day: u32,
+    pub fumbles: FumbleTable,
+    pub dialogues: DialogueBook,
+    /// Set by dialogue outcomes, they open or close dialogue choices.
+    pub flags: HashSet<String>,
+    pub quests: QuestBook,
+    pub journal: Journal,
+    pub reputation: Reputation,
+    pub party: Party,
+    pub hot_seat: HotSeat,
+}
+
+#[derive(Debug)]
+pub enum WorldError {
+    UnknownPlace(u8),
+    NotAdjacent(u8, u8),
+    OutOfRoom(u8, Position),
+    UnknownPawn(i64),
+    Disconnected(u8),
+}
+
+impl Display for WorldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorldError::UnknownPlace(id) => write!(f, "Place {} does not exist", id),
+            WorldError::NotAdjacent(from, to) => write!(f, "Place {} is not adjacent to place {}", from, to),
+            WorldError::OutOfRoom(id, position) => write!(f, "Position {:?} is outside of place {}", position, id),
+            WorldError::UnknownPawn(id) => write!(f, "Pawn {} is not in this place", id),
+            WorldError::Disconnected(id) => write!(f, "Place {} cannot be fully reached", id),
+        }
+    }
+}
+
+impl From<WorldError> for std::io::Error {
+    fn from(value: WorldError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::Other, value.to_string())
+    }
 }
 
 impl World {
     pub fn add_day(&mut self){
         self.day += 1;
     }
+
+    /// Move the journal forward, rewards of the quests completed go to the given pawn.
+    pub fn update_quests(&mut self, events: Vec<QuestEventEnum>, rewarded: Option<&Rc<RefCell<Pawn>>>, menu: &Menu) -> std::io::Result<()> {
+        self.journal.update(&self.quests, &self.flags, &events, rewarded, menu)
+    }
+
+    /// Change the standing of the players, a faction falling too low turns all its members against them.
+    pub fn update_reputation(&mut self, changes: Vec<(String, i16)>, menu: &Menu) -> std::io::Result<()> {
+        for (faction, amount) in changes {
+            if self.reputation.change(faction.as_str(), amount, menu)? {
+                menu.log(LogCategoryEnum::SYSTEM, format!("The {} turn against you!", faction).as_str())?;
+                self.turn_faction_hostile(faction.as_str());
+            }
+        }
+        Ok(())
+    }
+
+    /// Members in places the party has not reached yet hold the grudge as well.
+    fn turn_faction_hostile(&mut self, faction: &str) {
+        let pawns = self.places.iter()
+            .flat_map(|place| place.pawns.iter().cloned())
+            .collect::<Vec<Rc<RefCell<Pawn>>>>();
+        let players = pawns.iter()
+            .filter(|p| p.borrow().faction == PLAYERS)
+            .collect::<Vec<&Rc<RefCell<Pawn>>>>();
+        let members = pawns.iter()
+            .filter(|p| p.borrow().faction == faction && !p.borrow().is_dead())
+            .collect::<Vec<&Rc<RefCell<Pawn>>>>();
+        for member in members {
+            for player in players.iter() {
+                let player_id = player.borrow().id;
+                if !member.borrow().grudges.contains(&player_id) {
+                    member.borrow_mut().grudges.push(player_id);
+                    FactionRegistry::fight_back(member, player);
+                }
+            }
+        }
+    }
+
+    /// What the seated player knows of a place, none outside of hot-seat games.
+    pub fn fog(&mut self, place_id: u8) -> Option<Vec<Vec<FogEnum>>> {
+        let place = self.places.iter().find(|place| place.id == place_id)?;
+        self.hot_seat.fog(place)
+    }
+
+    pub fn get_place(&self, id: u8) -> Option<&Place> {
+        self.places.iter().find(|place| place.id == id)
+    }
+
+    pub fn get_place_mut(&mut self, id: u8) -> Option<&mut Place> {
+        self.places.iter_mut().find(|place| place.id == id)
+    }
+
+    pub fn are_adjacent(&self, from: u8, to: u8) -> bool {
+        self.get_place(from)
+            .map(|place| place.adjacent_places.contains(&to))
+            .unwrap_or(false)
+    }
+
+    /// Add a one way exit from a place to another one, both places become adjacent.
+    pub fn link(&mut self, from: u8, exit: Exit) -> Result<(), WorldError> {
+        let destination = self.get_place(exit.destination).ok_or(WorldError::UnknownPlace(exit.destination))?;
+        if !destination.contains(&exit.spawn) {
+            return Err(WorldError::OutOfRoom(exit.destination, exit.spawn));
+        }
+
+        let place = self.get_place_mut(from).ok_or(WorldError::UnknownPlace(from))?;
+        if !place.contains(&exit.position) {
+            return Err(WorldError::OutOfRoom(from, exit.position));
+        }
+
+        if !place.adjacent_places.contains(&exit.destination) {
+            place.adjacent_places.push(exit.destination);
+        }
+        place.exits.push(exit);
+        Ok(())
+    }
+
+    pub fn find_pawn_place(&self, pawn_id: i64) -> Option<u8> {
+        self.places.iter()
+            .find(|place| place.pawns.iter().any(|p| p.borrow().id == pawn_id))
+            .map(|place| place.id)
+    }
+
+    /// Check every place can be reached from the start one threw exits,
+    /// and every walkable tile of a place can be reached from where pawns arrive.
+    pub fn check_connectivity(&self, start: u8) -> Result<(), WorldError> {
+        let mut visited = vec![start];
+        let mut frontier = vec![start];
+        while let Some(id) = frontier.pop() {
+            let place = self.get_place(id).ok_or(WorldError::UnknownPlace(id))?;
+            for exit in &place.exits {
+                if !visited.contains(&exit.destination) {
+                    visited.push(exit.destination);
+                    frontier.push(exit.destination);
+                }
+            }
+        }
+
+        for place in &self.places {
+            if !visited.contains(&place.id) {
+                return Err(WorldError::Disconnected(place.id));
+            }
+
+            let arrivals = self.places.iter()
+                .flat_map(|p| p.exits.iter())
+                .filter(|exit| exit.destination == place.id)
+                .map(|exit| (exit.spawn.x, exit.spawn.y))
+                .chain(place.pawns.iter().map(|p| (p.borrow().position.x, p.borrow().position.y)))
+                .collect::<Vec<(u16, u16)>>();
+
+            let walkable_count = place.room.iter()
+                .flatten()
+                .filter(|&&tile| TILES.is_walkable(tile))
+                .count();
+
+            for arrival in arrivals {
+                if reachable_tiles(arrival, &place.room).len() != walkable_count {
+                    return Err(WorldError::Disconnected(place.id));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Take a pawn out of a place and put it on the spawn point of the exit destination.
+    /// The left place keeps its remaining pawns and objects until the next visit.
+    /// The traveller goes threw the exit first, the rest of the party follows and lines up behind.
+    pub fn move_party(&mut self, pawn_id: i64, from: u8, exit: &Exit) -> Result<(), WorldError> {
+        self.move_pawn(pawn_id, from, exit)?;
+        let followers = self.get_place(from).unwrap().pawns.iter()
+            .filter(|p| Party::is_member(&p.borrow()))
+            .map(|p| p.borrow().id)
+            .collect::<Vec<i64>>();
+        for follower in followers {
+            self.move_pawn(follower, from, exit)?;
+        }
+        self.form_party(exit.destination, &exit.spawn, pawn_id)
+    }
+
+    /// Put the party members of a place in formation around the anchor, the leader on it.
+    pub fn form_party(&mut self, place_id: u8, anchor: &Position, leader: i64) -> Result<(), WorldError> {
+        let formation = self.party.formation;
+        let place = self.get_place(place_id).ok_or(WorldError::UnknownPlace(place_id))?;
+        let mut members = place.pawns.iter()
+            .filter(|p| Party::is_member(&p.borrow()))
+            .cloned()
+            .collect::<Vec<Rc<RefCell<Pawn>>>>();
+        //The leader first, then the players before their companions
+        members.sort_by_key(|p| (p.borrow().id != leader, !p.borrow().playable));
+        let occupied = place.pawns.iter()
+            .filter(|p| !Party::is_member(&p.borrow()) && !p.borrow().is_dead())
+            .map(|p| p.borrow().position.clone())
+            .chain(place.exits.iter().map(|exit| exit.position.clone()))
+            .chain(place.triggers.iter().filter(|t| t.armed && t.effect.is_hazard()).map(|t| t.position.clone()))
+            .collect::<Vec<Position>>();
+
+        let positions = formation.place(anchor, members.len(), &place.room, &occupied);
+        for (member, position) in members.iter().zip(positions) {
+            member.borrow_mut().position = position;
+        }
+        Ok(())
+    }
+
+    pub fn move_pawn(&mut self, pawn_id: i64, from: u8, exit: &Exit) -> Result<(), WorldError> {
+        if !self.are_adjacent(from, exit.destination) {
+            return Err(WorldError::NotAdjacent(from, exit.destination));
+        }
+        if self.get_place(exit.destination).is_none() {
+            return Err(WorldError::UnknownPlace(exit.destination));
+        }
+
+        let place = self.get_place_mut(from).ok_or(WorldError::UnknownPlace(from))?;
+        let index = place.pawns.iter()
+            .position(|p| p.borrow().id == pawn_id)
+            .ok_or(WorldError::UnknownPawn(pawn_id))?;
+        let pawn = place.pawns.remove(index);
+
+        pawn.borrow_mut().position = exit.spawn.clone();
+
+        let destination = self.get_place_mut(exit.destination).unwrap();
+        destination.pawns.push(pawn);
+        destination.visited = true;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -25,6 +260,17 @@ pub struct Weather{
     pub wind: u8
 }
 
+/// Tile of a place leading to another place, pawns arrive on the spawn position.
+/// The description is what a pawn watching the tile is told of where it leads.
+#[derive(Debug, Clone)]
+pub struct Exit {
+    pub position: Position,
+    pub destination: u8,
+    pub spawn: Position,
+    pub description: String,
+}
+
+/// A room state is the pawns and objects it holds, they are kept in the place between visits.
 #[derive(Debug)]
 pub struct Place {
     pub id: u8,
@@ -33,10 +279,64 @@ pub struct Place {
     pub time: String,
     pub light: u8,
     pub adjacent_places: Vec<u8>,
+    pub exits: Vec<Exit>,
     pub pawns: Vec<Rc<RefCell<Pawn>>>,
+    pub objects: Vec<(Position, Rc<Item>)>,
+    pub triggers: Vec<TriggerTile>,
+    pub gas_clouds: Vec<GasCloud>,
+    pub visited: bool,
     pub room: Vec<Vec<u8>>
 }
 
+impl Place {
+    pub fn exit_at(&self, position: &Position) -> Option<&Exit> {
+        self.exits.iter().find(|exit| &exit.position == position)
+    }
+
+    pub fn armed_trigger_at(&self, position: &Position) -> Option<usize> {
+        self.triggers.iter().position(|trigger| trigger.armed && &trigger.position == position)
+    }
+
+    /// Positions of the armed hazards the pawn knows about, so it can walk around them.
+    pub fn known_hazards(&self, pawn_id: i64) -> Vec<(u16, u16)> {
+        self.triggers.iter()
+            .filter(|trigger| trigger.armed && trigger.effect.is_hazard() && trigger.is_known_by(pawn_id))
+            .map(|trigger| (trigger.position.x, trigger.position.y))
+            .collect()
+    }
+
+    /// Poison every pawn standing in a gas cloud, clouds fade after their last turn.
+    pub fn tick_gas_clouds(&mut self) -> Vec<(Rc<RefCell<Pawn>>, u8)> {
+        let mut poisoned = vec![];
+        for cloud in &self.gas_clouds {
+            for pawn in &self.pawns {
+                if pawn.borrow().life > 0 && cloud.covers(&pawn.borrow().position) {
+                    let damages = pawn.borrow_mut().take_hit(cloud.damage);
+                    poisoned.push((pawn.clone(), damages));
+                }
+            }
+        }
+
+        self.gas_clouds.iter_mut().for_each(|cloud| cloud.turns = cloud.turns.saturating_sub(1));
+        self.gas_clouds.retain(|cloud| cloud.turns > 0);
+        poisoned
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        self.room.get(position.y as usize)
+            .map(|row| (position.x as usize) < row.len())
+            .unwrap_or(false)
+    }
+}
+
+/// A pawn leaving its place threw an exit, applied to the world at the end of the action.
+#[derive(Debug, Clone)]
+pub struct RoomTransition {
+    pub pawn_id: i64,
+    pub from: u8,
+    pub exit: Exit,
+}
+
 impl ObjectToSprite for Place {
     fn get_world_origin(&self) -> Vec<Sprite> {
         self.room.iter()
@@ -47,7 +347,7 @@ impl ObjectToSprite for Place {
                 .collect::<Vec<(usize, usize)>>())
             .flatten()
             .map(|(el1, el2)|
-                Sprite::new(1, el1 as i32, el2 as i32, Layer::MOVABLES))
+                Sprite::new("selector", el1 as i32, el2 as i32, Layer::MOVABLES))
             .collect::<Vec<Sprite>>()
     }
 }
@@ -0,0 +1,127 @@
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LogCategoryEnum {
+    ATTACK,
+    DAMAGE,
+    MOVEMENT,
+    DIALOGUE,
+    SYSTEM,
+}
+
+impl LogCategoryEnum {
+    pub fn all() -> Vec<LogCategoryEnum> {
+        vec![LogCategoryEnum::ATTACK,
+             LogCategoryEnum::DAMAGE,
+             LogCategoryEnum::MOVEMENT,
+             LogCategoryEnum::DIALOGUE,
+             LogCategoryEnum::SYSTEM]
+    }
+
+    /// Short label of the filter buttons.
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogCategoryEnum::ATTACK => "ATK",
+            LogCategoryEnum::DAMAGE => "DMG",
+            LogCategoryEnum::MOVEMENT => "MOV",
+            LogCategoryEnum::DIALOGUE => "DLG",
+            LogCategoryEnum::SYSTEM => "SYS",
+        }
+    }
+}
+
+/// One line of the log, sent by the game thread on the stdout topic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub category: LogCategoryEnum,
+    pub text: String,
+}
+
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{:?}] {}", self.category, self.text)
+    }
+}
+
+/// Everything that happened during the session, the panel shows a window of the entries kept by the filters.
+#[derive(Debug, Clone)]
+pub struct CombatLog {
+    entries: Vec<LogEntry>,
+    shown: Vec<LogCategoryEnum>,
+    //Lines scrolled up from the most recent entry
+    scroll: usize,
+}
+
+impl Default for CombatLog {
+    fn default() -> Self {
+        Self {
+            entries: vec![],
+            shown: LogCategoryEnum::all(),
+            scroll: 0,
+        }
+    }
+}
+
+impl CombatLog {
+    /// Multi-line texts are split so every line can be scrolled to.
+    pub fn push(&mut self, entry: LogEntry) {
+        for line in entry.text.lines().filter(|line| !line.trim().is_empty()) {
+            self.entries.push(LogEntry { category: entry.category, text: line.to_string() });
+            //Keep the lines read while scrolled up in place
+            if self.scroll > 0 && self.shown.contains(&entry.category) {
+                self.scroll += 1;
+            }
+        }
+    }
+
+    pub fn is_shown(&self, category: &LogCategoryEnum) -> bool {
+        self.shown.contains(category)
+    }
+
+    pub fn toggle(&mut self, category: LogCategoryEnum) {
+        match self.shown.iter().position(|c| *c == category) {
+            Some(index) => {
+                self.shown.remove(index);
+            }
+            None => self.shown.push(category),
+        }
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    /// Positive lines go back in time.
+    pub fn scroll(&mut self, lines: i32) {
+        let scroll = (self.scroll as i32 + lines).max(0) as usize;
+        self.scroll = scroll.min(self.max_scroll());
+    }
+
+    fn filtered(&self) -> Vec<&LogEntry> {
+        self.entries.iter()
+            .filter(|entry| self.shown.contains(&entry.category))
+            .collect()
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.filtered().len().saturating_sub(1)
+    }
+
+    /// The last lines kept by the filters once scrolled, oldest first.
+    pub fn visible(&self, lines: usize) -> Vec<&LogEntry> {
+        let filtered = self.filtered();
+        let end = filtered.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(lines);
+        filtered[start..end].to_vec()
+    }
+
+    /// Write every entry, filtered or not, to a text file.
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        for entry in self.entries.iter() {
+            writeln!(file, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::environment::tile::TILES;
+use crate::environment::world::Place;
+use crate::logic::hot_seat::{FogEnum, HotSeat};
+
+/// Room drawn with the glyph of each tile.
+pub fn render_room(room: &Vec<Vec<u8>>) -> String {
+    room.iter()
+        .map(|row| row.iter()
+            .map(|&tile| TILES.get(tile).glyph)
+            .collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Room with its pawns on top, `@` for players and the first letter of the race for the others.
+/// Traps spotted by a pawn sharing its knowledge are shown as `^`.
+/// Items lying on the ground are shown as `*`.
+pub fn render_place(place: &Place, hot_seat: &HotSeat) -> String {
+    let mut lines = place.room.iter()
+        .map(|row| row.iter()
+            .map(|&tile| TILES.get(tile).glyph)
+            .collect::<Vec<char>>())
+        .collect::<Vec<Vec<char>>>();
+
+    let players = place.pawns.iter()
+        .filter(|p| p.borrow().playable && hot_seat.shares_knowledge(&p.borrow()))
+        .map(|p| p.borrow().id)
+        .collect::<Vec<i64>>();
+    for trigger in place.triggers.iter().filter(|t| t.armed && players.iter().any(|&id| t.is_known_by(id))) {
+        if let Some(cell) = lines.get_mut(trigger.position.y as usize).and_then(|row| row.get_mut(trigger.position.x as usize)) {
+            *cell = '^';
+        }
+    }
+
+    for (position, _) in &place.objects {
+        if let Some(cell) = lines.get_mut(position.y as usize).and_then(|row| row.get_mut(position.x as usize)) {
+            *cell = '*';
+        }
+    }
+
+    for pawn in &place.pawns {
+        let pawn = pawn.borrow();
+        let glyph = if pawn.playable {
+            '@'
+        } else {
+            pawn.race.chars().next().unwrap_or('?').to_ascii_lowercase()
+        };
+        if let Some(cell) = lines.get_mut(pawn.position.y as usize).and_then(|row| row.get_mut(pawn.position.x as usize)) {
+            *cell = glyph;
+        }
+    }
+
+    lines.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Place as one player knows it, tiles never seen are blank and only visible tiles show what stands on them.
+pub fn render_place_in_fog(place: &Place, hot_seat: &HotSeat, fog: &Vec<Vec<FogEnum>>) -> String {
+    let known = render_place(place, hot_seat);
+    let bare = render_room(&place.room);
+    known.lines()
+        .zip(bare.lines())
+        .enumerate()
+        .map(|(y, (known, bare))| known.chars()
+            .zip(bare.chars())
+            .enumerate()
+            .map(|(x, (known, bare))| match fog.get(y).and_then(|row| row.get(x)) {
+                Some(FogEnum::VISIBLE) => known,
+                Some(FogEnum::EXPLORED) => bare,
+                _ => ' '
+            })
+            .collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
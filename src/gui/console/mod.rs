@@ -0,0 +1 @@
+pub mod map;
\ No newline at end of file
@@ -0,0 +1,112 @@
+use ggez::input::keyboard::KeyCode;
+use serde::{Deserialize, Serialize};
+use crate::interact::actions::Actions;
+use crate::services::resources::load_ron;
+
+/// What a key does in the window, menus and targeting read the same directions.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyCommandEnum {
+    UP,
+    DOWN,
+    LEFT,
+    RIGHT,
+    CONFIRM,
+    CANCEL,
+    PAN_UP,
+    PAN_DOWN,
+    PAN_LEFT,
+    PAN_RIGHT,
+    ZOOM_IN,
+    ZOOM_OUT,
+    FOLLOW,
+    ACTION(Actions),
+}
+
+/// Keys are written with their `KeyCode` name, as `Return` or `A`.
+/// A key missing from the file keeps its default binding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub up: String,
+    pub down: String,
+    pub left: String,
+    pub right: String,
+    pub confirm: String,
+    pub cancel: String,
+    pub pan_up: String,
+    pub pan_down: String,
+    pub pan_left: String,
+    pub pan_right: String,
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub follow: String,
+    pub actions: Vec<(Actions, String)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            up: "Up".to_string(),
+            down: "Down".to_string(),
+            left: "Left".to_string(),
+            right: "Right".to_string(),
+            confirm: "Return".to_string(),
+            cancel: "Escape".to_string(),
+            pan_up: "Numpad8".to_string(),
+            pan_down: "Numpad2".to_string(),
+            pan_left: "Numpad4".to_string(),
+            pan_right: "Numpad6".to_string(),
+            zoom_in: "PageUp".to_string(),
+            zoom_out: "PageDown".to_string(),
+            follow: "F".to_string(),
+            actions: vec![
+                (Actions::OPEN, "O".to_string()),
+                (Actions::ATTACK, "A".to_string()),
+                (Actions::WALK_TO, "W".to_string()),
+                (Actions::WATCH, "L".to_string()),
+                (Actions::USE, "U".to_string()),
+                (Actions::EQUIP, "I".to_string()),
+                (Actions::DISARM, "D".to_string()),
+                (Actions::PICK_UP, "P".to_string()),
+                (Actions::DROP, "G".to_string()),
+                (Actions::LOOT, "K".to_string()),
+                (Actions::RELOAD, "R".to_string()),
+                (Actions::TALK, "T".to_string()),
+                (Actions::JOURNAL, "J".to_string()),
+                (Actions::SWITCH, "Tab".to_string()),
+                (Actions::END_TURN, "E".to_string()),
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn load(file: &str) -> std::io::Result<Self> {
+        load_ron(file)
+    }
+
+    pub fn command(&self, key: KeyCode) -> Option<KeyCommandEnum> {
+        let name = format!("{:?}", key);
+        let commands = [(&self.up, KeyCommandEnum::UP),
+            (&self.down, KeyCommandEnum::DOWN),
+            (&self.left, KeyCommandEnum::LEFT),
+            (&self.right, KeyCommandEnum::RIGHT),
+            (&self.confirm, KeyCommandEnum::CONFIRM),
+            (&self.cancel, KeyCommandEnum::CANCEL),
+            (&self.pan_up, KeyCommandEnum::PAN_UP),
+            (&self.pan_down, KeyCommandEnum::PAN_DOWN),
+            (&self.pan_left, KeyCommandEnum::PAN_LEFT),
+            (&self.pan_right, KeyCommandEnum::PAN_RIGHT),
+            (&self.zoom_in, KeyCommandEnum::ZOOM_IN),
+            (&self.zoom_out, KeyCommandEnum::ZOOM_OUT),
+            (&self.follow, KeyCommandEnum::FOLLOW)];
+
+        commands.into_iter()
+            .find(|(bound, _)| **bound == name)
+            .map(|(_, command)| command)
+            .or_else(|| self.actions.iter()
+                .find(|(_, bound)| *bound == name)
+                .map(|(action, _)| KeyCommandEnum::ACTION(action.clone())))
+    }
+}
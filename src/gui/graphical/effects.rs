@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Rect};
+use keyframe::{AnimationSequence, Keyframe, functions};
+use keyframe_derive::CanTween;
+use serde::{Deserialize, Serialize};
+use crate::gui::graphical::registry::{SpriteHandle, SpriteRegistry};
+use crate::inventory::item::DamageTypeEnum;
+use crate::services::resources::load_ron;
+
+/// Played for damage types the effects file does not list.
+pub const DEFAULT_IMPACT: &str = "impact";
+/// Flown for weapons and spells the effects file does not list.
+pub const DEFAULT_PROJECTILE: &str = "projectile";
+
+/// Curve followed between the start and the end of an effect.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum EasingEnum {
+    STEP,
+    LINEAR,
+    EASE_IN,
+    EASE_OUT,
+    EASE_IN_OUT,
+}
+
+/// An effect as written in the effects file.
+/// Frames are laid left to right from the region and loop until the effect is over.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectDefinition {
+    pub name: String,
+    pub sprite: String,
+    //Pixels of the first frame in the sprite image, the whole image when missing
+    #[serde(default)]
+    pub region: Option<(u32, u32, u32, u32)>,
+    #[serde(default = "single_frame")]
+    pub frames: u32,
+    //Milliseconds each frame is shown
+    #[serde(default)]
+    pub frame_duration: u64,
+    #[serde(default = "linear")]
+    pub easing: EasingEnum,
+    //Scale at the start and at the end
+    #[serde(default = "unscaled")]
+    pub scale: (f32, f32),
+    #[serde(default = "untinted")]
+    pub tint: (f32, f32, f32, f32),
+    //Fades out along the easing instead of vanishing at once
+    #[serde(default)]
+    pub fade: bool,
+    //Milliseconds on screen
+    pub duration: u64,
+    //Flies from the source tile to the target one instead of staying on the target
+    #[serde(default)]
+    pub travels: bool,
+    //Played on the target once this one is over
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+fn single_frame() -> u32 {
+    1
+}
+
+fn linear() -> EasingEnum {
+    EasingEnum::LINEAR
+}
+
+fn unscaled() -> (f32, f32) {
+    (1., 1.)
+}
+
+fn untinted() -> (f32, f32, f32, f32) {
+    (1., 1., 1., 1.)
+}
+
+/// Content of the effects file, effects and what plays them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EffectCatalog {
+    pub effects: Vec<EffectDefinition>,
+    //Impact played on a tile hit by each damage type
+    #[serde(default)]
+    pub damage_types: HashMap<DamageTypeEnum, String>,
+    //Effect flying to the target, by spell or weapon name
+    #[serde(default)]
+    pub projectiles: HashMap<String, String>,
+}
+
+/// What the keyframes of an effect move between its start and its end.
+#[derive(CanTween, Clone, Copy, Default)]
+pub struct Tweenable {
+    x: f32,
+    y: f32,
+    scale: f32,
+    alpha: f32,
+}
+
+impl Tweenable {
+    pub fn new(position: Vec2, scale: f32, alpha: f32) -> Self {
+        Self {
+            x: position.x,
+            y: position.y,
+            scale,
+            alpha,
+        }
+    }
+}
+
+struct LoadedEffect {
+    definition: EffectDefinition,
+    handle: SpriteHandle,
+    frames: Vec<Rect>,
+    next: Option<usize>,
+}
+
+struct PlayingEffect {
+    effect: usize,
+    sequence: AnimationSequence<Tweenable>,
+    started_at: Instant,
+    target: Vec2,
+    rotation: f32,
+    //Impact held back until the effect lands
+    then: Option<usize>,
+}
+
+/// Effects on screen, started from damage types and projectiles, positions are world pixels.
+#[derive(Default)]
+pub struct Effects {
+    effects: Vec<LoadedEffect>,
+    names: HashMap<String, usize>,
+    damage_types: HashMap<DamageTypeEnum, usize>,
+    projectiles: HashMap<String, usize>,
+    playing: Vec<PlayingEffect>,
+}
+
+impl Effects {
+    /// A file that cannot be read leaves no effect to play.
+    pub fn load(file: &str, registry: &SpriteRegistry) -> Self {
+        let catalog: EffectCatalog = load_ron(file).unwrap_or_else(|error| {
+            println!("Cannot load the effects {} : {}", file, error);
+            EffectCatalog::default()
+        });
+        Self::new(catalog, registry)
+    }
+
+    pub fn new(catalog: EffectCatalog, registry: &SpriteRegistry) -> Self {
+        let names = catalog.effects.iter()
+            .enumerate()
+            .map(|(index, definition)| (definition.name.clone(), index))
+            .collect::<HashMap<String, usize>>();
+
+        let effects = catalog.effects.into_iter()
+            .map(|definition| {
+                let handle = registry.handle(definition.sprite.as_str());
+                let (width, height) = registry.size(handle);
+                let frame_count = definition.frames.max(1);
+                let (x, y, w, h) = definition.region.unwrap_or((0, 0, width / frame_count, height));
+                let frames = (0..frame_count)
+                    .map(|frame| Rect::new((x + frame * w) as f32 / width as f32, y as f32 / height as f32, w as f32 / width as f32, h as f32 / height as f32))
+                    .collect();
+                let next = definition.next.as_ref().and_then(|next| names.get(next).copied());
+                LoadedEffect { definition, handle, frames, next }
+            })
+            .collect();
+
+        let resolve = |name: &String| {
+            let index = names.get(name).copied();
+            if index.is_none() {
+                println!("Unknown effect {}", name);
+            }
+            index
+        };
+        let damage_types = catalog.damage_types.iter()
+            .filter_map(|(damage_type, name)| resolve(name).map(|index| (damage_type.clone(), index)))
+            .collect();
+        let projectiles = catalog.projectiles.iter()
+            .filter_map(|(item, name)| resolve(name).map(|index| (item.clone(), index)))
+            .collect();
+
+        Self {
+            effects,
+            names,
+            damage_types,
+            projectiles,
+            playing: vec![],
+        }
+    }
+
+    /// Impact of a damage type on a tile, held back while a projectile is flying to it.
+    pub fn impact(&mut self, damage_type: &DamageTypeEnum, target: Vec2) {
+        let impact = match self.damage_types.get(damage_type).or(self.names.get(DEFAULT_IMPACT)) {
+            Some(impact) => *impact,
+            None => return
+        };
+
+        let effects = &self.effects;
+        let incoming = self.playing.iter_mut()
+            .find(|playing| playing.target == target && playing.then.is_none() && effects[playing.effect].definition.travels);
+        match incoming {
+            //A projectile with its own impact already ends on the tile
+            Some(incoming) if effects[incoming.effect].next.is_some() => {}
+            Some(incoming) => incoming.then = Some(impact),
+            None => self.start(impact, target, target),
+        }
+    }
+
+    /// What a weapon or a spell sends flying from one tile to another.
+    pub fn projectile(&mut self, name: &str, from: Vec2, to: Vec2) {
+        if let Some(projectile) = self.projectiles.get(name).or(self.names.get(DEFAULT_PROJECTILE)) {
+            self.start(*projectile, from, to);
+        }
+    }
+
+    fn start(&mut self, effect: usize, from: Vec2, to: Vec2) {
+        let definition = &self.effects[effect].definition;
+        let from = if definition.travels { from } else { to };
+        let end_alpha = if definition.fade { 0. } else { definition.tint.3 };
+
+        let start = Tweenable::new(from, definition.scale.0, definition.tint.3);
+        let end = Tweenable::new(to, definition.scale.1, end_alpha);
+        let duration = Duration::from_millis(definition.duration).as_secs_f64();
+        let keyframes: Vec<Keyframe<Tweenable>> = vec![Self::keyframe(start, 0., definition.easing),
+                                                       Self::keyframe(end, duration, definition.easing)];
+        let direction = to - from;
+
+        self.playing.push(PlayingEffect {
+            effect,
+            sequence: AnimationSequence::from(keyframes),
+            started_at: Instant::now(),
+            target: to,
+            rotation: direction.y.atan2(direction.x),
+            then: None,
+        });
+    }
+
+    fn keyframe(value: Tweenable, time: f64, easing: EasingEnum) -> Keyframe<Tweenable> {
+        match easing {
+            EasingEnum::STEP => Keyframe::new(value, time, functions::Step),
+            EasingEnum::LINEAR => Keyframe::new(value, time, functions::Linear),
+            EasingEnum::EASE_IN => Keyframe::new(value, time, functions::EaseIn),
+            EasingEnum::EASE_OUT => Keyframe::new(value, time, functions::EaseOut),
+            EasingEnum::EASE_IN_OUT => Keyframe::new(value, time, functions::EaseInOut),
+        }
+    }
+
+    /// Move the effects along, the ones over start what is chained to them.
+    pub fn update(&mut self) {
+        let effects = &self.effects;
+        let (over, playing): (Vec<PlayingEffect>, Vec<PlayingEffect>) = self.playing.drain(..)
+            .partition(|playing| playing.started_at.elapsed() >= Duration::from_millis(effects[playing.effect].definition.duration));
+        self.playing = playing;
+
+        for playing in self.playing.iter_mut() {
+            playing.sequence.advance_to(playing.started_at.elapsed().as_secs_f64());
+        }
+        for ended in over {
+            let chained = self.effects[ended.effect].next.into_iter().chain(ended.then);
+            for effect in chained.collect::<Vec<usize>>() {
+                self.start(effect, ended.target, ended.target);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.playing.clear();
+    }
+
+    /// Effects standing where `hidden` says the player cannot see are left out.
+    pub fn draw<F>(&self, canvas: &mut Canvas, registry: &SpriteRegistry, hidden: F) where F: Fn(Vec2) -> bool {
+        for playing in self.playing.iter() {
+            let effect = &self.effects[playing.effect];
+            let state = match playing.sequence.now_strict() {
+                Some(state) => state,
+                None => continue
+            };
+            if hidden(Vec2::new(state.x, state.y)) {
+                continue;
+            }
+            let frame = match effect.definition.frame_duration {
+                0 => 0,
+                frame_duration => (playing.started_at.elapsed().as_millis() / frame_duration as u128) as usize % effect.frames.len()
+            };
+            let (r, g, b, _) = effect.definition.tint;
+
+            let mut param = DrawParam::new()
+                .src(effect.frames[frame])
+                .dest(Vec2::new(state.x, state.y))
+                .offset(Vec2::new(0.5, 0.5))
+                .scale(Vec2::new(state.scale, state.scale))
+                .color(Color::new(r, g, b, state.alpha));
+            if effect.definition.travels {
+                param = param.rotation(playing.rotation);
+            }
+            registry.draw_region(canvas, effect.handle, param);
+        }
+    }
+}
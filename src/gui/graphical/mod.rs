@@ -1,2 +1,5 @@
+pub mod bindings;
+pub mod effects;
+pub mod registry;
 pub mod window;
 pub mod sprite;
\ No newline at end of file
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use ggez::Context;
+use ggez::graphics::{Canvas, DrawParam, Image, ImageFormat, Rect};
+use serde::{Deserialize, Serialize};
+use crate::services::resources::load_ron;
+
+/// Drawn for names missing from the manifest and for images that cannot be loaded.
+pub const MISSING_SPRITE: &str = "missing";
+const MISSING_SIZE: u32 = 32;
+
+/// A named sprite of the manifest, the whole image or a region of an atlas.
+/// Animated sprites have their frames laid left to right from the region.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteDefinition {
+    pub name: String,
+    pub image: String,
+    #[serde(default)]
+    pub region: Option<(u32, u32, u32, u32)>,
+    #[serde(default = "single_frame")]
+    pub frames: u32,
+    //Milliseconds each frame is shown
+    #[serde(default)]
+    pub frame_duration: u64,
+}
+
+fn single_frame() -> u32 {
+    1
+}
+
+/// Position of a sprite in the registry, resolved once from its name.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct SpriteHandle(usize);
+
+struct RegisteredSprite {
+    image: Image,
+    frames: Vec<Rect>,
+    frame_duration: Duration,
+}
+
+/// Every sprite the window can draw, adventures ship their own art by listing it in the manifest.
+pub struct SpriteRegistry {
+    sprites: Vec<RegisteredSprite>,
+    names: HashMap<String, SpriteHandle>,
+    started_at: Instant,
+}
+
+/// Draws nothing until loaded.
+impl Default for SpriteRegistry {
+    fn default() -> Self {
+        SpriteRegistry {
+            sprites: vec![],
+            names: HashMap::new(),
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl SpriteRegistry {
+    /// A manifest that cannot be read leaves only the missing sprite.
+    pub fn load(ctx: &Context, file: &str) -> Self {
+        let definitions: Vec<SpriteDefinition> = load_ron(file).unwrap_or_else(|error| {
+            println!("Cannot load the sprite manifest {} : {}", file, error);
+            vec![]
+        });
+        Self::new(ctx, definitions)
+    }
+
+    pub fn new(ctx: &Context, definitions: Vec<SpriteDefinition>) -> Self {
+        let missing = Self::missing_image(ctx);
+        let mut registry = SpriteRegistry {
+            sprites: vec![RegisteredSprite { image: missing, frames: vec![Rect::one()], frame_duration: Duration::ZERO }],
+            names: HashMap::from([(MISSING_SPRITE.to_string(), SpriteHandle(0))]),
+            started_at: Instant::now(),
+        };
+
+        //Atlases are loaded once whatever the number of sprites cut in them
+        let mut images: HashMap<String, Image> = HashMap::new();
+        for definition in definitions {
+            let image = match images.get(&definition.image) {
+                Some(image) => image.clone(),
+                None => match Image::from_path(ctx, definition.image.as_str()) {
+                    Ok(image) => {
+                        images.insert(definition.image.clone(), image.clone());
+                        image
+                    }
+                    Err(error) => {
+                        println!("Cannot load {} for the sprite {} : {}", definition.image, definition.name, error);
+                        continue;
+                    }
+                }
+            };
+
+            let (width, height) = (image.width() as f32, image.height() as f32);
+            let (x, y, w, h) = definition.region.unwrap_or((0, 0, image.width() / definition.frames.max(1), image.height()));
+            let frames = (0..definition.frames.max(1))
+                .map(|frame| Rect::new((x + frame * w) as f32 / width, y as f32 / height, w as f32 / width, h as f32 / height))
+                .collect();
+
+            registry.names.insert(definition.name, SpriteHandle(registry.sprites.len()));
+            registry.sprites.push(RegisteredSprite {
+                image,
+                frames,
+                frame_duration: Duration::from_millis(definition.frame_duration),
+            });
+        }
+        registry
+    }
+
+    /// Magenta and black checker, hard to miss on the map.
+    fn missing_image(ctx: &Context) -> Image {
+        let pixels = (0..MISSING_SIZE * MISSING_SIZE)
+            .flat_map(|i| {
+                let (x, y) = (i % MISSING_SIZE, i / MISSING_SIZE);
+                if (x / 8 + y / 8) % 2 == 0 { [255, 0, 255, 255] } else { [0, 0, 0, 255] }
+            })
+            .collect::<Vec<u8>>();
+        Image::from_pixels(ctx, pixels.as_slice(), ImageFormat::Rgba8UnormSrgb, MISSING_SIZE, MISSING_SIZE)
+    }
+
+    /// Unknown names get the missing sprite.
+    pub fn handle(&self, name: &str) -> SpriteHandle {
+        self.names.get(name).copied().unwrap_or(SpriteHandle(0))
+    }
+
+    /// Pixels of the whole image a sprite is cut in.
+    pub fn size(&self, handle: SpriteHandle) -> (u32, u32) {
+        self.sprites.get(handle.0)
+            .map(|sprite| (sprite.image.width(), sprite.image.height()))
+            .unwrap_or((MISSING_SIZE, MISSING_SIZE))
+    }
+
+    /// Source rectangle of the frame shown now.
+    fn frame(&self, sprite: &RegisteredSprite) -> Rect {
+        if sprite.frames.len() < 2 || sprite.frame_duration.is_zero() {
+            return sprite.frames[0];
+        }
+        let elapsed = self.started_at.elapsed().as_millis() / sprite.frame_duration.as_millis();
+        sprite.frames[(elapsed % sprite.frames.len() as u128) as usize]
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, handle: SpriteHandle, param: DrawParam) {
+        if let Some(sprite) = self.sprites.get(handle.0) {
+            canvas.draw(&sprite.image, param.src(self.frame(sprite)));
+        }
+    }
+
+    /// Draw with the source rectangle of the parameters, for atlases animated by the caller.
+    pub fn draw_region(&self, canvas: &mut Canvas, handle: SpriteHandle, param: DrawParam) {
+        if let Some(sprite) = self.sprites.get(handle.0) {
+            canvas.draw(&sprite.image, param);
+        }
+    }
+}
@@ -1,11 +1,12 @@
-use std::collections::BTreeMap;
 use ggez::glam::Vec2;
-use ggez::graphics::{DrawParam, Image};
+use ggez::graphics::DrawParam;
 use serde::{Deserialize, Serialize};
+use crate::gui::graphical::registry::{SpriteHandle, SpriteRegistry};
 
 #[derive(PartialEq, Clone, Serialize, Deserialize, Debug)]
 pub enum Layer {
     BACKGROUND,
+    OBJECTS,
     MOVABLES,
     UI,
     PARTICLE
@@ -15,26 +16,45 @@ pub trait ObjectToSprite {
     fn get_world_origin(&self) -> Vec<Sprite>;
 }
 
+/// Sprites are named as in the sprite manifest, the window resolves the name to a registry handle.
 #[derive(Clone,Serialize, Deserialize, Debug)]
 pub struct Sprite {
-    pub texture_id: u8,
+    pub name: String,
     pub pos_x: i32,
     pub pos_y: i32,
     pub layer: Layer
 }
 
+/// Life and mana of a pawn, drawn over its sprite.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PawnStatus {
+    pub id: i64,
+    pub pos_x: i32,
+    pub pos_y: i32,
+    pub life: u8,
+    pub max_life: u8,
+    pub mana: u8,
+    pub max_mana: u8,
+}
+
+impl PawnStatus {
+    pub fn is_dead(&self) -> bool {
+        self.life == 0
+    }
+}
+
 impl Sprite {
-    pub fn new(texture_id: u8, pos_x: i32, pos_y: i32, layer: Layer) -> Self {
+    pub fn new(name: &str, pos_x: i32, pos_y: i32, layer: Layer) -> Self {
         Sprite{
-            texture_id,
+            name: name.to_string(),
             pos_x,
             pos_y,
             layer
         }
     }
 
-    pub fn create_drawable(&self, sprite_size: f32, sprite_textures: &BTreeMap<u8, Image>) -> (Image, DrawParam){
+    pub fn create_drawable(&self, sprite_size: f32, registry: &SpriteRegistry) -> (SpriteHandle, DrawParam){
         let param = DrawParam::new().dest(Vec2::new(self.pos_x as f32* sprite_size, self.pos_y as f32 * sprite_size));
-        (sprite_textures.get(&self.texture_id).unwrap().clone(), param)
+        (registry.handle(self.name.as_str()), param)
     }
 }
\ No newline at end of file
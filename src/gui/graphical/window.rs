@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::HashMap;
 use std::ops::Not;
 use std::str::from_utf8;
 use std::sync::mpsc::{Receiver, Sender};
@@ -8,28 +8,69 @@ use ggez::{Context, GameResult};
 use ggez::conf::{NumSamples, WindowMode, WindowSetup};
 use ggez::event::MouseButton;
 use ggez::glam::Vec2;
-use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Image, Mesh, Rect, Text};
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use ggez::input::keyboard::KeyInput;
 use keyframe::{AnimationSequence, functions, keyframes};
 use keyframe_derive::CanTween;
-use crate::gui::graphical::sprite::{Layer, Sprite};
+use crate::gui::combat_log::{CombatLog, LogCategoryEnum, LogEntry};
+use crate::gui::graphical::bindings::{KeyBindings, KeyCommandEnum};
+use crate::gui::graphical::effects::Effects;
+use crate::gui::graphical::registry::{SpriteHandle, SpriteRegistry};
+use crate::gui::graphical::sprite::{Layer, PawnStatus, Sprite};
 use crate::interact::actions::Actions;
+use crate::interact::dialogue::DialogueLine;
+use crate::interact::interaction::BACK_OPTION;
+use crate::inventory::inventory::{InventoryCommandEnum, InventoryView};
+use crate::logic::hot_seat::FogEnum;
+use crate::logic::quest::JournalEntry;
+use crate::pawn::party::PartyMemberStatus;
 use crate::inventory::item::{DamageTypeEnum, ItemAttackTypeEnum};
-use crate::services::animator::Animator;
+use crate::pawn::turn::TurnBudget;
 use crate::services::messaging::MessageContent;
 
 const SPRITE_SIZE: i32 = 32;
+const WINDOW_SIZE: (f32, f32) = (800., 600.);
+//The map is drawn right of the menu column and above the log, the HUD owns the rest of the window
+const MAP_VIEWPORT: (f32, f32, f32, f32) = (160., 0., 640., 440.);
+const EDGE_SCROLL_MARGIN: f32 = 12.;
+const EDGE_SCROLL_SPEED: f32 = 400.;
+const ZOOM_STEP: f32 = 1.25;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.;
+const ROOM_TRANSITION_DURATION: Duration = Duration::from_millis(1500);
+const INVENTORY_ORIGIN: (f32, f32) = (420., 200.);
+const INVENTORY_CELL: f32 = 36.;
+const INVENTORY_COLUMNS: usize = 5;
+const FLOATING_NUMBER_DURATION: Duration = Duration::from_millis(1000);
+const DAMAGE_TYPE_MEMORY: Duration = Duration::from_millis(2000);
+const DEATH_FADE_DURATION: Duration = Duration::from_millis(1000);
+const CORPSE_ALPHA: f32 = 0.35;
+const LOG_ORIGIN: (f32, f32) = (200., 452.);
+const LOG_WIDTH: f32 = 600.;
+const LOG_LINES: usize = 6;
+const LOG_LINE_HEIGHT: f32 = 16.;
+const LOG_FILTER_WIDTH: f32 = 44.;
+const LOG_EXPORT_FILE: &str = "combat_log.txt";
+const KEY_BINDINGS_FILE: &str = "bindings.ron";
+const SPRITE_MANIFEST_FILE: &str = "sprites.ron";
+const EFFECTS_FILE: &str = "effects.ron";
+const MENU_ORIGIN: (f32, f32) = (0., 200.);
+const DIALOGUE_BOX_HEIGHT: f32 = 96.;
+const PORTRAIT_SIZE: f32 = 64.;
+//The party panel fills the top of the menu column
+const PARTY_PANEL: (f32, f32, f32, f32) = (4., 4., 152., 188.);
+const PARTY_LINE_HEIGHT: f32 = 36.;
 
 pub struct MainState {
-    sprites_movables: Vec<(Image, DrawParam)>,
-    sprites_background: Vec<(Image, DrawParam)>,
-    sprites_ui: Vec<(Image, DrawParam)>,
-    particles: Vec<(Image, DrawParam, Instant, u8)>,
-    animation_duration: u64,
+    sprites_movables: Vec<(SpriteHandle, DrawParam)>,
+    sprites_background: Vec<(SpriteHandle, DrawParam)>,
+    sprites_objects: Vec<(SpriteHandle, DrawParam)>,
+    sprites_ui: Vec<(SpriteHandle, DrawParam)>,
     mouse: Mouse,
     receivers: HashMap<String, Receiver<MessageContent>>,
     senders: HashMap<String, Sender<MessageContent>>,
-    sprites_textures: BTreeMap<u8, Image>,
-    stdout: String,
+    registry: SpriteRegistry,
+    combat_log: CombatLog,
     current_menu: Vec<String>,
     sprites: Vec<Sprite>,
     menu_to_show: Vec<((f32, f32), Vec<String>)>,
@@ -38,8 +79,29 @@ pub struct MainState {
     active_modal: Option<(f32, f32, String)>,
     gameplay_state: Option<Actions>,
     sprites_clicked: Vec<(f32, f32, Sprite)>,
-    animator: Animator,
     current_player_id: i64,
+    room_transition: Option<(Instant, String)>,
+    inventory: Option<InventoryView>,
+    dragged_item: Option<usize>,
+    effects: Effects,
+    turn_budget: Option<(i64, TurnBudget)>,
+    pawn_statuses: Vec<PawnStatus>,
+    floating_numbers: Vec<(Vec2, String, Color, Instant)>,
+    recent_damage_types: Vec<((u16, u16), DamageTypeEnum, Instant)>,
+    deaths: HashMap<i64, Instant>,
+    bindings: KeyBindings,
+    menu_cursor: usize,
+    menu_pending: bool,
+    awaiting_target: bool,
+    tile_cursor: (i32, i32),
+    targetable: Vec<Vec<bool>>,
+    camera: Camera,
+    room_size: Vec2,
+    dialogue: Option<DialogueLine>,
+    journal: Option<Vec<JournalEntry>>,
+    party: Vec<PartyMemberStatus>,
+    hand_off: Option<u8>,
+    fog: Option<Vec<Vec<FogEnum>>>,
 }
 
 impl Default for MainState {
@@ -47,14 +109,13 @@ impl Default for MainState {
         MainState {
             sprites_movables: vec![],
             sprites_background: vec![],
+            sprites_objects: vec![],
             sprites_ui: vec![],
-            particles: vec![],
-            animation_duration: 1,
             mouse: Default::default(),
             receivers: HashMap::new(),
             senders: HashMap::new(),
-            sprites_textures: Default::default(),
-            stdout: String::new(),
+            registry: SpriteRegistry::default(),
+            combat_log: CombatLog::default(),
             current_menu: vec![],
             sprites: vec![],
             menu_to_show: vec![],
@@ -63,8 +124,29 @@ impl Default for MainState {
             active_modal: None,
             gameplay_state: None,
             sprites_clicked: vec![],
-            animator: Animator::new(),
             current_player_id: i64::MAX,
+            room_transition: None,
+            inventory: None,
+            dragged_item: None,
+            effects: Effects::default(),
+            turn_budget: None,
+            pawn_statuses: vec![],
+            floating_numbers: vec![],
+            recent_damage_types: vec![],
+            deaths: HashMap::new(),
+            bindings: KeyBindings::default(),
+            menu_cursor: 0,
+            menu_pending: false,
+            awaiting_target: false,
+            tile_cursor: (0, 0),
+            targetable: vec![],
+            camera: Camera::default(),
+            room_size: Vec2::ZERO,
+            dialogue: None,
+            journal: None,
+            party: vec![],
+            hand_off: None,
+            fog: None,
         }
     }
 }
@@ -86,6 +168,69 @@ impl Mouse {
     }
 }
 
+/// Part of the room shown in the map viewport, positions are in world pixels.
+pub struct Camera {
+    center: Vec2,
+    zoom: f32,
+    follow: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            center: Vec2::ZERO,
+            zoom: 1.,
+            follow: true,
+        }
+    }
+}
+
+impl Camera {
+    /// World rectangle seen through the viewport.
+    pub fn view(&self, viewport: &Rect) -> Rect {
+        let (w, h) = (viewport.w / self.zoom, viewport.h / self.zoom);
+        Rect::new(self.center.x - w / 2., self.center.y - h / 2., w, h)
+    }
+
+    pub fn screen_to_world(&self, viewport: &Rect, screen: Vec2) -> Vec2 {
+        let view = self.view(viewport);
+        Vec2::new(view.x + (screen.x - viewport.x) / self.zoom, view.y + (screen.y - viewport.y) / self.zoom)
+    }
+
+    pub fn world_to_screen(&self, viewport: &Rect, world: Vec2) -> Vec2 {
+        let view = self.view(viewport);
+        Vec2::new(viewport.x + (world.x - view.x) * self.zoom, viewport.y + (world.y - view.y) * self.zoom)
+    }
+
+    /// Coordinates given to the canvas so world positions land in the viewport.
+    pub fn screen_coordinates(&self, viewport: &Rect, window: (f32, f32)) -> Rect {
+        let origin = self.screen_to_world(viewport, Vec2::ZERO);
+        Rect::new(origin.x, origin.y, window.0 / self.zoom, window.1 / self.zoom)
+    }
+
+    /// Moving the camera by hand stops following the pawn playing.
+    pub fn pan(&mut self, screen_delta: Vec2) {
+        self.center += screen_delta / self.zoom;
+        self.follow = false;
+    }
+
+    pub fn zoom(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    pub fn follow(&mut self) {
+        self.follow = true;
+    }
+
+    /// The center stays over the room.
+    pub fn update(&mut self, followed: Option<Vec2>, room_size: Vec2) {
+        if let Some(followed) = followed.filter(|_| self.follow) {
+            self.center = followed;
+        }
+        self.center = self.center.clamp(Vec2::ZERO, room_size.max(Vec2::ZERO));
+    }
+}
+
 impl MainState {
     fn new(ctx: &Context, receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>) -> GameResult<MainState> {
         let mouse = Mouse {
@@ -93,35 +238,32 @@ impl MainState {
             pos_x: 0.,
         };
 
-        let mut textures = BTreeMap::new();
-        textures.insert(0, Image::from_path(ctx, "/menu_background.png").unwrap());
-        textures.insert(1, Image::from_path(ctx, "/selector.png").unwrap());
-        textures.insert(2, Image::from_path(ctx, "/possible_area.png").unwrap());
-        textures.insert(10, Image::from_path(ctx, "/dungeon_ground.png").unwrap());
-        textures.insert(11, Image::from_path(ctx, "/door.png").unwrap());
-        textures.insert(12, Image::from_path(ctx, "/door.png").unwrap());
-        textures.insert(20, Image::from_path(ctx, "/wall.png").unwrap());
-        textures.insert(100, Image::from_path(ctx, "/particles.png").unwrap());
-        textures.insert(200, Image::from_path(ctx, "/warrior.png").unwrap());
-        textures.insert(201, Image::from_path(ctx, "/goblin.png").unwrap());
+        let registry = SpriteRegistry::load(ctx, SPRITE_MANIFEST_FILE);
+        let effects = Effects::load(EFFECTS_FILE, &registry);
 
+        let bindings = KeyBindings::load(KEY_BINDINGS_FILE).unwrap_or_else(|error| {
+            println!("Cannot load the key bindings, default ones are used : {}", error);
+            KeyBindings::default()
+        });
 
         let s = MainState {
             mouse,
             receivers,
             senders,
-            sprites_textures: textures,
+            registry,
+            effects,
+            bindings,
             ..Default::default()
         };
         Ok(s)
     }
 
-    fn draw_menu(&mut self, canvas: &mut Canvas, x: f32, y: f32, options: Vec<String>) -> GameResult<()> {
-        canvas.draw(self.sprites_textures.get(&(0 as u8))
-                        .unwrap(),
-                    DrawParam::new()
-                        .dest(Vec2::new(x, y))
-                        .scale(Vec2::new(5f32, 5f32)));
+    fn draw_menu(&mut self, ctx: &Context, canvas: &mut Canvas, x: f32, y: f32, options: Vec<String>) -> GameResult<()> {
+        self.registry.draw(canvas,
+                           self.registry.handle("menu_background"),
+                           DrawParam::new()
+                               .dest(Vec2::new(x, y))
+                               .scale(Vec2::new(5f32, 5f32)));
 
         options.iter()
             .enumerate()
@@ -135,15 +277,22 @@ impl MainState {
                                 .dest(Vec2::new(x + 10., (y + i as f32 * 20.) + 10.)));
             });
 
+        //Option picked by the arrows
+        if self.menu_pending {
+            let highlight = Rect::new(x + 6., (y + self.menu_cursor as f32 * 20.) + 8., 3. * 32. + 8., 19.);
+            let mesh = Mesh::new_rectangle(ctx, DrawMode::stroke(1.), highlight, Color::YELLOW)?;
+            canvas.draw(&mesh, DrawParam::new());
+        }
+
         Ok(())
     }
 
     fn draw_modal(&mut self, canvas: &mut Canvas, x: f32, y: f32, content: &str) -> GameResult<()> {
-        canvas.draw(self.sprites_textures.get(&(0 as u8))
-                        .unwrap(),
-                    DrawParam::new()
-                        .dest(Vec2::new(x, y))
-                        .scale(Vec2::new(7.5f32, 6.5f32)));
+        self.registry.draw(canvas,
+                           self.registry.handle("menu_background"),
+                           DrawParam::new()
+                               .dest(Vec2::new(x, y))
+                               .scale(Vec2::new(7.5f32, 6.5f32)));
 
         canvas.draw(&Text::new(content),
                     graphics::DrawParam::from([x, y])
@@ -166,7 +315,29 @@ impl MainState {
 
     fn set_gameplay_state(&mut self) {
         if let Ok(state_content) = self.receivers.get("gameplay_state").unwrap().try_recv() {
-            self.gameplay_state = Some(bincode::deserialize(state_content.content.as_slice()).unwrap());
+            let state: Actions = bincode::deserialize(state_content.content.as_slice()).unwrap();
+            //Watching asks for a tile straight away, other actions once their range is sent
+            if state == Actions::WATCH {
+                self.start_targeting();
+            }
+            self.gameplay_state = Some(state);
+        }
+    }
+
+    /// The cursor starts on the pawn playing.
+    fn start_targeting(&mut self) {
+        self.awaiting_target = true;
+        if let Some(status) = self.pawn_statuses.iter().find(|s| s.id == self.current_player_id) {
+            self.tile_cursor = (status.pos_x, status.pos_y);
+        }
+    }
+
+    fn receive_targetable(&mut self) {
+        if let Ok(targetable) = self.receivers.get("targetable").unwrap().try_recv() {
+            self.targetable = bincode::deserialize(targetable.content.as_slice()).unwrap();
+            if self.targetable.iter().flatten().any(|&cell| cell) {
+                self.start_targeting();
+            }
         }
     }
 
@@ -180,11 +351,156 @@ impl MainState {
         self.send_info_message(&x, &y);
     }
 
+    fn map_viewport() -> Rect {
+        Rect::new(MAP_VIEWPORT.0, MAP_VIEWPORT.1, MAP_VIEWPORT.2, MAP_VIEWPORT.3)
+    }
+
+    /// Tile under a point of the window, none outside of the map viewport.
+    fn screen_to_tile(&self, x: f32, y: f32) -> Option<(i32, i32)> {
+        let viewport = Self::map_viewport();
+        if !viewport.contains(Vec2::new(x, y)) {
+            return None;
+        }
+        let world = self.camera.screen_to_world(&viewport, Vec2::new(x, y));
+        let tile = (world / SPRITE_SIZE as f32).floor();
+        Some((tile.x as i32, tile.y as i32))
+    }
+
+    fn tile_center_on_screen(&self, tile: (i32, i32)) -> Vec2 {
+        let half_tile = SPRITE_SIZE as f32 / 2.;
+        let world = Vec2::new((tile.0 * SPRITE_SIZE) as f32 + half_tile, (tile.1 * SPRITE_SIZE) as f32 + half_tile);
+        self.camera.world_to_screen(&Self::map_viewport(), world)
+    }
+
+    fn followed_position(&self) -> Option<Vec2> {
+        self.pawn_statuses.iter()
+            .find(|s| s.id == self.current_player_id)
+            .map(|s| Vec2::new(((s.pos_x * SPRITE_SIZE) + SPRITE_SIZE / 2) as f32, ((s.pos_y * SPRITE_SIZE) + SPRITE_SIZE / 2) as f32))
+    }
+
+    /// The camera moves when the pointer rests on a border of the map.
+    fn edge_scroll(&mut self, delta: f32) {
+        let viewport = Self::map_viewport();
+        let pointer = Vec2::new(self.mouse.pos_x, self.mouse.pos_y);
+        if !viewport.contains(pointer) || self.inventory.is_some() {
+            return;
+        }
+
+        let mut direction = Vec2::ZERO;
+        if pointer.x < viewport.x + EDGE_SCROLL_MARGIN {
+            direction.x -= 1.;
+        } else if pointer.x > viewport.x + viewport.w - EDGE_SCROLL_MARGIN {
+            direction.x += 1.;
+        }
+        if pointer.y < viewport.y + EDGE_SCROLL_MARGIN {
+            direction.y -= 1.;
+        } else if pointer.y > viewport.y + viewport.h - EDGE_SCROLL_MARGIN {
+            direction.y += 1.;
+        }
+        if direction != Vec2::ZERO {
+            self.camera.pan(direction * EDGE_SCROLL_SPEED * delta);
+        }
+    }
+
+    /// Returns false when the command is not about the camera.
+    fn camera_command(&mut self, command: &KeyCommandEnum) -> bool {
+        //One tile on screen per key press
+        let pan_step = SPRITE_SIZE as f32 * self.camera.zoom;
+        match command {
+            KeyCommandEnum::PAN_UP => self.camera.pan(Vec2::new(0., -pan_step)),
+            KeyCommandEnum::PAN_DOWN => self.camera.pan(Vec2::new(0., pan_step)),
+            KeyCommandEnum::PAN_LEFT => self.camera.pan(Vec2::new(-pan_step, 0.)),
+            KeyCommandEnum::PAN_RIGHT => self.camera.pan(Vec2::new(pan_step, 0.)),
+            KeyCommandEnum::ZOOM_IN => self.camera.zoom(ZOOM_STEP),
+            KeyCommandEnum::ZOOM_OUT => self.camera.zoom(1. / ZOOM_STEP),
+            KeyCommandEnum::FOLLOW => self.camera.follow(),
+            _ => return false
+        }
+        true
+    }
+
     fn send_info_message(&mut self, x: &f32, y: &f32) {
+        let tile = match self.screen_to_tile(*x, *y) {
+            Some((tile_x, tile_y)) if tile_x >= 0 && tile_y >= 0 => Some((tile_x as u16, tile_y as u16)),
+            _ => return
+        };
+        self.senders.get("info").unwrap().send(MessageContent {
+            topic: "info".to_string(),
+            content: bincode::serialize(&tile).unwrap(),
+        }).unwrap();
+        self.awaiting_target = false;
+    }
+
+    /// Tell the game thread no tile will be picked, nothing is spent.
+    fn cancel_target(&mut self) {
         self.senders.get("info").unwrap().send(MessageContent {
             topic: "info".to_string(),
-            content: bincode::serialize(&((x / SPRITE_SIZE as f32).floor() as u16, (y / SPRITE_SIZE as f32).floor() as u16)).unwrap(),
+            content: bincode::serialize(&None::<(u16, u16)>).unwrap(),
         }).unwrap();
+        self.awaiting_target = false;
+        self.clear_after_turn();
+    }
+
+    /// Escape and right click go one step back: close what is open, pick the back entry or drop the targeting.
+    fn cancel(&mut self) {
+        if self.journal.is_some() {
+            self.journal = None;
+        } else if self.inventory.is_some() {
+            self.send_inventory_command(InventoryCommandEnum::CLOSE);
+        } else if self.active_modal.is_some() {
+            self.close_modal();
+        } else if self.menu_pending {
+            if let Some(back) = self.current_menu.iter().position(|option| option == BACK_OPTION) {
+                self.select_menu_option(back);
+            }
+        } else if self.awaiting_target {
+            self.cancel_target();
+        }
+    }
+
+    fn close_modal(&mut self) {
+        self.senders.get("info").unwrap().send(MessageContent {
+            topic: "info".to_string(),
+            content: vec![],
+        }).unwrap();
+        self.active_modal = None;
+    }
+
+    fn select_menu_option(&mut self, index: usize) {
+        self.selected_menu_option = Some(index);
+        self.senders.get("select_response").unwrap().send(MessageContent {
+            topic: "select_response".to_string(),
+            content: bincode::serialize(&index).unwrap(),
+        }).unwrap();
+        self.menu_pending = false;
+    }
+
+    /// Same as a click on the tile, for sprites and actions waiting for a target.
+    fn select_at(&mut self, x: f32, y: f32) {
+        let tile = match self.screen_to_tile(x, y) {
+            Some(tile) => tile,
+            None => return
+        };
+        let sprites_selected = self.sprites.iter()
+            .filter(|s| (s.pos_x, s.pos_y) == tile)
+            .map(|e| e.clone())
+            .collect::<Vec<Sprite>>();
+
+        //We check if user has clicked on something interactable and if interactions are availables
+        if !sprites_selected.is_empty() {
+            self.mouse_hovering_characterisation(x, y, sprites_selected);
+        }
+    }
+
+    fn draw_tile_cursor(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        if !self.awaiting_target || self.active_modal.is_some() {
+            return Ok(());
+        }
+        let (x, y) = self.tile_cursor;
+        let cursor = Rect::new((x * SPRITE_SIZE) as f32, (y * SPRITE_SIZE) as f32, SPRITE_SIZE as f32, SPRITE_SIZE as f32);
+        let mesh = Mesh::new_rectangle(ctx, DrawMode::stroke(2.), cursor, Color::YELLOW)?;
+        canvas.draw(&mesh, DrawParam::new());
+        Ok(())
     }
 
     fn watch_action(&mut self, x: &f32, y: &f32, sprites: Vec<Sprite>) {
@@ -195,17 +511,17 @@ impl MainState {
             .collect::<Vec<(f32, f32, Sprite)>>();
     }
 
-    fn get_all_targetables_cell_to_sprites(&self) -> Vec<Sprite> {
+    fn get_all_targetables_cell_to_sprites(&mut self) -> Vec<Sprite> {
         //Get all targetables cells
-        if let Ok(targetable) = self.receivers.get("targetable").unwrap().try_recv() {
-            let targetable_coordinates: Vec<Vec<bool>> = bincode::deserialize(targetable.content.as_slice()).unwrap();
+        let targetable_coordinates = std::mem::take(&mut self.targetable);
+        if !targetable_coordinates.is_empty() {
             targetable_coordinates.iter()
                 .enumerate()
                 .flat_map(|(y, row)| {
                     row.iter().enumerate()
                         .filter(|(x, &cell)| cell)
                         .map(|(x, &cell)|
-                            Sprite::new(2, x as i32, y as i32, Layer::UI)
+                            Sprite::new("possible_area", x as i32, y as i32, Layer::UI)
                         )
                         .collect::<Vec<Sprite>>()
                 })
@@ -254,8 +570,8 @@ impl MainState {
         if !targetable_cells.is_empty() {
             self.sprites_ui.append(&mut targetable_cells.iter()
                 .filter(|s| s.layer == Layer::UI)
-                .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures))
-                .collect::<Vec<(Image, DrawParam)>>());
+                .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.registry))
+                .collect::<Vec<(SpriteHandle, DrawParam)>>());
 
             self.sprites.append(&mut targetable_cells);
         }
@@ -265,46 +581,617 @@ impl MainState {
         if let Ok(response) = self.receivers.get("show_damage").unwrap().try_recv() {
             println!("receive damage info");
             if let Ok(target_position) = bincode::deserialize::<((u16, u16), DamageTypeEnum)>(response.content.as_slice()) {
-                let sprite = Sprite::new(1, target_position.0.0 as i32, target_position.0.1 as i32, Layer::UI);
-                self.sprites_ui.append(&mut vec![sprite.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures)]);
-                let attack_particle = Sprite::new(100, target_position.0.0 as i32, target_position.0.1 as i32, Layer::PARTICLE)
-                    .create_drawable(SPRITE_SIZE as f32, &self.sprites_textures);
-
-                let damage_type = match target_position.1 {
-                    DamageTypeEnum::FIRE => 0,
-                    DamageTypeEnum::SLASHING => 1,
-                    DamageTypeEnum::BLUNT => 2,
-                    _ => 0
+                if !self.is_hidden(target_position.0.0 as i32, target_position.0.1 as i32) {
+                    let sprite = Sprite::new("selector", target_position.0.0 as i32, target_position.0.1 as i32, Layer::UI);
+                    self.sprites_ui.append(&mut vec![sprite.create_drawable(SPRITE_SIZE as f32, &self.registry)]);
+                }
+                self.effects.impact(&target_position.1, Self::tile_center(target_position.0));
+                self.recent_damage_types.push((target_position.0, target_position.1.clone(), Instant::now()));
+            }
+        }
+    }
+
+    /// Life changes between two statuses become floating numbers, coloured by the last damage type seen on the tile.
+    fn receive_pawn_status(&mut self) {
+        while let Ok(response) = self.receivers.get("pawn_status").unwrap().try_recv() {
+            let statuses = match bincode::deserialize::<Vec<PawnStatus>>(response.content.as_slice()) {
+                Ok(statuses) => statuses,
+                Err(_) => continue
+            };
+
+            for status in statuses.iter() {
+                let previous = match self.pawn_statuses.iter().find(|p| p.id == status.id) {
+                    Some(previous) => previous.clone(),
+                    None => continue
+                };
+                let difference = status.life as i16 - previous.life as i16;
+                if difference != 0 {
+                    self.spawn_floating_number(status, difference);
+                }
+                if status.is_dead() && !previous.is_dead() {
+                    self.deaths.insert(status.id, Instant::now());
+                }
+            }
+            self.pawn_statuses = statuses;
+        }
+    }
+
+    fn spawn_floating_number(&mut self, status: &PawnStatus, difference: i16) {
+        let tile = (status.pos_x as u16, status.pos_y as u16);
+        let damage_type = self.recent_damage_types.iter()
+            .position(|(position, _, _)| *position == tile)
+            .map(|index| self.recent_damage_types.remove(index).1);
+
+        let (text, color) = if difference > 0 {
+            (format!("+{}", difference), Self::damage_color(Some(&DamageTypeEnum::HEAL)))
+        } else {
+            (difference.to_string(), Self::damage_color(damage_type.as_ref()))
+        };
+        let position = Vec2::new((status.pos_x * SPRITE_SIZE) as f32 + 8., (status.pos_y * SPRITE_SIZE) as f32);
+        self.floating_numbers.push((position, text, color, Instant::now()));
+    }
+
+    fn damage_color(damage_type: Option<&DamageTypeEnum>) -> Color {
+        match damage_type {
+            Some(DamageTypeEnum::PIERCING) => Color::new(0.9, 0.9, 0.9, 1.),
+            Some(DamageTypeEnum::SLASHING) => Color::new(1., 0.3, 0.3, 1.),
+            Some(DamageTypeEnum::BLUNT) => Color::new(1., 0.6, 0.2, 1.),
+            Some(DamageTypeEnum::ELECTRIC) => Color::new(1., 1., 0.3, 1.),
+            Some(DamageTypeEnum::FIRE) => Color::new(1., 0.45, 0., 1.),
+            Some(DamageTypeEnum::ICE) => Color::new(0.5, 0.85, 1., 1.),
+            Some(DamageTypeEnum::HEAL) => Color::new(0.3, 1., 0.3, 1.),
+            Some(DamageTypeEnum::POISON) => Color::new(0.6, 0.9, 0.1, 1.),
+            None => Color::new(1., 0.2, 0.2, 1.),
+        }
+    }
+
+    /// Dead pawns fade until only their corpse is left.
+    fn sprite_alpha(&self, sprite: &Sprite) -> f32 {
+        let statuses = self.pawn_statuses.iter()
+            .filter(|s| s.pos_x == sprite.pos_x && s.pos_y == sprite.pos_y)
+            .collect::<Vec<&PawnStatus>>();
+        if statuses.is_empty() || statuses.iter().any(|s| !s.is_dead()) {
+            return 1.;
+        }
+
+        let faded = statuses.iter()
+            .filter_map(|s| self.deaths.get(&s.id))
+            .map(|died_at| (died_at.elapsed().as_secs_f32() / DEATH_FADE_DURATION.as_secs_f32()).min(1.))
+            .fold(1f32, f32::min);
+        1. - (1. - CORPSE_ALPHA) * faded
+    }
+
+    fn draw_movables(&self, canvas: &mut Canvas) {
+        let movables = self.sprites.iter().filter(|s| s.layer == Layer::MOVABLES);
+        for (sprite, mesh) in movables.zip(self.sprites_movables.iter()) {
+            if self.is_hidden(sprite.pos_x, sprite.pos_y) {
+                continue;
+            }
+            self.registry.draw(canvas, mesh.0, mesh.1.color(Color::new(1., 1., 1., self.sprite_alpha(sprite))));
+        }
+    }
+
+    fn draw_objects(&self, canvas: &mut Canvas) {
+        let objects = self.sprites.iter().filter(|s| s.layer == Layer::OBJECTS);
+        for (sprite, mesh) in objects.zip(self.sprites_objects.iter()) {
+            if !self.is_hidden(sprite.pos_x, sprite.pos_y) {
+                self.registry.draw(canvas, mesh.0, mesh.1);
+            }
+        }
+    }
+
+    /// Out of the sight of the seated player in hot-seat games, what stands there is not drawn.
+    fn is_hidden(&self, x: i32, y: i32) -> bool {
+        match &self.fog {
+            Some(fog) => fog.get(y as usize).and_then(|row| row.get(x as usize)) != Some(&FogEnum::VISIBLE),
+            None => false
+        }
+    }
+
+    /// Same as `is_hidden` for a position in world pixels.
+    fn is_hidden_at(&self, position: Vec2) -> bool {
+        self.is_hidden((position.x / SPRITE_SIZE as f32).floor() as i32, (position.y / SPRITE_SIZE as f32).floor() as i32)
+    }
+
+    /// Tiles never seen are black, the ones seen before are darkened.
+    fn draw_fog(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let fog = match &self.fog {
+            Some(fog) => fog,
+            None => return Ok(())
+        };
+        for (y, row) in fog.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let alpha = match tile {
+                    FogEnum::UNEXPLORED => 1.,
+                    FogEnum::EXPLORED => 0.6,
+                    FogEnum::VISIBLE => continue,
                 };
+                let rect = Rect::new((x as i32 * SPRITE_SIZE) as f32, (y as i32 * SPRITE_SIZE) as f32, SPRITE_SIZE as f32, SPRITE_SIZE as f32);
+                let mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), rect, Color::new(0., 0., 0., alpha))?;
+                canvas.draw(&mesh, DrawParam::new());
+            }
+        }
+        Ok(())
+    }
+
+    /// Everything is hidden until the next player says it is ready in the menu.
+    fn draw_hand_off(&mut self, ctx: &Context, canvas: &mut Canvas, player: u8) -> GameResult<()> {
+        let screen = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., WINDOW_SIZE.0, WINDOW_SIZE.1), Color::BLACK)?;
+        canvas.draw(&screen, DrawParam::new());
+        canvas.draw(&Text::new(format!("Player {}'s turn", player)),
+                    DrawParam::new()
+                        .dest(Vec2::new(WINDOW_SIZE.0 / 2. - 60., WINDOW_SIZE.1 / 2. - 20.))
+                        .color(Color::WHITE));
+        canvas.draw(&Text::new("Hand the machine over, then choose Ready"),
+                    DrawParam::new()
+                        .dest(Vec2::new(WINDOW_SIZE.0 / 2. - 150., WINDOW_SIZE.1 / 2. + 4.))
+                        .color(Color::new(0.7, 0.7, 0.7, 1.)));
+        if self.current_menu.len() > 0 {
+            let options = self.current_menu.clone();
+            self.draw_menu(ctx, canvas, MENU_ORIGIN.0, MENU_ORIGIN.1, options)?;
+        }
+        Ok(())
+    }
+
+    /// Life and mana bars over living pawns, a ring around the one playing and the floating numbers.
+    fn draw_pawn_overlays(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        for status in self.pawn_statuses.iter().filter(|s| !s.is_dead() && !self.is_hidden(s.pos_x, s.pos_y)) {
+            let (x, y) = ((status.pos_x * SPRITE_SIZE) as f32, (status.pos_y * SPRITE_SIZE) as f32);
+
+            if status.id == self.current_player_id {
+                let center = Vec2::new(x + SPRITE_SIZE as f32 / 2., y + SPRITE_SIZE as f32 / 2.);
+                let ring = Mesh::new_circle(ctx, DrawMode::stroke(2.), center, SPRITE_SIZE as f32 / 2. + 1., 0.5, Color::YELLOW)?;
+                canvas.draw(&ring, DrawParam::new());
+            }
 
-                self.particles.push((attack_particle.0, attack_particle.1, Instant::now(), damage_type));
+            let mut bars = vec![(status.life, status.max_life, Color::new(0.2, 0.85, 0.2, 1.))];
+            if status.max_mana > 0 {
+                bars.push((status.mana, status.max_mana, Color::new(0.25, 0.45, 1., 1.)));
             }
+            for (index, (value, max, color)) in bars.into_iter().enumerate() {
+                let ratio = value as f32 / max.max(value).max(1) as f32;
+                let bar_y = (y - 7. + index as f32 * 3.).max(0.);
+                let background = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(x + 2., bar_y, 28., 3.), Color::new(0.2, 0.2, 0.2, 0.8))?;
+                canvas.draw(&background, DrawParam::new());
+                if ratio > 0. {
+                    let bar = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(x + 2., bar_y, 28. * ratio, 3.), color)?;
+                    canvas.draw(&bar, DrawParam::new());
+                }
+            }
+        }
+
+        for (position, text, color, started_at) in self.floating_numbers.iter().filter(|(position, ..)| !self.is_hidden_at(*position)) {
+            let progress = started_at.elapsed().as_secs_f32() / FLOATING_NUMBER_DURATION.as_secs_f32();
+            let color = Color::new(color.r, color.g, color.b, 1. - progress);
+            canvas.draw(&Text::new(text.as_str()),
+                        DrawParam::new()
+                            .dest(Vec2::new(position.x, position.y - 20. * progress))
+                            .color(color));
         }
+        Ok(())
+    }
+
+    fn receive_projectile(&mut self) {
+        if let Ok(response) = self.receivers.get("projectile").unwrap().try_recv() {
+            if let Ok((from, to, name)) = bincode::deserialize::<((u16, u16), (u16, u16), String)>(response.content.as_slice()) {
+                self.effects.projectile(name.as_str(), Self::tile_center(from), Self::tile_center(to));
+            }
+        }
+    }
+
+    /// World pixels of the center of a tile, where effects are played.
+    fn tile_center(tile: (u16, u16)) -> Vec2 {
+        Vec2::new(tile.0 as f32 + 0.5, tile.1 as f32 + 0.5) * SPRITE_SIZE as f32
     }
 
-    fn get_sprites(&mut self, sprites: &Vec<Sprite>, layer: Layer) -> Vec<(Image, DrawParam)> {
+    fn change_room(&mut self) {
+        if let Ok(response) = self.receivers.get("change_room").unwrap().try_recv() {
+            if let Ok((_from, _to, place_name)) = bincode::deserialize::<(u8, u8, String)>(response.content.as_slice()) {
+                //Nothing from the left room should stay on screen
+                self.clear_after_turn();
+                self.effects.clear();
+                self.active_modal = None;
+                self.room_transition = Some((Instant::now(), place_name));
+            }
+        }
+    }
+
+    /// What the playing pawn has left this turn, at the bottom of the window.
+    fn draw_turn_budget(&self, ctx: &Context, canvas: &mut Canvas) {
+        if let Some((pawn_id, budget)) = &self.turn_budget {
+            if *pawn_id == self.current_player_id {
+                let (_, height) = ctx.gfx.drawable_size();
+                canvas.draw(&Text::new(budget.to_string()),
+                            DrawParam::new()
+                                .dest(Vec2::new(10., height - 24.))
+                                .color(Color::WHITE));
+            }
+        }
+    }
+
+    /// Name, life and mana bars of each party member, the one playing is framed.
+    fn draw_party(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        if self.party.is_empty() {
+            return Ok(());
+        }
+        let (x, y, w, h) = PARTY_PANEL;
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(x, y, w, h), Color::new(0., 0., 0., 0.6))?;
+        canvas.draw(&background, DrawParam::new());
+
+        for (index, member) in self.party.iter().enumerate() {
+            let line_y = y + 4. + index as f32 * PARTY_LINE_HEIGHT;
+            if line_y + PARTY_LINE_HEIGHT > y + h {
+                break;
+            }
+            if member.current {
+                let frame = Mesh::new_rectangle(ctx, DrawMode::stroke(1.), Rect::new(x + 2., line_y - 2., w - 4., PARTY_LINE_HEIGHT - 2.), Color::new(1., 0.85, 0.3, 1.))?;
+                canvas.draw(&frame, DrawParam::new());
+            }
+            let color = if member.life == 0 { Color::new(0.5, 0.5, 0.5, 1.) } else { Color::WHITE };
+            canvas.draw(&Text::new(member.name.as_str()),
+                        DrawParam::new()
+                            .dest(Vec2::new(x + 6., line_y))
+                            .color(color));
+
+            let bars = [(member.life, member.max_life, Color::new(0.8, 0.1, 0.1, 1.)),
+                (member.mana, member.max_mana, Color::new(0.2, 0.3, 0.9, 1.))];
+            for (bar, (value, max, bar_color)) in bars.iter().enumerate() {
+                let ratio = if *max == 0 { 0. } else { *value as f32 / *max as f32 };
+                let bar_rect = Rect::new(x + 6., line_y + 18. + bar as f32 * 6., (w - 12.) * ratio, 4.);
+                if bar_rect.w > 0. {
+                    let mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), bar_rect, *bar_color)?;
+                    canvas.draw(&mesh, DrawParam::new());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Portrait and line of the speaker along the bottom of the map, the answers are in the menu.
+    fn draw_dialogue(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let line = match &self.dialogue {
+            Some(line) => line,
+            None => return Ok(())
+        };
+        let viewport = Self::map_viewport();
+        let panel = Rect::new(viewport.x + 8., viewport.bottom() - DIALOGUE_BOX_HEIGHT - 8., viewport.w - 16., DIALOGUE_BOX_HEIGHT);
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), panel, Color::new(0., 0., 0., 0.8))?;
+        canvas.draw(&background, DrawParam::new());
+        let border = Mesh::new_rectangle(ctx, DrawMode::stroke(2.), panel, Color::new(0.6, 1., 0.6, 1.))?;
+        canvas.draw(&border, DrawParam::new());
+
+        let portrait = self.registry.handle(line.portrait.as_str());
+        let (width, height) = self.registry.size(portrait);
+        self.registry.draw(canvas,
+                           portrait,
+                           DrawParam::new()
+                               .dest(Vec2::new(panel.x + 16., panel.y + 16.))
+                               .scale(Vec2::new(PORTRAIT_SIZE / width as f32, PORTRAIT_SIZE / height as f32)));
+
+        let text_x = panel.x + PORTRAIT_SIZE + 32.;
+        canvas.draw(&Text::new(line.speaker.as_str()),
+                    DrawParam::new()
+                        .dest(Vec2::new(text_x, panel.y + 12.))
+                        .color(Self::log_color(&LogCategoryEnum::DIALOGUE)));
+        let mut text = Text::new(line.text.as_str());
+        text.set_bounds(Vec2::new(panel.right() - text_x - 12., DIALOGUE_BOX_HEIGHT - 36.));
+        canvas.draw(&text,
+                    DrawParam::new()
+                        .dest(Vec2::new(text_x, panel.y + 32.))
+                        .color(Color::WHITE));
+        Ok(())
+    }
+
+    /// Quests over the map until closed, in progress ones first.
+    fn draw_journal(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let entries = match &self.journal {
+            Some(entries) => entries,
+            None => return Ok(())
+        };
+        let viewport = Self::map_viewport();
+        let panel = Rect::new(viewport.x + 20., viewport.y + 20., viewport.w - 40., viewport.h - 40.);
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), panel, Color::new(0., 0., 0., 0.85))?;
+        canvas.draw(&background, DrawParam::new());
+
+        canvas.draw(&Text::new("Journal (Escape to close)"),
+                    DrawParam::new()
+                        .dest(Vec2::new(panel.x + 12., panel.y + 12.))
+                        .color(Color::WHITE));
+        if entries.is_empty() {
+            canvas.draw(&Text::new("No quest yet"),
+                        DrawParam::new()
+                            .dest(Vec2::new(panel.x + 12., panel.y + 40.))
+                            .color(Color::WHITE));
+        }
+
+        let mut y = panel.y + 40.;
+        for entry in entries.iter() {
+            let color = if entry.completed { Color::new(0.5, 0.5, 0.5, 1.) } else { Color::WHITE };
+            let lines = entry.to_string();
+            for (index, line) in lines.lines().enumerate() {
+                if y > panel.bottom() - LOG_LINE_HEIGHT {
+                    return Ok(());
+                }
+                canvas.draw(&Text::new(line),
+                            DrawParam::new()
+                                .dest(Vec2::new(panel.x + 12., y))
+                                .color(if index == 0 && !entry.completed { Self::log_color(&LogCategoryEnum::ATTACK) } else { color }));
+                y += LOG_LINE_HEIGHT;
+            }
+            y += LOG_LINE_HEIGHT / 2.;
+        }
+        Ok(())
+    }
+
+    fn log_panel(&self) -> Rect {
+        Rect::new(LOG_ORIGIN.0, LOG_ORIGIN.1, LOG_WIDTH, LOG_LINE_HEIGHT * (LOG_LINES + 1) as f32)
+    }
+
+    fn log_filter_rect(index: usize) -> Rect {
+        Rect::new(LOG_ORIGIN.0 + index as f32 * (LOG_FILTER_WIDTH + 4.), LOG_ORIGIN.1, LOG_FILTER_WIDTH, LOG_LINE_HEIGHT - 2.)
+    }
+
+    fn log_filter_at(&self, x: f32, y: f32) -> Option<LogCategoryEnum> {
+        LogCategoryEnum::all().into_iter()
+            .enumerate()
+            .find(|(index, _)| Self::log_filter_rect(*index).contains(Vec2::new(x, y)))
+            .map(|(_, category)| category)
+    }
+
+    fn log_color(category: &LogCategoryEnum) -> Color {
+        match category {
+            LogCategoryEnum::ATTACK => Color::new(1., 0.85, 0.3, 1.),
+            LogCategoryEnum::DAMAGE => Color::new(1., 0.4, 0.4, 1.),
+            LogCategoryEnum::MOVEMENT => Color::new(0.5, 0.8, 1., 1.),
+            LogCategoryEnum::DIALOGUE => Color::new(0.6, 1., 0.6, 1.),
+            LogCategoryEnum::SYSTEM => Color::new(0.85, 0.85, 0.85, 1.),
+        }
+    }
+
+    /// Filter buttons on top, then the last lines kept by the filters.
+    fn draw_combat_log(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), self.log_panel(), Color::new(0., 0., 0., 0.6))?;
+        canvas.draw(&background, DrawParam::new());
+
+        for (index, category) in LogCategoryEnum::all().iter().enumerate() {
+            let rect = Self::log_filter_rect(index);
+            let color = if self.combat_log.is_shown(category) { Self::log_color(category) } else { Color::new(0.4, 0.4, 0.4, 1.) };
+            let border = Mesh::new_rectangle(ctx, DrawMode::stroke(1.), rect, color)?;
+            canvas.draw(&border, DrawParam::new());
+            canvas.draw(&Text::new(category.label()),
+                        DrawParam::new()
+                            .dest(Vec2::new(rect.x + 8., rect.y))
+                            .color(color));
+        }
+
+        for (line, entry) in self.combat_log.visible(LOG_LINES).iter().enumerate() {
+            canvas.draw(&Text::new(entry.text.as_str()),
+                        DrawParam::new()
+                            .dest(Vec2::new(LOG_ORIGIN.0 + 4., LOG_ORIGIN.1 + (line + 1) as f32 * LOG_LINE_HEIGHT))
+                            .color(Self::log_color(&entry.category)));
+        }
+        Ok(())
+    }
+
+    fn draw_room_transition(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        if let Some((started_at, place_name)) = &self.room_transition {
+            let alpha = 1. - started_at.elapsed().as_secs_f32() / ROOM_TRANSITION_DURATION.as_secs_f32();
+            let (width, height) = ctx.gfx.drawable_size();
+            let fade = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(0., 0., width, height), Color::new(0., 0., 0., alpha))?;
+            canvas.draw(&fade, DrawParam::new());
+            canvas.draw(&Text::new(place_name.as_str()),
+                        DrawParam::new()
+                            .dest(Vec2::new(width / 2. - 50., height / 2.))
+                            .color(Color::new(1., 1., 1., alpha)));
+        }
+        Ok(())
+    }
+
+    fn receive_inventory(&mut self) {
+        if let Ok(response) = self.receivers.get("inventory").unwrap().try_recv() {
+            if let Ok(view) = bincode::deserialize::<InventoryView>(response.content.as_slice()) {
+                self.inventory = Some(view);
+            }
+        }
+    }
+
+    fn send_inventory_command(&self, command: InventoryCommandEnum) {
+        self.senders.get("inventory_command").unwrap().send(MessageContent {
+            topic: "inventory_command".to_string(),
+            content: bincode::serialize(&command).unwrap(),
+        }).unwrap();
+    }
+
+    fn inventory_slot_rects(&self) -> Vec<Rect> {
+        self.inventory.as_ref()
+            .map(|view| (0..view.slots.len())
+                .map(|i| Rect::new(INVENTORY_ORIGIN.0 + 10., INVENTORY_ORIGIN.1 + 30. + i as f32 * INVENTORY_CELL, 130., SPRITE_SIZE as f32))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    fn inventory_item_rects(&self) -> Vec<Rect> {
+        self.inventory.as_ref()
+            .map(|view| (0..view.entries.len())
+                .map(|i| Rect::new(INVENTORY_ORIGIN.0 + 150. + (i % INVENTORY_COLUMNS) as f32 * INVENTORY_CELL,
+                                   INVENTORY_ORIGIN.1 + 30. + (i / INVENTORY_COLUMNS) as f32 * INVENTORY_CELL,
+                                   SPRITE_SIZE as f32, SPRITE_SIZE as f32))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Sort, filter and close buttons at the bottom of the panel.
+    fn inventory_control_rects() -> Vec<Rect> {
+        (0..3)
+            .map(|i| Rect::new(INVENTORY_ORIGIN.0 + 10. + i as f32 * 110., INVENTORY_ORIGIN.1 + 260., 100., 20.))
+            .collect()
+    }
+
+    fn inventory_pressed(&mut self, x: f32, y: f32) {
+        self.dragged_item = self.inventory_item_rects().iter()
+            .position(|r| r.contains([x, y]));
+    }
+
+    fn inventory_released(&mut self, x: f32, y: f32) {
+        let view = match self.inventory.clone() {
+            Some(view) => view,
+            None => return
+        };
+        let slot = self.inventory_slot_rects().iter().position(|r| r.contains([x, y]));
+
+        //An item dropped on a slot is equipped there
+        if let Some(dragged) = self.dragged_item.take() {
+            if let (Some(entry), Some(slot)) = (view.entries.get(dragged), slot) {
+                self.send_inventory_command(InventoryCommandEnum::EQUIP(entry.index, view.slots[slot].0.clone()));
+            }
+            return;
+        }
+
+        if let Some(slot) = slot {
+            if view.slots[slot].1.is_some() {
+                self.send_inventory_command(InventoryCommandEnum::UNEQUIP(view.slots[slot].0.clone()));
+            }
+            return;
+        }
+
+        match Self::inventory_control_rects().iter().position(|r| r.contains([x, y])) {
+            Some(0) => self.send_inventory_command(InventoryCommandEnum::SORT(view.sort.next())),
+            Some(1) => self.send_inventory_command(InventoryCommandEnum::FILTER(view.filter.next())),
+            Some(_) => {
+                self.send_inventory_command(InventoryCommandEnum::CLOSE);
+                self.inventory = None;
+            }
+            None => ()
+        }
+    }
+
+    fn draw_inventory(&self, ctx: &Context, canvas: &mut Canvas) -> GameResult<()> {
+        let view = match &self.inventory {
+            Some(view) => view,
+            None => return Ok(())
+        };
+
+        let panel = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(INVENTORY_ORIGIN.0, INVENTORY_ORIGIN.1, 370., 290.), Color::new(0.1, 0.1, 0.1, 0.9))?;
+        canvas.draw(&panel, DrawParam::new());
+        let load_color = if view.weight > view.capacity { Color::RED } else { Color::WHITE };
+        canvas.draw(&Text::new(format!("{} - {}/{} - {} gold", view.owner, view.weight, view.capacity, view.gold)),
+                    DrawParam::new()
+                        .dest(Vec2::new(INVENTORY_ORIGIN.0 + 10., INVENTORY_ORIGIN.1 + 8.))
+                        .color(load_color));
+
+        for ((part, item), rect) in view.slots.iter().zip(self.inventory_slot_rects()) {
+            let outline = Mesh::new_rectangle(ctx, DrawMode::stroke(1.), rect, Color::WHITE)?;
+            canvas.draw(&outline, DrawParam::new());
+            canvas.draw(&Text::new(format!("{}\n{}", part, item.clone().unwrap_or_default())),
+                        DrawParam::new().dest(Vec2::new(rect.x + 2., rect.y + 1.)).color(Color::WHITE));
+        }
+
+        for (i, (entry, rect)) in view.entries.iter().zip(self.inventory_item_rects()).enumerate() {
+            if self.dragged_item != Some(i) {
+                self.registry.draw(canvas, self.registry.handle(entry.icon.as_str()), DrawParam::new().dest(Vec2::new(rect.x, rect.y)));
+            }
+            if entry.equipped {
+                let outline = Mesh::new_rectangle(ctx, DrawMode::stroke(2.), rect, Color::YELLOW)?;
+                canvas.draw(&outline, DrawParam::new());
+            }
+            if entry.quantity > 1 {
+                canvas.draw(&Text::new(entry.quantity.to_string()),
+                            DrawParam::new().dest(Vec2::new(rect.x + 20., rect.y + 18.)).color(Color::WHITE));
+            }
+        }
+
+        let controls = [format!("Sort: {:?}", view.sort), format!("Show: {:?}", view.filter), "Close".to_string()];
+        for (label, rect) in controls.iter().zip(Self::inventory_control_rects()) {
+            let outline = Mesh::new_rectangle(ctx, DrawMode::stroke(1.), rect, Color::WHITE)?;
+            canvas.draw(&outline, DrawParam::new());
+            canvas.draw(&Text::new(label.as_str()), DrawParam::new().dest(Vec2::new(rect.x + 4., rect.y + 2.)).color(Color::WHITE));
+        }
+
+        let mouse = Vec2::new(self.mouse.pos_x, self.mouse.pos_y);
+        if let Some(entry) = self.dragged_item.and_then(|i| view.entries.get(i)) {
+            self.registry.draw(canvas, self.registry.handle(entry.icon.as_str()), DrawParam::new().dest(mouse - Vec2::new(16., 16.)));
+
+            //Hovering a slot previews what equipping there would change
+            if let Some(slot) = self.inventory_slot_rects().iter().position(|r| r.contains(mouse)) {
+                let part = &view.slots[slot].0;
+                let preview = entry.previews.iter()
+                    .find(|(p, _)| p == part)
+                    .map(|(_, preview)| preview.clone())
+                    .unwrap_or_else(|| format!("{} cannot be equipped on {}", entry.name, part));
+                Self::draw_tooltip(ctx, canvas, mouse, preview.as_str())?;
+            }
+        } else if let Some(i) = self.inventory_item_rects().iter().position(|r| r.contains(mouse)) {
+            Self::draw_tooltip(ctx, canvas, mouse, view.entries.get(i).unwrap().details.as_str())?;
+        }
+        Ok(())
+    }
+
+    fn draw_tooltip(ctx: &Context, canvas: &mut Canvas, mouse: Vec2, content: &str) -> GameResult<()> {
+        let tooltip = Text::new(content);
+        let size = tooltip.measure(ctx)?;
+        let background = Mesh::new_rectangle(ctx, DrawMode::fill(), Rect::new(mouse.x + 12., mouse.y, size.x + 8., size.y + 8.), Color::BLACK)?;
+        canvas.draw(&background, DrawParam::new());
+        canvas.draw(&tooltip, DrawParam::new().dest(Vec2::new(mouse.x + 16., mouse.y + 4.)).color(Color::WHITE));
+        Ok(())
+    }
+
+    fn get_sprites(&mut self, sprites: &Vec<Sprite>, layer: Layer) -> Vec<(SpriteHandle, DrawParam)> {
         sprites.iter()
             .filter(|s| s.layer == layer)
-            .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.sprites_textures))
-            .collect::<Vec<(Image, DrawParam)>>()
+            .map(|e| e.create_drawable(SPRITE_SIZE as f32, &self.registry))
+            .collect::<Vec<(SpriteHandle, DrawParam)>>()
     }
 }
 
 
 impl event::EventHandler<ggez::GameError> for MainState {
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) -> Result<(), GameError> {
+        let pointer = Vec2::new(self.mouse.pos_x, self.mouse.pos_y);
+        if self.log_panel().contains(pointer) {
+            self.combat_log.scroll(y.signum() as i32);
+        } else if Self::map_viewport().contains(pointer) && self.inventory.is_none() {
+            self.camera.zoom(if y > 0. { ZOOM_STEP } else { 1. / ZOOM_STEP });
+        }
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, GameError> {
+        if let Err(error) = self.combat_log.export(LOG_EXPORT_FILE) {
+            println!("Cannot export the combat log : {}", error);
+        }
+        Ok(false)
+    }
+
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> Result<(), GameError> {
+        if button == MouseButton::Left && self.inventory.is_some() {
+            self.inventory_pressed(x, y);
+        }
+        Ok(())
+    }
+
     fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> Result<(), GameError> {
+        if button == MouseButton::Right {
+            self.cancel();
+            return Ok(());
+        }
         if button != MouseButton::Left {
             return Ok(());
         }
 
-        //If some modal exist, we close it on click
-        if let Some(a_m) = self.active_modal.clone() {
-            self.senders.get("info").unwrap().send(MessageContent {
-                topic: "info".to_string(),
-                content: vec![],
-            }).unwrap();
+        if let Some(category) = self.log_filter_at(x, y) {
+            self.combat_log.toggle(category);
+            return Ok(());
+        }
 
-            self.active_modal = None;
+        //The inventory panel catches every click while it is open
+        if self.inventory.is_some() {
+            self.inventory_released(x, y);
+            return Ok(());
+        }
+
+        //If some modal exist, we close it on click
+        if self.active_modal.is_some() {
+            self.close_modal();
             return Ok(());
         }
 
@@ -320,48 +1207,92 @@ impl event::EventHandler<ggez::GameError> for MainState {
                     b.y < y && b.y + b.h > y);
 
             if let Some(menu_option) = self.selected_menu_option {
-                self.senders.get("select_response").unwrap().send(MessageContent {
-                    topic: "select_response".to_string(),
-                    content: bincode::serialize(&menu_option).unwrap(),
-                }).unwrap();
+                self.select_menu_option(menu_option);
             }
             return Ok(());
         }
 
-        let sprites_selected = self.sprites.iter()
-            .filter(|s| s.pos_y * SPRITE_SIZE < y as i32 && s.pos_y * SPRITE_SIZE + SPRITE_SIZE > y as i32 &&
-                s.pos_x * SPRITE_SIZE < x as i32 && s.pos_x * SPRITE_SIZE + SPRITE_SIZE > x as i32)
-            .map(|e| e.clone())
-            .collect::<Vec<Sprite>>();
+        self.select_at(x, y);
+        Ok(())
+    }
 
-        //We check if user has clicked on something interactable and if interactions are availables
-        if !sprites_selected.is_empty() {
-            self.mouse_hovering_characterisation(x, y, sprites_selected);
+    fn key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, _repeated: bool) -> Result<(), GameError> {
+        let command = match input.keycode.and_then(|key| self.bindings.command(key)) {
+            Some(command) => command,
+            None => return Ok(())
+        };
+
+        //The camera can be moved whatever is waited for
+        if self.camera_command(&command) {
+            return Ok(());
         }
 
+        if command == KeyCommandEnum::CANCEL {
+            self.cancel();
+            return Ok(());
+        }
 
+        if self.inventory.is_some() {
+            return Ok(());
+        }
+
+        if self.active_modal.is_some() {
+            if command == KeyCommandEnum::CONFIRM {
+                self.close_modal();
+            }
+            return Ok(());
+        }
+
+        if self.menu_pending {
+            let options = self.current_menu.len().max(1);
+            match command {
+                KeyCommandEnum::UP => self.menu_cursor = (self.menu_cursor + options - 1) % options,
+                KeyCommandEnum::DOWN => self.menu_cursor = (self.menu_cursor + 1) % options,
+                KeyCommandEnum::CONFIRM => self.select_menu_option(self.menu_cursor),
+                //Hotkeys only pick actions from the action menu
+                KeyCommandEnum::ACTION(action) if self.current_menu == Actions::vec_string() => self.select_menu_option(action as usize),
+                _ => ()
+            }
+            return Ok(());
+        }
+
+        if self.awaiting_target {
+            let (x, y) = self.tile_cursor;
+            match command {
+                KeyCommandEnum::UP => self.tile_cursor = (x, (y - 1).max(0)),
+                KeyCommandEnum::DOWN => self.tile_cursor = (x, y + 1),
+                KeyCommandEnum::LEFT => self.tile_cursor = ((x - 1).max(0), y),
+                KeyCommandEnum::RIGHT => self.tile_cursor = (x + 1, y),
+                KeyCommandEnum::CONFIRM => {
+                    let center = self.tile_center_on_screen(self.tile_cursor);
+                    self.select_at(center.x, center.y);
+                }
+                _ => ()
+            }
+        }
         Ok(())
     }
 
     fn update(&mut self, ctx: &mut Context) -> GameResult {
         let point2 = ctx.mouse.position();
         self.set_gameplay_state();
+        self.receive_targetable();
 
         if let Ok(id_content) = self.receivers.get("current_player").unwrap().try_recv() {
             self.current_player_id = bincode::deserialize(id_content.content.as_slice()).unwrap();
         }
 
+        //The log keeps its history, clearing only ends the current prompt
         if let Some(clear_container) = self.receivers.get("clear") {
-            if let Ok(clear) = clear_container.try_recv() {
-                self.stdout.clear();
-            }
+            while clear_container.try_recv().is_ok() {}
         }
 
         //Get stdout
         if let Some(stdout_container) = self.receivers.get("stdout") {
-            if let Ok(text) = stdout_container.try_recv() {
-                let out = format!("{}\n{}", self.stdout, from_utf8(text.content.as_slice()).unwrap());
-                self.stdout = out;
+            while let Ok(text) = stdout_container.try_recv() {
+                if let Ok(entry) = bincode::deserialize::<LogEntry>(text.content.as_slice()) {
+                    self.combat_log.push(entry);
+                }
             }
         }
 
@@ -373,6 +1304,10 @@ impl event::EventHandler<ggez::GameError> for MainState {
                     .split(":")
                     .map(|el| el.to_string())
                     .collect();
+                self.menu_cursor = 0;
+                self.menu_pending = true;
+                //A menu means the game thread no longer waits for a tile
+                self.awaiting_target = false;
             }
         }
 
@@ -385,13 +1320,41 @@ impl event::EventHandler<ggez::GameError> for MainState {
 
                 self.sprites_background = self.get_sprites(&sprites, Layer::BACKGROUND);
 
+                self.sprites_objects = self.get_sprites(&sprites, Layer::OBJECTS);
+
                 self.sprites_ui = self.get_sprites(&sprites, Layer::UI);
 
+                let (width, height) = sprites.iter()
+                    .fold((0, 0), |(width, height), s| (width.max(s.pos_x + 1), height.max(s.pos_y + 1)));
+                self.room_size = Vec2::new((width * SPRITE_SIZE) as f32, (height * SPRITE_SIZE) as f32);
+
                 self.sprites = sprites
             }
         }
 
         self.show_damages();
+        self.change_room();
+        self.receive_inventory();
+        self.receive_projectile();
+        self.receive_pawn_status();
+        if let Ok(response) = self.receivers.get("dialogue").unwrap().try_recv() {
+            self.dialogue = bincode::deserialize(response.content.as_slice()).unwrap_or(None);
+        }
+        if let Ok(response) = self.receivers.get("journal").unwrap().try_recv() {
+            self.journal = bincode::deserialize(response.content.as_slice()).ok();
+        }
+        if let Ok(response) = self.receivers.get("hand_off").unwrap().try_recv() {
+            self.hand_off = bincode::deserialize(response.content.as_slice()).unwrap_or(None);
+        }
+        if let Ok(response) = self.receivers.get("fog").unwrap().try_recv() {
+            self.fog = bincode::deserialize(response.content.as_slice()).ok();
+        }
+        if let Ok(response) = self.receivers.get("party").unwrap().try_recv() {
+            self.party = bincode::deserialize(response.content.as_slice()).unwrap_or_default();
+        }
+        if let Ok(response) = self.receivers.get("turn_budget").unwrap().try_recv() {
+            self.turn_budget = bincode::deserialize(response.content.as_slice()).ok();
+        }
 
 
         if let Some(state) = self.gameplay_state.clone() {
@@ -402,6 +1365,15 @@ impl event::EventHandler<ggez::GameError> for MainState {
                 Actions::WATCH => self.wait_for_watch(),
                 Actions::USE => {}
                 Actions::EQUIP => {}
+                Actions::DISARM => {}
+                Actions::PICK_UP => {}
+                Actions::DROP => {}
+                Actions::LOOT => {}
+                Actions::RELOAD => {}
+                Actions::TALK => {}
+                Actions::JOURNAL => {}
+                Actions::SWITCH => {}
+                Actions::END_TURN => {}
             }
 
 
@@ -415,9 +1387,17 @@ impl event::EventHandler<ggez::GameError> for MainState {
         }
 
         self.mouse.set_pointer_position(point2.x, point2.y);
-        self.animator.advance(1., ctx.time.delta().as_secs_f64());
-
-        self.particles.retain(|p: &(Image, DrawParam, Instant, u8)| p.2.elapsed() < Duration::new(self.animation_duration as u64, 0));
+        self.edge_scroll(ctx.time.delta().as_secs_f32());
+        self.camera.update(self.followed_position(), self.room_size);
+        self.effects.update();
+
+        self.floating_numbers.retain(|(_, _, _, started_at)| started_at.elapsed() < FLOATING_NUMBER_DURATION);
+        self.recent_damage_types.retain(|(_, _, received_at)| received_at.elapsed() < DAMAGE_TYPE_MEMORY);
+        if let Some((started_at, _)) = &self.room_transition {
+            if started_at.elapsed() > ROOM_TRANSITION_DURATION {
+                self.room_transition = None;
+            }
+        }
 
         Ok(())
     }
@@ -430,33 +1410,56 @@ impl event::EventHandler<ggez::GameError> for MainState {
             graphics::Color::from([0., 0., 0., 1.0]),
         );
 
+        //The room is drawn in world pixels through the camera, clipped to the map viewport
+        let viewport = Self::map_viewport();
+        canvas.set_screen_coordinates(self.camera.screen_coordinates(&viewport, WINDOW_SIZE));
+        canvas.set_scissor_rect(viewport)?;
+
         for mesh in &self.sprites_background {
-            canvas.draw(&mesh.0, mesh.1);
-        }
-        for mesh in &self.sprites_movables {
-            canvas.draw(&mesh.0, mesh.1);
-        }
-        for particle in &self.particles {
-            let mut local_clone = particle.clone();
-            canvas.draw(&particle.0, local_clone.1
-                .src(self.animator.get_currenct_rect(local_clone.3 as usize)));
+            self.registry.draw(&mut canvas, mesh.0, mesh.1);
         }
+        self.draw_objects(&mut canvas);
+        self.draw_movables(&mut canvas);
+        self.draw_pawn_overlays(ctx, &mut canvas)?;
+        self.effects.draw(&mut canvas, &self.registry, |position| self.is_hidden_at(position));
         for mesh in &self.sprites_ui {
-            canvas.draw(&mesh.0, mesh.1);
+            self.registry.draw(&mut canvas, mesh.0, mesh.1);
+        }
+        self.draw_fog(ctx, &mut canvas)?;
+        self.draw_tile_cursor(ctx, &mut canvas)?;
+
+        canvas.set_default_scissor_rect();
+        canvas.set_screen_coordinates(Rect::new(0., 0., WINDOW_SIZE.0, WINDOW_SIZE.1));
+
+        if let Some(player) = self.hand_off {
+            self.draw_hand_off(ctx, &mut canvas, player)?;
+            canvas.finish(ctx)?;
+            return Ok(());
         }
 
         if self.current_menu.len() > 0 {
             let options = self.current_menu.clone();
-            self.draw_menu(&mut canvas, 0., 200.0, options)?;
+            self.draw_menu(ctx, &mut canvas, MENU_ORIGIN.0, MENU_ORIGIN.1, options)?;
         }
 
-        canvas.draw(&Text::new(self.stdout.clone()),
-                    graphics::DrawParam::from(Vec2::new(200.0, 0.0)).color(Color::WHITE).scale(Vec2::new(1., 1.)));
+        self.draw_combat_log(ctx, &mut canvas)?;
 
         if let Some((x, y, content)) = self.active_modal.clone() {
             self.draw_modal(&mut canvas, x, y, content.as_str())?;
         }
 
+        self.draw_turn_budget(ctx, &mut canvas);
+
+        self.draw_party(ctx, &mut canvas)?;
+
+        self.draw_dialogue(ctx, &mut canvas)?;
+
+        self.draw_journal(ctx, &mut canvas)?;
+
+        self.draw_inventory(ctx, &mut canvas)?;
+
+        self.draw_room_transition(ctx, &mut canvas)?;
+
         canvas.draw(&self.mouse.get_mesh(&ctx), Vec2::new(0.0, 0.0));
 
 
@@ -467,7 +1470,7 @@ impl event::EventHandler<ggez::GameError> for MainState {
 
 pub fn init(receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>) -> GameResult {
     let cb = ggez::ContextBuilder::new("super simple", "ggez")
-        .window_mode(WindowMode::default().dimensions(800.0, 600.0))
+        .window_mode(WindowMode::default().dimensions(WINDOW_SIZE.0, WINDOW_SIZE.1))
         .window_setup(WindowSetup::default().samples(NumSamples::Four));
     let (mut ctx, event_loop) = cb.build()?;
 
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use std::time::Duration;
@@ -5,7 +6,9 @@ use console::Term;
 use dialoguer::Select;
 use dialoguer::theme::ColorfulTheme;
 use lazy_static::lazy_static;
+use crate::gui::combat_log::LogCategoryEnum;
 use crate::services::messaging::MessageContent;
+use crate::services::network::OWNER_TOPIC;
 
 pub struct Menu {
     STDOUT: Term,
@@ -13,6 +16,8 @@ pub struct Menu {
     selected_option: Receiver<MessageContent>,
     stdout: Sender<MessageContent>,
     clear: Sender<MessageContent>,
+    //Player answering the menus, 0 while the AI plays
+    owner: Cell<u8>,
 }
 
 impl Menu {
@@ -27,6 +32,7 @@ impl Menu {
             select_menu,
             stdout,
             clear,
+            owner: Cell::new(0),
         }
     }
 
@@ -57,10 +63,30 @@ impl Menu {
         }
     }
 
+    /// Player answering the next menus. It goes on the same channel as the menus so the server
+    /// always knows who a menu is for before it comes.
+    pub fn set_owner(&self, owner: u8) {
+        self.owner.set(owner);
+        self.select_menu.send(MessageContent {
+            topic: OWNER_TOPIC.to_string(),
+            content: bincode::serialize(&owner).unwrap(),
+        }).unwrap();
+    }
+
+    pub fn owner(&self) -> u8 {
+        self.owner.get()
+    }
+
     pub fn write_line(&self, out: &str) -> std::io::Result<()> {
+        self.log(LogCategoryEnum::SYSTEM, out)
+    }
+
+    /// Write a line to the combat log under a category.
+    pub fn log(&self, category: LogCategoryEnum, out: &str) -> std::io::Result<()> {
         #[cfg(not(feature = "graphical_mode"))]
         {
-            return self.STDOUT.write_line(out)?;
+            let _ = category;
+            return self.STDOUT.write_line(out);
         }
 
         #[cfg(feature = "graphical_mode")]
@@ -69,12 +95,19 @@ impl Menu {
 
             self.stdout.send(MessageContent {
                 topic: stdout_topic.to_string(),
-                content: out.as_bytes().to_vec(),
+                content: bincode::serialize(&crate::gui::combat_log::LogEntry { category, text: out.to_string() }).unwrap(),
             }).unwrap();
         }
         Ok(())
     }
 
+    /// Hide what was written so far from the console, the window hides its screen on its own.
+    pub fn clear_screen(&self) -> std::io::Result<()> {
+        #[cfg(not(feature = "graphical_mode"))]
+        self.STDOUT.clear_screen()?;
+        Ok(())
+    }
+
     pub fn clear_line(&self) -> std::io::Result<()> {
         #[cfg(not(feature = "graphical_mode"))]
         self.STDOUT.clear_line()?;
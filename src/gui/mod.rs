@@ -1,3 +1,4 @@
+pub mod combat_log;
 pub mod console;
 pub mod graphical;
-pub mod menu;
+pub mod menu;
\ No newline at end of file
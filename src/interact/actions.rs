@@ -1,5 +1,5 @@
 use std::cell::{Ref, RefCell};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Display, Formatter};
 use std::io::Error;
 use std::rc::Rc;
@@ -8,18 +8,33 @@ use std::thread::current;
 use console::Term;
 use rand::random;
 use serde::{Deserialize, Serialize};
-use crate::inventory::item::{DamageTypeEnum, Item, ItemAttackTypeEnum, PartToEquiEnum, Pocketable};
+use crate::inventory::inventory::{InventoryCommandEnum, InventoryFilterEnum, InventorySortEnum, InventoryView};
+use crate::inventory::item::{DamageTypeEnum, Item, PartToEquiEnum, Pocketable};
 use crate::ai;
 use crate::ai::ai::let_ai_or_human_play;
+use crate::pawn::faction::{ReputationSnapshot, FACTIONS};
+use crate::pawn::party::Party;
 use crate::pawn::pawn::{Pawn, Position};
 use crate::services::dice::RollDiceResult;
+use crate::services::rules::Rules;
 use crate::services::interactions::Attack;
 use crate::Select;
 use crate::ColorfulTheme;
-use crate::environment::world::{Place, World};
+use crate::environment::world::{Place, RoomTransition, World};
+use crate::gui::combat_log::LogCategoryEnum;
 use crate::gui::menu::Menu;
-use crate::services::a_star::calculate_range;
+use crate::environment::tile::{DisarmOutcomeEnum, GasCloud, TileTriggerEnum, TILES};
+use crate::services::a_star::{calculate_range, calculate_walk_range, find_path, line_of_sight, path_cost};
+use crate::services::dice::Dice;
 use crate::services::messaging::MessageContent;
+use crate::logic::game_loop::GameLoop;
+use crate::logic::quest::{QuestEventEnum, QuestSnapshot};
+use crate::pawn::turn::ActionCostEnum;
+use crate::interact::fumble::{FumbleEnum, FumbleTable};
+use crate::interact::dialogue::Conversation;
+use crate::interact::interaction::Interaction;
+use crate::interact::reactions::Reactions;
+use crate::pawn::condition::ConditionEnum;
 
 #[warn(non_camel_case_types)]
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -30,6 +45,15 @@ pub enum Actions {
     WATCH,
     USE,
     EQUIP,
+    DISARM,
+    PICK_UP,
+    DROP,
+    LOOT,
+    RELOAD,
+    TALK,
+    JOURNAL,
+    SWITCH,
+    END_TURN,
 }
 
 impl Actions {
@@ -39,7 +63,16 @@ impl Actions {
              "Walk to".to_string(),
              "Watch".to_string(),
              "Use".to_string(),
-             "Equip".to_string()]
+             "Inventory".to_string(),
+             "Disarm".to_string(),
+             "Pick up".to_string(),
+             "Drop".to_string(),
+             "Loot".to_string(),
+             "Reload".to_string(),
+             "Talk".to_string(),
+             "Journal".to_string(),
+             "Switch character".to_string(),
+             "End turn".to_string()]
     }
 }
 
@@ -52,11 +85,32 @@ impl From<usize> for Actions {
             x if x == Actions::WALK_TO as usize => Actions::WALK_TO,
             x if x == Actions::WATCH as usize => Actions::WATCH,
             x if x == Actions::EQUIP as usize => Actions::EQUIP,
+            x if x == Actions::DISARM as usize => Actions::DISARM,
+            x if x == Actions::PICK_UP as usize => Actions::PICK_UP,
+            x if x == Actions::DROP as usize => Actions::DROP,
+            x if x == Actions::LOOT as usize => Actions::LOOT,
+            x if x == Actions::RELOAD as usize => Actions::RELOAD,
+            x if x == Actions::TALK as usize => Actions::TALK,
+            x if x == Actions::JOURNAL as usize => Actions::JOURNAL,
+            x if x == Actions::SWITCH as usize => Actions::SWITCH,
+            x if x == Actions::END_TURN as usize => Actions::END_TURN,
             _ => Actions::OPEN,
         }
     }
 }
 
+impl Actions {
+    /// What the action takes from the turn budget when it is chosen.
+    /// Walking and attacking are free here, they are paid by the path walked and the hand used.
+    pub fn cost(&self) -> ActionCostEnum {
+        match self {
+            Actions::WALK_TO | Actions::ATTACK | Actions::JOURNAL | Actions::SWITCH | Actions::END_TURN => ActionCostEnum::FREE,
+            Actions::WATCH | Actions::EQUIP | Actions::PICK_UP | Actions::DROP | Actions::RELOAD => ActionCostEnum::BONUS_ACTION,
+            Actions::OPEN | Actions::USE | Actions::DISARM | Actions::LOOT | Actions::TALK => ActionCostEnum::ACTION,
+        }
+    }
+}
+
 impl Display for Actions {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -65,130 +119,675 @@ impl Display for Actions {
 
 impl Actions {
     pub fn handle_actions(pawns: &Vec<Rc<RefCell<Pawn>>>,
-                          world: &World,
-                          place_id:&mut u8,
+                          world: &mut World,
+                          place_id: u8,
+                          transition: &mut Option<RoomTransition>,
                           receivers: &HashMap<String, Receiver<MessageContent>>,
                           senders: &HashMap<String, Sender<MessageContent>>,
                           menu: &Menu) -> std::io::Result<()> {
-        let mut pawns_iter = (&pawns).iter();
-        while let Some(current_pawn) = pawns_iter.next() {
+        let fumbles = world.fumbles.clone();
+        //Pawns waiting for their turn, a pawn handing its turn over to another party member comes back resumed
+        let mut queue = pawns.iter()
+            .map(|pawn| (pawn.clone(), false))
+            .collect::<VecDeque<(Rc<RefCell<Pawn>>, bool)>>();
+        while let Some((current_pawn, resumed)) = queue.pop_front() {
+            let current_pawn = &current_pawn;
+            if current_pawn.borrow().is_dead() {
+                continue;
+            }
             menu.write_line(format!("{} turn.", current_pawn.clone().borrow().name).as_str())?;
 
             senders.get("current_player").unwrap().send(MessageContent {
                 topic: "current_player".to_string(),
                 content: bincode::serialize(&current_pawn.clone().borrow().id).unwrap(),
             }).unwrap();
+            //Over the network only the player owning the pawn gives orders
+            menu.set_owner(current_pawn.borrow().owner);
+
+            let graphical_mode = false;
+            #[cfg(feature = "graphical_mode")] let graphical_mode = true;
+
+            //The next player takes the seat before anything of the turn is shown
+            if world.hot_seat.hand_over(current_pawn, senders, menu)? {
+                GameLoop::send_fog(world, place_id, senders, menu)?;
+            }
 
-            println!("debut de tour de {}", current_pawn.clone().borrow().name);
+            GameLoop::send_party_statuses(pawns, current_pawn.borrow().id, senders);
+            if current_pawn.borrow().playable && !graphical_mode {
+                for status in Party::statuses(pawns, current_pawn.borrow().id) {
+                    menu.write_line(status.to_string().as_str())?;
+                }
+            }
+
+            if !resumed {
+                println!("debut de tour de {}", current_pawn.clone().borrow().name);
+                let conditions = current_pawn.borrow_mut().start_turn();
+                for condition in conditions {
+                    menu.log(LogCategoryEnum::DAMAGE, condition.as_str())?;
+                }
+            }
+            if current_pawn.borrow().is_dead() || current_pawn.borrow().turn_budget.is_spent() {
+                continue;
+            }
+
+            let mut switched = false;
+
+            //The pawn acts until its budget is spent or it ends its turn
+            loop {
+                Self::send_turn_budget(senders, &current_pawn.borrow());
+                if current_pawn.borrow().playable {
+                    menu.write_line(current_pawn.borrow().turn_budget.to_string().as_str())?;
+                }
 
-            let actions = ai::ai::let_ai_or_human_play(current_pawn.clone(),
-                                                       || {
-                                                           if let Ok(selected_action_id) = menu.menu(Actions::vec_string()) {
-                                                               selected_action_id
-                                                           } else {
+                let actions = ai::ai::let_ai_or_human_play(current_pawn.clone(),
+                                                           || {
+                                                               if let Ok(selected_action_id) = menu.menu(Actions::vec_string()) {
+                                                                   selected_action_id
+                                                               } else {
+                                                                   None
+                                                               }
+                                                           },
+                                                           || {
+                                                               //The AI is changed in place so it keeps its target from one step to the next
+                                                               if let Some(ai) = current_pawn.borrow().ai.borrow_mut().as_mut() {
+                                                                   let i = ai.get_action(current_pawn.clone(), menu).unwrap();
+                                                                   return Some(i);
+                                                               }
                                                                None
-                                                           }
-                                                       },
-                                                       || {
-                                                           if let Some(mut ref_mut) = current_pawn.clone().borrow().ai.clone().borrow_mut().to_owned() {
-                                                               let i = ref_mut.get_action(current_pawn.clone(), menu).unwrap();
-                                                               return Some(i);
-                                                           }
-                                                           None
-                                                       });
-
-            menu.clear_line()?;
+                                                           });
 
-            let graphical_mode = false;
-            #[cfg(feature = "graphical_mode")] let graphical_mode = true;
+                menu.clear_line()?;
+
+                let action: Actions = match actions {
+                    Some(action) => action.into(),
+                    None => break
+                };
+                if action == Actions::END_TURN {
+                    break;
+                }
+                if action == Actions::SWITCH {
+                    if let Some(index) = Self::switch_character(current_pawn, &queue, menu)? {
+                        let next = queue.remove(index).unwrap();
+                        queue.push_front((current_pawn.clone(), true));
+                        queue.push_front(next);
+                        switched = true;
+                        break;
+                    }
+                    continue;
+                }
+
+                if !Self::can_afford(&current_pawn.borrow(), &action) {
+                    menu.write_line(format!("{} cannot {} anymore this turn", current_pawn.borrow().name, action.to_string().to_lowercase()).as_str())?;
+                    if current_pawn.borrow().playable {
+                        continue;
+                    }
+                    break;
+                }
+                let snapshot = QuestSnapshot::take(current_pawn, pawns);
+                let reputation = ReputationSnapshot::take(pawns);
+                let mut interaction = Interaction::new();
+                interaction.spend(current_pawn, action.cost());
 
-            if let Some(action) = actions {
-                let room_arrays = &world.places.iter().find(|&place| place.id == place_id.clone()).unwrap().room;
-                match action.into() {
+                let place = world.get_place_mut(place_id).unwrap();
+                match action {
                     Actions::USE => {
                         println!("USE");
                         Ok(())
                     }
-                    Actions::WATCH => Self::watch_action(current_pawn.clone(), pawns, &room_arrays, receivers, senders, menu, graphical_mode),
-                    Actions::WALK_TO => Self::walk_action(&room_arrays, receivers, senders, menu, current_pawn, &world.places.iter().map(|el| el.id).collect::<Vec<u8>>(), place_id),
-                    Actions::ATTACK => Self::attack_action(pawns, current_pawn.clone(), senders, receivers, menu, &room_arrays, graphical_mode),
+                    Actions::WATCH => Self::watch_action(current_pawn.clone(), pawns, place, &mut interaction, receivers, senders, menu, graphical_mode),
+                    Actions::WALK_TO => Self::walk_action(place, &fumbles, &mut interaction, receivers, senders, menu, current_pawn, transition),
+                    Actions::ATTACK => Self::attack_action(pawns, current_pawn.clone(), &mut interaction, senders, receivers, menu, place, &fumbles, graphical_mode),
+                    Actions::RELOAD => Self::reload_action(current_pawn, &mut interaction, menu),
+                    Actions::DISARM => Self::disarm_action(current_pawn, place, &mut interaction, senders, menu),
+                    Actions::PICK_UP => Self::pick_up_action(current_pawn, place, &mut interaction, menu),
+                    Actions::DROP => Self::drop_action(current_pawn, place, &mut interaction, menu),
+                    Actions::LOOT => Self::loot_action(current_pawn, place, &mut interaction, menu),
+                    Actions::TALK => Self::talk_action(current_pawn, pawns, world, &mut interaction, senders, menu),
+                    Actions::JOURNAL => Self::journal_action(world, senders, menu, graphical_mode),
                     Actions::OPEN => {
                         println!("OPEN");
 
                         Ok(())
                     }
-                    Actions::EQUIP => Self::equip_item(current_pawn.clone(), menu)
+                    Actions::EQUIP => {
+                        let lenders = if world.party.shared_inventory { Some(pawns) } else { None };
+                        Self::inventory_action(current_pawn.clone(), lenders, &mut interaction, receivers, senders, menu, graphical_mode)
+                    }
+                    Actions::SWITCH | Actions::END_TURN => Ok(())
                 }?;
+
+                //Nothing happened, the pawn picks another action
+                if interaction.settle(current_pawn, menu)? {
+                    continue;
+                }
+
+                //Kills made out of a player turn, by a reaction or a fumble, are rewarded to the first player standing
+                let rewarded = if current_pawn.borrow().playable {
+                    Some(current_pawn)
+                } else {
+                    pawns.iter().find(|p| p.borrow().playable && !p.borrow().is_dead())
+                };
+                world.update_quests(snapshot.events(current_pawn, pawns), rewarded, menu)?;
+                world.update_reputation(reputation.changes(current_pawn, pawns), menu)?;
+
+                GameLoop::send_place_sprites(world.get_place(place_id).unwrap(), &world.hot_seat, senders);
+                GameLoop::send_fog(world, place_id, senders, menu)?;
+                GameLoop::send_party_statuses(pawns, current_pawn.borrow().id, senders);
+
+                if transition.is_some() || current_pawn.borrow().is_dead() || current_pawn.borrow().turn_budget.is_spent() {
+                    break;
+                }
+            }
+            //The chosen party member plays now, this pawn ends its turn after it
+            if switched {
+                continue;
             }
             println!("fin de tour de {}", current_pawn.clone().borrow().name);
+
+            //The place is left, remaining pawns will play when it is visited again
+            if transition.is_some() {
+                break;
+            }
         }
 
         Ok(())
     }
 
-    fn walk_action(room: &Vec<Vec<u8>>,
+    /// Party members of the same player still waiting for their turn this round, the one picked plays before the current pawn goes on.
+    fn switch_character(current_pawn: &Rc<RefCell<Pawn>>,
+                        queue: &VecDeque<(Rc<RefCell<Pawn>>, bool)>,
+                        menu: &Menu) -> std::io::Result<Option<usize>> {
+        let owner = current_pawn.borrow().owner;
+        let waiting = queue.iter()
+            .enumerate()
+            .filter(|(_, (pawn, _))| pawn.borrow().playable && pawn.borrow().owner == owner && Party::is_member(&pawn.borrow()))
+            .map(|(index, (pawn, _))| (index, pawn.borrow().name.clone()))
+            .collect::<Vec<(usize, String)>>();
+        if waiting.is_empty() {
+            menu.write_line("Every party member has played this round")?;
+            return Ok(None);
+        }
+
+        let mut interaction = Interaction::new();
+        let names = waiting.iter().map(|(_, name)| name.clone()).collect::<Vec<String>>();
+        let selected = interaction.choose(current_pawn, menu, &names, || None)?;
+        Ok(selected.and_then(|selected| waiting.get(selected)).map(|(index, _)| *index))
+    }
+
+    /// Attacks and walks are paid once the weapon or the path is known, so only their availability is checked here.
+    fn can_afford(pawn: &Pawn, action: &Actions) -> bool {
+        let budget = &pawn.turn_budget;
+        match action {
+            Actions::ATTACK => budget.action || (pawn.dual_wielding() && budget.bonus_action),
+            Actions::WALK_TO => budget.movement > 0,
+            _ => budget.can_afford(&action.cost())
+        }
+    }
+
+    fn send_turn_budget(senders: &HashMap<String, Sender<MessageContent>>, pawn: &Pawn) {
+        senders.get("turn_budget").unwrap().send(MessageContent {
+            topic: "turn_budget".to_string(),
+            content: bincode::serialize(&(pawn.id, &pawn.turn_budget)).unwrap(),
+        }).unwrap();
+    }
+
+    fn walk_action(place: &mut Place,
+                   fumbles: &FumbleTable,
+                   interaction: &mut Interaction,
                    receivers: &HashMap<String, Receiver<MessageContent>>,
                    senders: &HashMap<String, Sender<MessageContent>>,
                    menu: &Menu,
                    current_pawn: &Rc<RefCell<Pawn>>,
-                   places_id: &Vec<u8>,
-                   place_id: &mut u8) -> Result<(), Error> {
+                   transition: &mut Option<RoomTransition>) -> Result<(), Error> {
+        let room = place.room.clone();
+        let room = &room;
         senders.get("gameplay_state").unwrap().send(MessageContent {
             topic: "gameplay_state".to_string(),
             content: bincode::serialize(&Actions::WALK_TO).unwrap(),
         }).unwrap();
 
         let current_pawn_clone = current_pawn.clone();
-        let stats = (current_pawn_clone.borrow().characteristics.dexterity as u16 + current_pawn_clone.borrow().characteristics.force as u16) / 3u16;
+        let stats = current_pawn_clone.borrow().turn_budget.movement;
 
-        let range = Self::calculate_range(current_pawn_clone.clone(), room, stats);
+        let position = (current_pawn_clone.borrow().position.x, current_pawn_clone.borrow().position.y);
+        let range = calculate_walk_range(position, stats, room);
 
-        let selected_target = loop {
-            let selected_target = Self::communicate_to_ui_for_target(&range, senders, receivers);
-            let x = range.get(selected_target.1 as usize).unwrap().get(selected_target.0 as usize).unwrap();
-            if *x {
-                break selected_target;
+        let selected_target = if current_pawn_clone.borrow().playable {
+            loop {
+                let selected_target = match interaction.target(&range, senders, receivers) {
+                    Some(selected_target) => selected_target,
+                    None => return Ok(())
+                };
+                let x = range.get(selected_target.1 as usize).unwrap().get(selected_target.0 as usize).unwrap();
+                if *x {
+                    break selected_target;
+                }
+            }
+        } else {
+            let destination = current_pawn_clone.borrow().ai.borrow().as_ref()
+                .and_then(|ai| ai.choose_destination(&current_pawn_clone.borrow(), &range, place));
+            match destination {
+                Some(destination) => destination,
+                None => {
+                    //Nowhere better to go, the AI stays where it is
+                    current_pawn_clone.borrow_mut().turn_budget.spend(&ActionCostEnum::MOVEMENT(stats));
+                    Self::send_end_turn_signal(senders, current_pawn_clone.borrow().id);
+                    return Ok(());
+                }
             }
         };
         let desired_next_position = room.get(selected_target.1 as usize).unwrap().get(selected_target.0 as usize).unwrap();
 
-        let filtered_next_door = places_id.iter()
-            .filter(|&&el| {
-                el == desired_next_position.clone()
-            })
-            .collect::<Vec<&u8>>();
-        let door_id = filtered_next_door
-            .first();
+        let exit = place.exit_at(&Position { x: selected_target.0, y: selected_target.1 }).cloned();
 
         let current_pawn_id = current_pawn_clone.borrow().id;
         let current_pawn_name = current_pawn_clone.borrow().name.clone();
 
         //We click on door
-        if let Some(&id) = door_id {
-            *place_id = id.clone();
-            println!("next door {}, current next {}", id, place_id);
-
+        if let Some(exit) = exit {
             Self::send_end_turn_signal(senders, current_pawn_id);
-            menu.write_line(format!("{} walk to the door...", current_pawn_name).as_str())?;
+            menu.log(LogCategoryEnum::MOVEMENT, format!("{} walk to the door...", current_pawn_name).as_str())?;
+
+            //The way to the door is walked like any other, a trap or an enemy may stop the pawn before it
+            let path = Self::walking_path(current_pawn_id, position, selected_target, stats, place);
+            Self::walk_path(current_pawn, &path, place, fumbles, senders, menu)?;
+            current_pawn_clone.borrow_mut().turn_budget.spend(&ActionCostEnum::MOVEMENT(stats));
+
+            let through = current_pawn_clone.borrow().position == exit.position && !current_pawn_clone.borrow().is_dead();
+            if through {
+                println!("next door {}, current place {}", exit.destination, place.id);
+                *transition = Some(RoomTransition {
+                    pawn_id: current_pawn_id,
+                    from: place.id,
+                    exit,
+                });
+            }
         }
         //We click on a wall or non walkable surface
-        else if desired_next_position == &20u8 {
+        else if !TILES.is_walkable(*desired_next_position) {
             Self::send_end_turn_signal(senders, current_pawn_id);
 
-            menu.write_line(format!("{} cannot walk there", current_pawn_name).as_str())?;
+            menu.log(LogCategoryEnum::MOVEMENT, format!("{} cannot walk there", current_pawn_name).as_str())?;
         }
         //We click on walkable surface
         else {
-            let mut ref_mut1 = current_pawn_clone.borrow_mut();
-            ref_mut1.position = Position { x: selected_target.0, y: selected_target.1 };
-
             Self::send_end_turn_signal(senders, current_pawn_id);
 
-            menu.write_line(format!("{} walk...", current_pawn_name).as_str())?;
+            menu.log(LogCategoryEnum::MOVEMENT, format!("{} walk...", current_pawn_name).as_str())?;
+
+            let path = Self::walking_path(current_pawn_id, position, selected_target, stats, place);
+            let cost = path_cost(&path, room).max(1);
+            current_pawn_clone.borrow_mut().turn_budget.spend(&ActionCostEnum::MOVEMENT(cost));
+            Self::walk_path(current_pawn, &path, place, fumbles, senders, menu)?;
+        }
+        Ok(())
+    }
+
+    /// Known hazards are walked around unless the detour is longer than what the pawn can walk.
+    fn walking_path(pawn_id: i64, from: (u16, u16), to: (u16, u16), movement: u16, place: &Place) -> Vec<(u16, u16)> {
+        let known_hazards = place.known_hazards(pawn_id);
+        let safe_path = find_path(from, to, &place.room, &known_hazards)
+            .filter(|path| path_cost(path, &place.room) <= movement);
+
+        safe_path
+            .or_else(|| find_path(from, to, &place.room, &vec![]))
+            .unwrap_or_else(|| vec![to])
+    }
+
+    /// Move the pawn tile by tile, firing the triggers it steps on.
+    fn walk_path(pawn: &Rc<RefCell<Pawn>>,
+                 path: &Vec<(u16, u16)>,
+                 place: &mut Place,
+                 fumbles: &FumbleTable,
+                 senders: &HashMap<String, Sender<MessageContent>>,
+                 menu: &Menu) -> std::io::Result<()> {
+        for &(x, y) in path {
+            let step = Position { x, y };
+
+            //Leaving the reach of an enemy provokes it
+            Reactions::on_leave_reach(pawn, &step, place, fumbles, menu)?;
+            if pawn.borrow().is_dead() {
+                break;
+            }
+            pawn.borrow_mut().position = step.clone();
+
+            let mut triggers = vec![];
+            if let Some(trigger) = TILES.get(place.room[y as usize][x as usize]).trigger.clone() {
+                triggers.push(trigger);
+            }
+            if let Some(index) = place.armed_trigger_at(&step) {
+                triggers.push(place.triggers.get_mut(index).unwrap().step_on(pawn.borrow().id));
+            }
+
+            let mut stop = false;
+            for trigger in triggers {
+                stop |= Self::fire_tile_trigger(pawn, &trigger, place, senders, menu)?;
+            }
+            if stop || pawn.borrow().life == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the trigger effect on the pawn, returns true when the pawn cannot keep walking.
+    fn fire_tile_trigger(pawn: &Rc<RefCell<Pawn>>,
+                         trigger: &TileTriggerEnum,
+                         place: &mut Place,
+                         senders: &HashMap<String, Sender<MessageContent>>,
+                         menu: &Menu) -> std::io::Result<bool> {
+        let position = (pawn.borrow().position.x, pawn.borrow().position.y);
+        let name = pawn.borrow().name.clone();
+        match trigger {
+            TileTriggerEnum::DAMAGE(damage_type, amount) => {
+                let damages = pawn.borrow_mut().take_hit(*amount);
+                Self::send_damage_type_message(senders, damage_type, &position);
+                menu.log(LogCategoryEnum::DAMAGE, format!("{} steps on a {} and takes {} damages", name, trigger, damages).as_str())?;
+            }
+            TileTriggerEnum::HEAL(amount) => {
+                let mut pawn = pawn.borrow_mut();
+                pawn.life = pawn.life.saturating_add(*amount).min(pawn.max_life);
+                Self::send_damage_type_message(senders, &DamageTypeEnum::HEAL, &position);
+                menu.log(LogCategoryEnum::DAMAGE, format!("{} drinks from a {} and is healed by {}", name, trigger, amount).as_str())?;
+            }
+            TileTriggerEnum::POISON_GAS { radius, turns, damage } => {
+                place.gas_clouds.push(GasCloud {
+                    position: pawn.borrow().position.clone(),
+                    radius: *radius,
+                    turns: *turns,
+                    damage: *damage,
+                });
+                Self::send_damage_type_message(senders, &DamageTypeEnum::POISON, &position);
+                menu.write_line(format!("{} triggers a {}, poison gas fills the air!", name, trigger).as_str())?;
+            }
+            TileTriggerEnum::PIT(amount) => {
+                let damages = pawn.borrow_mut().take_hit(*amount);
+                Self::send_damage_type_message(senders, &DamageTypeEnum::BLUNT, &position);
+                menu.log(LogCategoryEnum::DAMAGE, format!("{} falls in a {} and takes {} damages", name, trigger, damages).as_str())?;
+            }
+            TileTriggerEnum::TELEPORT(destination) => {
+                pawn.borrow_mut().position = destination.clone();
+                menu.log(LogCategoryEnum::MOVEMENT, format!("{} steps on a {} and vanishes!", name, trigger).as_str())?;
+            }
+        }
+        Ok(trigger.stops_movement())
+    }
+
+    /// Perception check against every hidden trap the pawn can see, d20 + intelligence versus trap detection.
+    fn search_traps(pawn: &Rc<RefCell<Pawn>>, place: &mut Place, menu: &Menu) -> std::io::Result<()> {
+        let (pawn_id, name, intelligence) = (pawn.borrow().id, pawn.borrow().name.clone(), pawn.borrow().characteristics.intelligence);
+        let position = (pawn.borrow().position.x, pawn.borrow().position.y);
+        let room = place.room.clone();
+
+        for trigger in place.triggers.iter_mut()
+            .filter(|t| t.armed && !t.is_known_by(pawn_id))
+            .filter(|t| line_of_sight(position, (t.position.x, t.position.y), &room)) {
+            let perception = Dice::roll_1d20() as u8 + intelligence;
+            if perception >= trigger.detection {
+                trigger.discovered_by.push(pawn_id);
+                menu.write_line(format!("{} spots a {}", name, trigger.effect).as_str())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Dexterity check on a known trap next to the pawn, failing badly sets it off.
+    fn disarm_action(pawn: &Rc<RefCell<Pawn>>,
+                     place: &mut Place,
+                     interaction: &mut Interaction,
+                     senders: &HashMap<String, Sender<MessageContent>>,
+                     menu: &Menu) -> std::io::Result<()> {
+        let (pawn_id, name, dexterity) = (pawn.borrow().id, pawn.borrow().name.clone(), pawn.borrow().characteristics.dexterity);
+        let position = pawn.borrow().position.clone();
+
+        let disarmables = place.triggers.iter()
+            .enumerate()
+            .filter(|(_, t)| t.armed && t.effect.is_disarmable() && t.is_known_by(pawn_id))
+            .filter(|(_, t)| (t.position.x as i32 - position.x as i32).abs() <= 1 &&
+                (t.position.y as i32 - position.y as i32).abs() <= 1)
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+
+        if disarmables.is_empty() {
+            menu.write_line("There is no known trap to disarm around")?;
+            return Ok(());
+        }
+
+        let names = disarmables.iter()
+            .map(|&i| place.triggers.get(i).unwrap().effect.to_string())
+            .collect::<Vec<String>>();
+        let selected = interaction.choose(pawn, menu, &names, || Some(0))?;
+
+        if let Some(&index) = selected.and_then(|s| disarmables.get(s)) {
+            let roll = Dice::roll_1d20() as u8 + dexterity;
+            let trigger = place.triggers.get_mut(index).unwrap();
+            let effect = trigger.effect.clone();
+            match trigger.try_disarm(roll) {
+                DisarmOutcomeEnum::DISARMED => menu.write_line(format!("{} disarms the {}", name, effect).as_str())?,
+                DisarmOutcomeEnum::FAILED => menu.write_line(format!("{} fails to disarm the {}", name, effect).as_str())?,
+                DisarmOutcomeEnum::SET_OFF => {
+                    menu.write_line(format!("{} sets off the {}!", name, effect).as_str())?;
+                    Self::fire_tile_trigger(pawn, &effect, place, senders, menu)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pick one of the items lying on the pawn tile or next to it.
+    fn pick_up_action(pawn: &Rc<RefCell<Pawn>>, place: &mut Place, interaction: &mut Interaction, menu: &Menu) -> std::io::Result<()> {
+        let position = pawn.borrow().position.clone();
+        let name = pawn.borrow().name.clone();
+
+        let reachables = place.objects.iter()
+            .enumerate()
+            .filter(|(_, (p, _))| Self::is_next_to(&position, p))
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+
+        if reachables.is_empty() {
+            menu.write_line("There is nothing to pick up around")?;
+            return Ok(());
+        }
+
+        let names = reachables.iter()
+            .map(|&i| place.objects.get(i).unwrap().1.name.clone())
+            .collect::<Vec<String>>();
+        let selected = interaction.choose(pawn, menu, &names, || Some(0))?;
+
+        if let Some(&index) = selected.and_then(|s| reachables.get(s)) {
+            let (position, item) = place.objects.remove(index);
+            let mut quantity = 1;
+
+            //Stackable items lying on the same tile are picked up together
+            if item.stackable {
+                let before = place.objects.len();
+                place.objects.retain(|(p, i)| !(p == &position && i.name == item.name));
+                quantity += (before - place.objects.len()) as u16;
+            }
+
+            menu.write_line(format!("{} picks up {} x{}", name, item.name, quantity).as_str())?;
+            pawn.borrow_mut().inventory.add_stack(item, quantity);
+        }
+        Ok(())
+    }
+
+    /// Leave an item of the inventory on the pawn tile, it is unequipped first.
+    fn drop_action(pawn: &Rc<RefCell<Pawn>>, place: &mut Place, interaction: &mut Interaction, menu: &Menu) -> std::io::Result<()> {
+        let items = pawn.borrow().inventory.items();
+        let names = items.iter()
+            .map(|item| item.name.clone())
+            .collect::<Vec<String>>();
+
+        if names.is_empty() {
+            menu.write_line("There is nothing to drop")?;
+            return Ok(());
+        }
+
+        let selected = interaction.choose(pawn, menu, &names, || None)?;
+
+        if let Some(item) = selected.and_then(|s| items.get(s)) {
+            let dropped = pawn.borrow_mut().remove_item(item);
+            if let Some(dropped) = dropped {
+                let position = pawn.borrow().position.clone();
+                menu.write_line(format!("{} drops {}", pawn.borrow().name, dropped.name).as_str())?;
+                place.objects.push((position, dropped));
+            }
         }
         Ok(())
     }
 
+    /// Search a dead creature next to the pawn, its gold is always taken, then items one by one.
+    fn loot_action(pawn: &Rc<RefCell<Pawn>>, place: &mut Place, interaction: &mut Interaction, menu: &Menu) -> std::io::Result<()> {
+        let position = pawn.borrow().position.clone();
+        let name = pawn.borrow().name.clone();
+
+        let corpses = place.pawns.iter()
+            .filter(|p| !Rc::ptr_eq(p, pawn))
+            .filter(|p| p.borrow().is_dead() && Self::is_next_to(&position, &p.borrow().position))
+            .cloned()
+            .collect::<Vec<Rc<RefCell<Pawn>>>>();
+
+        if corpses.is_empty() {
+            menu.write_line("There is no body to loot around")?;
+            return Ok(());
+        }
+
+        let corpses_name = corpses.iter()
+            .map(|c| c.borrow().name.clone())
+            .collect::<Vec<String>>();
+        let selected = interaction.choose(pawn, menu, &corpses_name, || Some(0))?;
+        let corpse = match selected.and_then(|s| corpses.get(s)) {
+            Some(corpse) => corpse.clone(),
+            None => return Ok(())
+        };
+
+        let gold = std::mem::take(&mut corpse.borrow_mut().gold);
+        if gold > 0 {
+            pawn.borrow_mut().gold += gold;
+            menu.write_line(format!("{} finds {} gold on {}", name, gold, corpse.borrow().name).as_str())?;
+        }
+
+        loop {
+            let items = corpse.borrow().inventory.items();
+            if items.is_empty() {
+                menu.write_line(format!("There is nothing left on {}", corpse.borrow().name).as_str())?;
+                break;
+            }
+
+            let items_name = items.iter()
+                .map(|item| item.name.clone())
+                .chain(std::iter::once("Done".to_string()))
+                .collect::<Vec<String>>();
+            let selected = let_ai_or_human_play(pawn.clone(), || menu.menu(items_name.clone()).unwrap_or(None), || Some(0));
+
+            match selected.and_then(|s| items.get(s)) {
+                Some(item) => {
+                    let taken = corpse.borrow_mut().remove_item(item);
+                    if let Some(taken) = taken {
+                        menu.write_line(format!("{} takes {}", name, taken.name).as_str())?;
+                        pawn.borrow_mut().inventory.add(taken);
+                    }
+                }
+                None => break
+            }
+        }
+        Ok(())
+    }
+
+    /// Start the dialogue of a pawn standing next to the speaker.
+    fn talk_action(pawn: &Rc<RefCell<Pawn>>,
+                   pawns: &Vec<Rc<RefCell<Pawn>>>,
+                   world: &mut World,
+                   interaction: &mut Interaction,
+                   senders: &HashMap<String, Sender<MessageContent>>,
+                   menu: &Menu) -> std::io::Result<()> {
+        let position = pawn.borrow().position.clone();
+        let listeners = pawns.iter()
+            .filter(|p| !p.borrow().is_dead() && p.borrow().dialogue.is_some() && Self::is_next_to(&position, &p.borrow().position))
+            .cloned()
+            .collect::<Vec<Rc<RefCell<Pawn>>>>();
+
+        if listeners.is_empty() {
+            menu.write_line("There is nobody to talk to around")?;
+            interaction.nothing_done(pawn);
+            return Ok(());
+        }
+
+        let names = listeners.iter()
+            .map(|p| p.borrow().name.clone())
+            .collect::<Vec<String>>();
+        let listener = match interaction.choose(pawn, menu, &names, || None)?.and_then(|s| listeners.get(s)) {
+            Some(listener) => listener.clone(),
+            None => return Ok(())
+        };
+
+        let tree_name = listener.borrow().dialogue.clone().unwrap();
+        match world.dialogues.get(tree_name.as_str()) {
+            Some(tree) => Conversation::run(pawn, &listener, tree, &mut world.flags, senders, menu)?,
+            None => {
+                interaction.nothing_done(pawn);
+                return menu.write_line(format!("{} has nothing to say", listener.borrow().name).as_str());
+            }
+        }
+        let talked = listener.borrow().name.clone();
+        world.update_quests(vec![QuestEventEnum::TALKED(talked)], Some(pawn), menu)
+    }
+
+    /// Quests given so far, printed in the console or opened in the window.
+    fn journal_action(world: &World, senders: &HashMap<String, Sender<MessageContent>>, menu: &Menu, graphical_mode: bool) -> std::io::Result<()> {
+        let entries = world.journal.view(&world.quests);
+        if graphical_mode {
+            senders.get("journal").unwrap().send(MessageContent {
+                topic: "journal".to_string(),
+                content: bincode::serialize(&entries).unwrap(),
+            }).unwrap();
+            return Ok(());
+        }
+
+        if entries.is_empty() {
+            return menu.write_line("The journal is empty");
+        }
+        for entry in entries.iter() {
+            menu.write_line(entry.to_string().as_str())?;
+        }
+        Ok(())
+    }
+
+    /// Load every equipped weapon which needs it, as long as there is ammunition.
+    fn reload_action(pawn: &Rc<RefCell<Pawn>>, interaction: &mut Interaction, menu: &Menu) -> std::io::Result<()> {
+        let name = pawn.borrow().name.clone();
+        let weapons = pawn.borrow().equipped.unique_items().into_iter()
+            .filter(|weapon| weapon.reload)
+            .collect::<Vec<Rc<Item>>>();
+
+        if weapons.is_empty() {
+            menu.write_line(format!("{} has no weapon to reload", name).as_str())?;
+            interaction.nothing_done(pawn);
+            return Ok(());
+        }
+
+        let mut any_reloaded = false;
+        for weapon in weapons {
+            let reloaded = pawn.borrow_mut().reload(&weapon);
+            match reloaded {
+                Ok(()) => {
+                    any_reloaded = true;
+                    menu.write_line(format!("{} reloads {}", name, weapon.name).as_str())?
+                }
+                Err(error) => menu.write_line(error.as_str())?
+            }
+        }
+        if !any_reloaded {
+            interaction.nothing_done(pawn);
+        }
+        Ok(())
+    }
+
+    fn is_next_to(position: &Position, other: &Position) -> bool {
+        (position.x as i32 - other.x as i32).abs() <= 1 && (position.y as i32 - other.y as i32).abs() <= 1
+    }
+
     fn send_end_turn_signal(senders: &HashMap<String, Sender<MessageContent>>, current_pawn_id: i64) {
         senders.get("end_turn").unwrap().send(MessageContent {
             topic: "end_turn".to_string(),
@@ -198,11 +797,15 @@ impl Actions {
 
     fn watch_action(current_player: Rc<RefCell<Pawn>>,
                     creatures: &Vec<Rc<RefCell<Pawn>>>,
-                    room: &Vec<Vec<u8>>,
+                    place: &mut Place,
+                    interaction: &mut Interaction,
                     receivers: &HashMap<String, Receiver<MessageContent>>,
                     senders: &HashMap<String, Sender<MessageContent>>,
                     menu: &Menu,
                     graphical_mode: bool) -> std::io::Result<()> {
+        Self::search_traps(&current_player, place, menu)?;
+        let room = &place.room;
+
         #[cfg(feature = "graphical_mode")]
         if current_player.clone().borrow().playable {
             senders.get("gameplay_state").unwrap().send(MessageContent {
@@ -210,17 +813,24 @@ impl Actions {
                 content: bincode::serialize(&Actions::WATCH).unwrap(),
             }).unwrap();
 
-            loop {
-                if let Ok(command) = receivers.get("info").unwrap().try_recv() {
-                    let (x, y): (u16, u16) = bincode::deserialize(command.content.as_slice()).unwrap();
-
+            match interaction.wait_target(receivers) {
+                None => (),
+                Some((x, y)) => {
                     println!("position {}, {}", x, y);
+                    let watcher_position = (current_player.borrow().position.x, current_player.borrow().position.y);
+                    let in_sight = line_of_sight(watcher_position, (x, y), room);
+
                     let creatures = creatures.iter()
                         .filter(|c| c.borrow().position.y == y && c.borrow().position.x == x)
                         .map(|el| el.clone())
                         .collect::<Vec<Rc<RefCell<Pawn>>>>();
 
-                    if let Some(creature_watched) = creatures.first() {
+                    if !in_sight {
+                        senders.get("info_response").unwrap().send(MessageContent {
+                            topic: "info_response".to_string(),
+                            content: "You cannot see there from here".as_bytes().to_vec(),
+                        }).unwrap();
+                    } else if let Some(creature_watched) = creatures.first() {
                         let creature_watched = creature_watched.clone();
                         let creature_stats = current_player.clone().borrow().try_watch(creature_watched);
 
@@ -234,12 +844,16 @@ impl Actions {
                             .get(x as usize)
                             .unwrap();
 
-                        let tile_info = match tile_spec {
-                            10 => "Simple floor",
-                            11 => "Path to First room",
-                            12 => "Path to Second room",
-                            _ => ""
-                        };
+                        let watcher_id = current_player.borrow().id;
+                        let known_trigger = place.triggers.iter()
+                            .find(|t| t.armed && t.position == Position { x, y } && t.is_known_by(watcher_id))
+                            .map(|t| format!("\nThere is a {} here", t.effect))
+                            .unwrap_or_default();
+                        //Doors tell where they lead, other tiles what they are
+                        let description = place.exit_at(&Position { x, y })
+                            .map(|exit| exit.description.clone())
+                            .unwrap_or_else(|| TILES.get(*tile_spec).description.clone());
+                        let tile_info = format!("{}{}", description, known_trigger);
 
                         senders.get("info_response").unwrap().send(MessageContent {
                             topic: "info_response".to_string(),
@@ -251,7 +865,6 @@ impl Actions {
                             break;
                         }
                     }
-                    break;
                 }
             }
         }
@@ -278,24 +891,20 @@ impl Actions {
                 }
             };
 
-            let selected_creature_index: Option<usize> = ai::ai::let_ai_or_human_play(current_player.clone(), move || {
-                if let Ok(result) = menu.menu(creatures_name.clone()) {
-                    result
-                } else {
-                    None
-                }
-            }, || {
+            let selected_creature_index = interaction.choose(&current_player, menu, &creatures_name, || {
                 let creatures_number = creatures.iter()
                     .filter(|e| {
                         let pawn_clone = e.clone();
                         pawn_clone.borrow().life > 0 &&
-                            pawn_clone.borrow().playable &&
-                            pawn_clone.borrow().id != current_player.clone().borrow().id
+                            FACTIONS.is_hostile(&pawn_clone.borrow(), &current_player.borrow())
                     }).count();
+                if creatures_number == 0 {
+                    return None;
+                }
                 let random_id = ((rand::random::<f32>() * creatures_number as f32) as f32).floor() as usize;
 
                 Some(random_id)
-            });
+            })?;
 
             if let Some(creature_id) = selected_creature_index {
                 if current_player.clone().borrow().playable {
@@ -306,8 +915,7 @@ impl Actions {
                         .filter(|e| {
                             let pawn_clone = e.clone();
                             pawn_clone.borrow().life > 0 &&
-                                pawn_clone.borrow().playable &&
-                                pawn_clone.borrow().id != current_player.clone().borrow().id
+                                FACTIONS.is_hostile(&pawn_clone.borrow(), &current_player.borrow())
                         })
                         .map(|e| e.clone())
                         .collect::<Vec<Rc<RefCell<Pawn>>>>();
@@ -329,76 +937,168 @@ impl Actions {
     }
 
 
-    fn equip_item(player: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<()> {
-        let selected_item: Option<Rc<Item>> =
-            {
-                let borrowed_player = player.borrow();
+    /// Inventory screen, the player may equip, sort and filter items until the screen is closed.
+    /// With a shared inventory, the companions' bags are shown too and an item changes bag once equipped.
+    /// Closing it without equipping or unequipping anything gives the bonus action back.
+    fn inventory_action(player: Rc<RefCell<Pawn>>,
+                        lenders: Option<&Vec<Rc<RefCell<Pawn>>>>,
+                        interaction: &mut Interaction,
+                        receivers: &HashMap<String, Receiver<MessageContent>>,
+                        senders: &HashMap<String, Sender<MessageContent>>,
+                        menu: &Menu,
+                        graphical_mode: bool) -> std::io::Result<()> {
+        if !player.borrow().playable {
+            return Ok(());
+        }
 
-                let mut inventory = borrowed_player
-                    .inventory
-                    .iter()
-                    .map(|item| item.name.as_str())
-                    .collect::<Vec<&str>>();
+        let mut sort = InventorySortEnum::NAME;
+        let mut filter = InventoryFilterEnum::ALL;
+        let mut changed = false;
 
-                inventory.push("Unequip");
+        if graphical_mode {
+            senders.get("gameplay_state").unwrap().send(MessageContent {
+                topic: "gameplay_state".to_string(),
+                content: bincode::serialize(&Actions::EQUIP).unwrap(),
+            }).unwrap();
 
-                let items = menu.menu(inventory.iter().map(|el| el.to_string()).collect::<Vec<String>>()).unwrap();
+            loop {
+                let lent = lenders.map(|pawns| Party::lent_stacks(&player, pawns, sort, filter)).unwrap_or_default();
+                senders.get("inventory").unwrap().send(MessageContent {
+                    topic: "inventory".to_string(),
+                    content: bincode::serialize(&InventoryView::pooled(&player.borrow(), &lent, sort, filter)).unwrap(),
+                }).unwrap();
 
-                if let Some(i) = items {
-                    let selected_item = inventory.get(i).unwrap();
-                    if *selected_item == "Unequip" {
-                        None
-                    } else {
-                        Some(borrowed_player.inventory.get(i).unwrap().clone())
+                let command = loop {
+                    if let Ok(command) = receivers.get("inventory_command").unwrap().try_recv() {
+                        break bincode::deserialize::<InventoryCommandEnum>(command.content.as_slice()).unwrap();
                     }
-                } else {
-                    None
+                };
+
+                match command {
+                    InventoryCommandEnum::EQUIP(index, part) => changed |= Self::equip_item(&player, index, part, &lent, menu)?,
+                    InventoryCommandEnum::UNEQUIP(part) => changed |= Self::unequip_part(&player, part),
+                    InventoryCommandEnum::SORT(new_sort) => sort = new_sort,
+                    InventoryCommandEnum::FILTER(new_filter) => filter = new_filter,
+                    InventoryCommandEnum::CLOSE => break,
                 }
+            }
+            Self::send_end_turn_signal(senders, player.borrow().id);
+            if !changed {
+                interaction.nothing_done(&player);
+            }
+            return Ok(());
+        }
+
+        loop {
+            let lent = lenders.map(|pawns| Party::lent_stacks(&player, pawns, sort, filter)).unwrap_or_default();
+            let view = InventoryView::pooled(&player.borrow(), &lent, sort, filter);
+            menu.write_line(view.header().as_str())?;
+
+            let options = view.entries.iter()
+                .map(|entry| entry.label())
+                .chain([format!("Sort by {:?}", sort.next()),
+                    format!("Show {:?}", filter.next()),
+                    "Unequip".to_string(),
+                    "Close".to_string()])
+                .collect::<Vec<String>>();
+
+            let selected = match menu.menu(options)? {
+                Some(selected) => selected,
+                None => break
             };
 
-        let mut mutable_player = player.borrow_mut();
-        if let Some(item) = selected_item {
-            menu.write_line(format!("You equipped {}", item.clone().name).as_str())?;
-            mutable_player.equip(Rc::clone(&item));
-        } else {
-            menu.write_line("What part do you want to unequip?")?;
-            let part_to_unequip = menu.menu([
-                PartToEquiEnum::HEAD,
-                PartToEquiEnum::FEET,
-                PartToEquiEnum::LEGS,
-                PartToEquiEnum::RIGHT_HAND,
-                PartToEquiEnum::LEFT_HAND,
-                PartToEquiEnum::BODY
-            ].iter().map(|el| el.to_string()).collect::<Vec<String>>());
-
-            let equip_enum = {
-                if let Some(part) = part_to_unequip.unwrap() {
-                    match part {
-                        0 => PartToEquiEnum::HEAD,
-                        1 => PartToEquiEnum::FEET,
-                        2 => PartToEquiEnum::LEGS,
-                        3 => PartToEquiEnum::RIGHT_HAND,
-                        4 => PartToEquiEnum::LEFT_HAND,
-                        5 => PartToEquiEnum::BODY,
-                        _ => PartToEquiEnum::BODY,
+            if let Some(entry) = view.entries.get(selected) {
+                menu.write_line(entry.details.as_str())?;
+                let options = entry.previews.iter()
+                    .map(|(part, preview)| format!("Equip on {} ({})", part, preview))
+                    .chain(std::iter::once("Back".to_string()))
+                    .collect::<Vec<String>>();
+                if let Some((part, _)) = menu.menu(options)?.and_then(|i| entry.previews.get(i)) {
+                    changed |= Self::equip_item(&player, entry.index, part.clone(), &lent, menu)?;
+                }
+                continue;
+            }
+
+            match selected - view.entries.len() {
+                0 => sort = sort.next(),
+                1 => filter = filter.next(),
+                2 => {
+                    menu.write_line("What part do you want to unequip?")?;
+                    let parts = PartToEquiEnum::all();
+                    let part = menu.menu(parts.iter().map(|el| el.to_string()).collect::<Vec<String>>())?;
+                    if let Some(part) = part.and_then(|p| parts.get(p)) {
+                        changed |= Self::unequip_part(&player, part.clone());
                     }
-                } else {
-                    PartToEquiEnum::BODY
                 }
+                _ => break
+            }
+        }
+        if !changed {
+            interaction.nothing_done(&player);
+        }
+        Ok(())
+    }
+
+    /// True when something was worn there.
+    fn unequip_part(player: &Rc<RefCell<Pawn>>, part: PartToEquiEnum) -> bool {
+        let worn = player.borrow().equipped.get(&part).is_some();
+        player.borrow_mut().de_equip(part);
+        worn
+    }
+
+    /// Indexes past the pawn's own stacks are lent ones, the item only leaves its companion's bag when it can be equipped.
+    /// Returns true once the item is equipped.
+    fn equip_item(player: &Rc<RefCell<Pawn>>, index: usize, part: PartToEquiEnum, lent: &Vec<(Rc<RefCell<Pawn>>, usize)>, menu: &Menu) -> std::io::Result<bool> {
+        let own = player.borrow().inventory.stacks().len();
+        let item = if index < own {
+            player.borrow().inventory.get(index).unwrap().item.clone()
+        } else {
+            let lent = match lent.get(index - own) {
+                Some(lent) => lent,
+                None => return Ok(false)
+            };
+            let item = lent.0.borrow().inventory.get(lent.1).map(|stack| stack.item.clone());
+            let item = match item {
+                Some(item) => item,
+                None => return Ok(false)
             };
+            let preview = player.borrow().equip_preview(&item, part.clone());
+            if let Err(error) = preview {
+                menu.write_line(error.to_string().as_str())?;
+                return Ok(false);
+            }
+            match Party::take_lent(player, lent, menu)? {
+                Some(item) => item,
+                None => return Ok(false)
+            }
+        };
 
-            mutable_player.de_equip(equip_enum);
+        let equipped = player.borrow_mut().equip(item.clone(), part);
+        match equipped {
+            Ok(unequipped) => {
+                for previous in unequipped {
+                    menu.write_line(format!("You put away {}", previous.name).as_str())?;
+                }
+                menu.write_line(format!("You equipped {}", item.name).as_str())?;
+                Ok(true)
+            }
+            Err(error) => {
+                menu.write_line(error.to_string().as_str())?;
+                Ok(false)
+            }
         }
-        Ok(())
     }
 
     fn attack_action(creatures: &Vec<Rc<RefCell<Pawn>>>,
                      player: Rc<RefCell<Pawn>>,
+                     interaction: &mut Interaction,
                      senders: &HashMap<String, Sender<MessageContent>>,
                      receivers: &HashMap<String, Receiver<MessageContent>>,
                      menu: &Menu,
-                     room: &Vec<Vec<u8>>,
+                     place: &mut Place,
+                     fumbles: &FumbleTable,
                      graphical_mode: bool) -> std::io::Result<()> {
+        let room = &place.room.clone();
         senders.get("gameplay_state").unwrap().send(MessageContent {
             topic: "gameplay_state".to_string(),
             content: bincode::serialize(&Actions::ATTACK).unwrap(),
@@ -410,36 +1110,77 @@ impl Actions {
         }
 
         //Select action
-        let action = ai::ai::let_ai_or_human_play(player.clone(),
-                                                  || menu.menu(vec!["your equipped weapon".to_string(), "a spell".to_string()]).unwrap(),
-                                                  || {
-                                                      let rc = player.clone();
-                                                      let x = rc.borrow();
-                                                      let rc1 = x.ai.clone();
-                                                      let ref_mut = rc1.borrow_mut();
-                                                      if let Some(ai) = ref_mut.to_owned() {
-                                                          return Some(ai.select_weapon_or_spell(player.clone()));
-                                                      }
-                                                      None
-                                                  });
-
-        let select_item_to_attack_with = Attack::select_item_to_attack_with(player.clone(), action, menu)?;
+        let mut ways_to_attack = vec!["your equipped weapon".to_string(), "a spell".to_string()];
+        if player.borrow().dual_wielding() {
+            ways_to_attack.push("your off-hand weapon".to_string());
+        }
+        let action = interaction.choose(&player, menu, &ways_to_attack, || {
+            let rc = player.clone();
+            let x = rc.borrow();
+            let rc1 = x.ai.clone();
+            let ref_mut = rc1.borrow_mut();
+            if let Some(ai) = ref_mut.to_owned() {
+                return Some(ai.select_weapon_or_spell(player.clone()));
+            }
+            None
+        })?;
+        if interaction.is_cancelled() {
+            return Ok(());
+        }
+
+        //The off-hand strike is a bonus action, every other attack takes the action
+        let cost = if action == Some(2) { ActionCostEnum::BONUS_ACTION } else { ActionCostEnum::ACTION };
+        if action.is_some() {
+            if !player.borrow().turn_budget.can_afford(&cost) {
+                menu.write_line("You cannot attack that way anymore this turn")?;
+                interaction.nothing_done(&player);
+                return Ok(());
+            }
+            interaction.spend(&player, cost);
+        }
+
+        let select_item_to_attack_with = Attack::select_item_to_attack_with(player.clone(), action, interaction, menu)?;
+        if interaction.is_cancelled() {
+            return Ok(());
+        }
+        let penalty = match action {
+            Some(0) => Attack::hand_penalty(&player.borrow(), false),
+            Some(2) => Attack::hand_penalty(&player.borrow(), true),
+            _ => 0
+        };
 
         if let None = select_item_to_attack_with {
             menu.write_line("You have no way to deal damage to any target!")?;
+            interaction.nothing_done(&player);
             return Ok(());
         }
         let unwrapped_selected_item = select_item_to_attack_with.unwrap();
 
         if let None = unwrapped_selected_item.get_range() {
             menu.write_line("You have no way to deal damage to any target!")?;
+            interaction.nothing_done(&player);
             return Ok(());
         }
 
+        let ranged_weapon = Attack::weapon_in_hand(&player.borrow(), action)
+            .filter(|weapon| weapon.uses_ammunition.is_some());
+        if let Some(weapon) = &ranged_weapon {
+            let kind = weapon.uses_ammunition.unwrap();
+            if !player.borrow().is_ready_to_shoot(weapon) {
+                menu.write_line(format!("{} has to be reloaded", weapon.name).as_str())?;
+                interaction.nothing_done(&player);
+                return Ok(());
+            }
+            if player.borrow().find_ammunition(kind).is_none() {
+                menu.write_line(format!("There is no {:?} left for {}", kind, weapon.name).as_str())?;
+                interaction.nothing_done(&player);
+                return Ok(());
+            }
+        }
+
         let range = Self::calculate_range(player.clone(), room, unwrapped_selected_item.get_range().unwrap());
 
-        let attackable_things = creatures.clone()
-            .iter()
+        let attackable_pawns = creatures.iter()
             .filter(|&e| {
                 e.borrow().life > 0 &&
                     range.get(e.borrow().position.y as usize)
@@ -447,6 +1188,9 @@ impl Actions {
                         .get(e.borrow().position.x as usize)
                         .unwrap().clone()
             })
+            .cloned()
+            .collect::<Vec<Rc<RefCell<Pawn>>>>();
+        let attackable_things = attackable_pawns.iter()
             .map(|c| {
                 c.borrow().name.clone()
             })
@@ -456,6 +1200,7 @@ impl Actions {
 
         if attackable_things.is_empty() {
             menu.write_line("There is nothing to attack")?;
+            interaction.nothing_done(&player);
             return Ok(());
         }
 
@@ -463,6 +1208,7 @@ impl Actions {
 
         if usability == 0 {
             menu.write_line("You don't know what to do!")?;
+            interaction.nothing_done(&player);
             return Ok(());
         }
 
@@ -470,26 +1216,81 @@ impl Actions {
         let playable = player.clone().borrow().playable;
 
         let selected_creature = if !graphical_mode || !playable {
-            let targeted_creature = Self::select_target_console(creatures, attackable_things, player.clone(), menu)?;
+            let targeted_creature = match Self::select_target_console(&attackable_pawns, attackable_things, player.clone(), interaction, menu)? {
+                Some(targeted_creature) => targeted_creature,
+                None => {
+                    interaction.nothing_done(&player);
+                    return Ok(());
+                }
+            };
             if graphical_mode && !playable {
                 let toto: Vec<Vec<bool>> = Vec::new();
                 senders.get("targetable").unwrap().send(MessageContent {
                     topic: "targetable".to_string(),
                     content: bincode::serialize(&toto).unwrap(),
                 }).unwrap();
-
-                let targeted_creature = targeted_creature.clone();
-
-                let position = (targeted_creature.borrow().position.x, targeted_creature.borrow().position.y);
-                Self::send_damage_type_message(senders, &unwrapped_selected_item.get_damage_type().unwrap(), &position);
             }
             targeted_creature
         } else {
-            Self::select_target_ui(range, player.clone(), creatures, senders, receivers, &unwrapped_selected_item.get_damage_type().unwrap(), menu)?
+            match Self::select_target_ui(range, player.clone(), creatures, interaction, senders, receivers, menu)? {
+                Some(targeted_creature) => targeted_creature,
+                None => return Ok(())
+            }
         };
-        menu.write_line("Roll 1d20 : ")?;
 
-        Self::roll_dice_attack(player.clone(), unwrapped_selected_item, selected_creature, menu)?;
+        //Attacking a pawn that was not hostile makes an enemy of it
+        FACTIONS.provoke(&player, &selected_creature, menu)?;
+
+        //Enemies may counter the spell, it is lost with the action
+        if action == Some(1) && Reactions::on_spell_cast(&player, unwrapped_selected_item.get_name(), creatures, place, menu)? {
+            return Ok(());
+        }
+
+        let mut penalty = penalty;
+        let mut disadvantage = false;
+        let mut shot = None;
+        if let Some(weapon) = &ranged_weapon {
+            let ammunition = player.borrow_mut().shoot(weapon).unwrap();
+            shot = Some(ammunition);
+
+            let from = player.borrow().position.clone();
+            let to = selected_creature.borrow().position.clone();
+            Self::send_projectile_message(senders, &from, &to, weapon.name.as_str());
+
+            let range_penalty = Attack::range_penalty(&from, &to, weapon.range.unwrap_or(1));
+            if range_penalty > 0 {
+                menu.log(LogCategoryEnum::ATTACK, format!("Long range : -{}", range_penalty).as_str())?;
+            }
+            penalty += range_penalty;
+
+            //Shooting with an enemy at arm's length
+            disadvantage = creatures.iter()
+                .any(|c| c.borrow().life > 0 && FACTIONS.is_hostile(&c.borrow(), &player.borrow()) &&
+                    Self::is_next_to(&from, &c.borrow().position));
+            if disadvantage {
+                menu.log(LogCategoryEnum::ATTACK, "An enemy is too close, the shot is made with disadvantage")?;
+            }
+        }
+
+        //Spells reaching further than a hand fly to their target, its impact waits for them to land
+        if action == Some(1) && unwrapped_selected_item.get_range().unwrap_or(1) > 1 {
+            Self::send_projectile_message(senders, &player.borrow().position, &selected_creature.borrow().position, unwrapped_selected_item.get_name());
+        }
+        if graphical_mode {
+            let position = (selected_creature.borrow().position.x, selected_creature.borrow().position.y);
+            Self::send_damage_type_message(senders, &unwrapped_selected_item.get_damage_type().unwrap(), &position);
+        }
+
+        menu.log(LogCategoryEnum::ATTACK, "Roll 1d20 : ")?;
+
+        Self::roll_dice_attack(player.clone(), unwrapped_selected_item, selected_creature.clone(), penalty, disadvantage, place, fumbles, menu)?;
+
+        //Half of the shots can be picked up again where the target stands
+        if let Some(ammunition) = shot {
+            if random::<bool>() {
+                place.objects.push((selected_creature.borrow().position.clone(), ammunition));
+            }
+        }
 
         if graphical_mode {
             senders.get("end_turn").unwrap().send(MessageContent {
@@ -501,41 +1302,54 @@ impl Actions {
         return Ok(());
     }
 
-    fn roll_dice_attack(player: Rc<RefCell<Pawn>>, unwrapped_selected_item: Rc<dyn Pocketable>, selected_creature: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<()> {
+    pub fn roll_dice_attack(player: Rc<RefCell<Pawn>>,
+                            unwrapped_selected_item: Rc<dyn Pocketable>,
+                            selected_creature: Rc<RefCell<Pawn>>,
+                            penalty: u8,
+                            disadvantage: bool,
+                            place: &mut Place,
+                            fumbles: &FumbleTable,
+                            menu: &Menu) -> std::io::Result<()> {
+        let critical_threshold = player.borrow().critical_threshold();
+        let roll = if disadvantage {
+            Attack::roll_attack_with_disadvantage(critical_threshold)
+        } else {
+            Attack::roll_attack(critical_threshold)
+        };
+
         // Roll dice
-        match Attack::roll_attack() {
+        match roll {
             RollDiceResult::Critical => Self::crititcal(&player, &unwrapped_selected_item, selected_creature, menu)?,
-            RollDiceResult::Fumble => Self::fumble(menu)?,
-            RollDiceResult::Normal(dice_result) => Self::normal(player, unwrapped_selected_item, selected_creature, dice_result, menu)?,
+            RollDiceResult::Fumble => Self::fumble(&player, &unwrapped_selected_item, place, fumbles, menu)?,
+            RollDiceResult::Normal(dice_result) => Self::normal(player, unwrapped_selected_item, selected_creature, dice_result, penalty, menu)?,
         };
 
         Ok(())
     }
 
-    fn normal(player: Rc<RefCell<Pawn>>, unwrapped_selected_item: Rc<dyn Pocketable>, selected_creature: Rc<RefCell<Pawn>>, dice_result: u8, menu: &Menu) -> std::io::Result<()> {
-        menu.write_line(format!("Normal attack, dice result : {}", dice_result).as_str())?;
+    fn normal(player: Rc<RefCell<Pawn>>, unwrapped_selected_item: Rc<dyn Pocketable>, selected_creature: Rc<RefCell<Pawn>>, dice_result: u8, penalty: u8, menu: &Menu) -> std::io::Result<()> {
+        menu.log(LogCategoryEnum::ATTACK, format!("Normal attack, dice result : {}", dice_result).as_str())?;
 
         let target_armor_points = selected_creature.clone().borrow().calculate_armor_points();
 
-        //Add modificator to dice roll
-        let dice_result = if let Some(attack_type) = unwrapped_selected_item.clone().get_attack_type() {
-            let characteristics = player.clone().borrow().characteristics;
-            match attack_type {
-                ItemAttackTypeEnum::CONTACT => dice_result + characteristics.force,
-                ItemAttackTypeEnum::DISTANCE => dice_result + characteristics.dexterity,
-                ItemAttackTypeEnum::MAGIC => dice_result + characteristics.willpower,
-            }
+        //Dual wielding and long range shots are harder, the penalty is part of the roll
+        let attack_roll = Rules::pawn_attack_roll(&player.borrow(), unwrapped_selected_item.as_ref(), dice_result, penalty);
+
+        //The target may still raise its guard
+        let target_armor_points = if attack_roll.hits(target_armor_points) {
+            target_armor_points + Reactions::on_attacked(&selected_creature, attack_roll.total(), target_armor_points, menu)?
         } else {
-            dice_result
+            target_armor_points
         };
+        menu.log(LogCategoryEnum::ATTACK, attack_roll.breakdown(target_armor_points).as_str())?;
 
-        // Check if target CA is greater than dice roll with modificator
-        if target_armor_points < dice_result {
+        // Check if the roll with its modifiers reaches the target armor class
+        if attack_roll.hits(target_armor_points) {
             let player_clone = player.clone();
             let damages_dealt = player_clone.borrow().hit(unwrapped_selected_item.clone(), selected_creature.clone());
-            menu.write_line(format!("{} inflict {} to {}", player_clone.borrow().name, damages_dealt, selected_creature.clone().borrow().name).as_str())?;
+            menu.log(LogCategoryEnum::DAMAGE, format!("{} inflict {} to {}", player_clone.borrow().name, damages_dealt, selected_creature.clone().borrow().name).as_str())?;
         } else {
-            menu.write_line(format!("{} cannot inflict damage to {}",
+            menu.log(LogCategoryEnum::ATTACK, format!("{} cannot inflict damage to {}",
                                     player.clone().borrow().name,
                                     selected_creature.clone().borrow().name).as_str())?;
         }
@@ -543,62 +1357,97 @@ impl Actions {
         Ok(())
     }
 
-    fn fumble(menu: &Menu) -> std::io::Result<()> {
-        menu.write_line("Fumble")?;
+    /// The adventure fumble table tells what goes wrong.
+    fn fumble(player: &Rc<RefCell<Pawn>>, unwrapped_selected_item: &Rc<dyn Pocketable>, place: &mut Place, fumbles: &FumbleTable, menu: &Menu) -> std::io::Result<()> {
+        menu.log(LogCategoryEnum::ATTACK, "Fumble")?;
+        let name = player.borrow().name.clone();
+
+        match fumbles.roll() {
+            FumbleEnum::NOTHING => {}
+            FumbleEnum::DROP_WEAPON => {
+                let weapon = player.borrow().equipped.unique_items()
+                    .into_iter()
+                    .find(|item| item.name == unwrapped_selected_item.get_name());
+                let dropped = weapon.and_then(|weapon| player.borrow_mut().remove_item(&weapon));
+                if let Some(dropped) = dropped {
+                    let position = player.borrow().position.clone();
+                    menu.log(LogCategoryEnum::ATTACK, format!("{} drops {}", name, dropped.name).as_str())?;
+                    place.objects.push((position, dropped));
+                }
+            }
+            FumbleEnum::HIT_ALLY => {
+                let reach = unwrapped_selected_item.get_range().unwrap_or(1);
+                let ally = place.pawns.iter()
+                    .find(|p| {
+                        let (p, player) = (p.borrow(), player.borrow());
+                        p.id != player.id && !p.is_dead() && FACTIONS.is_allied(&p, &player) &&
+                            (p.position.x as i32 - player.position.x as i32).abs().max((p.position.y as i32 - player.position.y as i32).abs()) as u16 <= reach
+                    })
+                    .cloned();
+                if let Some(ally) = ally {
+                    let damages_dealt = player.borrow().hit(unwrapped_selected_item.clone(), ally.clone());
+                    menu.log(LogCategoryEnum::DAMAGE, format!("{} hits {} instead and inflict {}", name, ally.borrow().name, damages_dealt).as_str())?;
+                }
+            }
+            FumbleEnum::LOSE_NEXT_ACTION => {
+                player.borrow_mut().add_condition(ConditionEnum::LOST_ACTION, 1);
+                menu.log(LogCategoryEnum::ATTACK, format!("{} is off balance and will lose its next action", name).as_str())?;
+            }
+        }
         Ok(())
     }
 
+    /// Damage dices are doubled and some weapons leave a lasting wound.
     fn crititcal(player: &Rc<RefCell<Pawn>>, unwrapped_selected_item: &Rc<dyn Pocketable>, selected_creature: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<()> {
-        menu.write_line("Critical!")?;
+        menu.log(LogCategoryEnum::ATTACK, "Critical!")?;
+
+        let damages_dealt = player.clone().borrow().critical_hit(unwrapped_selected_item.clone(), selected_creature.clone());
+        menu.log(LogCategoryEnum::DAMAGE, format!("{} inflict {} to {}", player.clone().borrow().name, damages_dealt, selected_creature.clone().borrow().name).as_str())?;
 
-        let damages_dealt = player.clone().borrow().hit(unwrapped_selected_item.clone(), selected_creature.clone());
-        menu.write_line(format!("{} inflict {} to {}", player.clone().borrow().name, damages_dealt, selected_creature.clone().borrow().name).as_str())?;
+        let effect = unwrapped_selected_item.get_damage_type()
+            .filter(|_| !selected_creature.borrow().is_dead())
+            .and_then(|damage_type| ConditionEnum::from_critical(&damage_type));
+        if let Some((condition, turns)) = effect {
+            selected_creature.borrow_mut().add_condition(condition, turns);
+            menu.log(LogCategoryEnum::DAMAGE, format!("{} is {:?}", selected_creature.borrow().name, condition).as_str())?;
+        }
         Ok(())
     }
 
-    fn select_target_console(creatures: &Vec<Rc<RefCell<Pawn>>>, attackable_things: Vec<String>, player: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<Rc<RefCell<Pawn>>> {
-        let target = {
-            if player.clone().borrow().playable {
-                menu.menu(attackable_things).unwrap()
-            } else {
-                let playable_pawns = creatures.iter()
-                    .filter(|c| {
-                        let creature_clone = c.clone();
-                        creature_clone.borrow().playable &&
-                            creature_clone.borrow().id != player.clone().borrow().id
-                    })
-                    .collect::<Vec<&Rc<RefCell<Pawn>>>>();
-                let i = creatures.iter()
-                    .position(|c| playable_pawns.get((random::<f32>() * playable_pawns.len() as f32).floor() as usize)
-                        .unwrap()
-                        .clone()
-                        .borrow()
-                        .id == c.clone().borrow().id)
-                    .unwrap();
+    /// One of the pawns in reach, the names are the menu shown to a human player in the same order.
+    /// The AI hits the target it focuses on when it is in reach, a random enemy in reach otherwise.
+    fn select_target_console(attackables: &Vec<Rc<RefCell<Pawn>>>, attackable_things: Vec<String>, player: Rc<RefCell<Pawn>>, interaction: &mut Interaction, menu: &Menu) -> std::io::Result<Option<Rc<RefCell<Pawn>>>> {
+        if player.borrow().playable {
+            let target = interaction.choose(&player, menu, &attackable_things, || None)?;
+            return Ok(target.and_then(|t| attackables.get(t)).cloned());
+        }
 
-                Some(i)
-            }
-        };
-        let selected_creature = {
-            if let Some(t) = target {
-                creatures.get(t).unwrap().clone()
-            } else {
-                //Select default creature
-                creatures.get(0).unwrap().clone()
-            }
-        };
-        Ok(selected_creature)
+        let focused = player.borrow().ai.borrow().as_ref().and_then(|ai| ai.selected_target.clone());
+        if let Some(focused) = focused.filter(|focused| attackables.iter().any(|a| a.borrow().id == focused.borrow().id)) {
+            return Ok(Some(focused));
+        }
+
+        let hostile_pawns = attackables.iter()
+            .filter(|c| FACTIONS.is_hostile(&c.borrow(), &player.borrow()))
+            .collect::<Vec<&Rc<RefCell<Pawn>>>>();
+        if hostile_pawns.is_empty() {
+            return Ok(None);
+        }
+        Ok(hostile_pawns.get(random::<usize>() % hostile_pawns.len()).map(|&c| c.clone()))
     }
 
     fn select_target_ui(range: Vec<Vec<bool>>,
                         player: Rc<RefCell<Pawn>>,
                         creatures: &Vec<Rc<RefCell<Pawn>>>,
+                        interaction: &mut Interaction,
                         senders: &HashMap<String, Sender<MessageContent>>,
                         receivers: &HashMap<String, Receiver<MessageContent>>,
-                        damage_type: &DamageTypeEnum,
-                        menu: &Menu) -> std::io::Result<Rc<RefCell<Pawn>>> {
+                        menu: &Menu) -> std::io::Result<Option<Rc<RefCell<Pawn>>>> {
         loop {
-            let selected_target = Self::communicate_to_ui_for_target(&range, senders, receivers);
+            let selected_target = match interaction.target(&range, senders, receivers) {
+                Some(selected_target) => selected_target,
+                None => return Ok(None)
+            };
 
             let filtered_creatures = creatures.iter()
                 .filter(|el| {
@@ -613,33 +1462,23 @@ impl Actions {
 
 
             if !filtered_creatures.is_empty() {
-                let targeted_creature = filtered_creatures.first().unwrap().clone();
-
-                let position = (targeted_creature.borrow().position.x, targeted_creature.borrow().position.y);
-                Self::send_damage_type_message(senders, damage_type, &position);
-                return Ok((creatures.get(0).unwrap().clone()));
+                return Ok(Some(filtered_creatures.first().unwrap().clone()));
             } else {
                 menu.write_line("No target selected. Try again.")?;
             }
         }
     }
 
-    fn communicate_to_ui_for_target(range: &Vec<Vec<bool>>, senders: &HashMap<String, Sender<MessageContent>>, receivers: &HashMap<String, Receiver<MessageContent>>) -> (u16, u16) {
-        senders.get("targetable").unwrap().send(MessageContent {
-            topic: "targetable".to_string(),
-            content: bincode::serialize(&range).unwrap(),
-        }).unwrap();
 
-        let info_receiver = receivers.get("info").unwrap();
-        let selected_target: (u16, u16) = loop {
-            if let Ok(info) = info_receiver.try_recv() {
-                break bincode::deserialize(info.content.as_slice()).unwrap();
-            }
-        };
-        selected_target
+    /// The window picks the effect flying from the weapon or spell name.
+    fn send_projectile_message(senders: &HashMap<String, Sender<MessageContent>>, from: &Position, to: &Position, name: &str) {
+        senders.get("projectile").unwrap().send(MessageContent {
+            topic: "projectile".to_string(),
+            content: bincode::serialize(&((from.x, from.y), (to.x, to.y), name)).unwrap(),
+        }).unwrap();
     }
 
-    fn send_damage_type_message(senders: &HashMap<String, Sender<MessageContent>>, damage_type: &DamageTypeEnum, position: &(u16, u16)) {
+    pub fn send_damage_type_message(senders: &HashMap<String, Sender<MessageContent>>, damage_type: &DamageTypeEnum, position: &(u16, u16)) {
         senders.get("show_damage").unwrap().send(MessageContent {
             topic: "show_damage".to_string(),
             content: bincode::serialize(&(position, damage_type)).unwrap(),
@@ -649,6 +1488,132 @@ impl Actions {
     fn calculate_range(player: Rc<RefCell<Pawn>>, room: &Vec<Vec<u8>>, range: u16) -> Vec<Vec<bool>> {
         let (x, y) = (player.clone().borrow().position.x, player.clone().borrow().position.y);
         let range = calculate_range((x, y), range, room);
-        range
+
+        //Only what can be seen can be targeted
+        range.iter()
+            .enumerate()
+            .map(|(j, row)| row.iter()
+                .enumerate()
+                .map(|(i, &in_range)| in_range && line_of_sight((x, y), (i as u16, j as u16), room))
+                .collect::<Vec<bool>>())
+            .collect::<Vec<Vec<bool>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::*;
+    use crate::interact::dialogue::DialogueBook;
+    use crate::logic::hot_seat::HotSeat;
+    use crate::logic::quest::{Journal, QuestBook};
+    use crate::pawn::faction::Reputation;
+    use crate::pawn::party::PartySettings;
+    use crate::services::initializer::Initializer;
+    use crate::services::messaging::Messaging;
+
+    /// A single place of bare floor holding the pawns.
+    fn world_of(pawns: &Vec<Rc<RefCell<Pawn>>>, room: Vec<Vec<u8>>) -> World {
+        World {
+            name: "test".to_string(),
+            places: vec![Place {
+                id: 1,
+                name: "Test room".to_string(),
+                weather: Initializer::init_weather().remove(0),
+                time: "Day".to_string(),
+                light: 255,
+                adjacent_places: vec![],
+                exits: vec![],
+                pawns: pawns.clone(),
+                objects: vec![],
+                triggers: vec![],
+                gas_clouds: vec![],
+                visited: true,
+                room,
+            }],
+            day: 0,
+            fumbles: FumbleTable::default(),
+            dialogues: DialogueBook::default(),
+            flags: HashSet::new(),
+            quests: QuestBook::default(),
+            journal: Journal::default(),
+            reputation: Reputation::default(),
+            party: Party::default(),
+            hot_seat: HotSeat::default(),
+        }
+    }
+
+    /// A party member left to the AI side, so the test never waits on a menu.
+    fn unplayed_member(position: Position) -> Rc<RefCell<Pawn>> {
+        let member = PartySettings::load("party.ron").unwrap().create_members().pop().unwrap();
+        member.borrow_mut().playable = false;
+        member.borrow_mut().position = position;
+        member
+    }
+
+    #[test]
+    fn an_ai_out_of_reach_walks_to_its_target_then_attacks_it() {
+        let mut messaging = Messaging::init();
+        let (menu, senders, receivers) = crate::gameplay_topics(&mut messaging);
+
+        let mut goblin = Initializer::generate_goblin(Initializer::item_named("Basic iron sword").unwrap(), Position { x: 0, y: 1 });
+        goblin.characteristics.dexterity = 10;
+        let goblin = Rc::new(RefCell::new(goblin));
+        let target = unplayed_member(Position { x: 3, y: 1 });
+        let pawns = vec![goblin.clone(), target.clone()];
+        let mut world = world_of(&pawns, vec![vec![10u8; 5]; 3]);
+
+        Actions::handle_actions(&pawns, &mut world, 1, &mut None, &receivers, &senders, &menu).unwrap();
+
+        let goblin = goblin.borrow();
+        assert!(Actions::is_next_to(&goblin.position, &target.borrow().position), "goblin stopped at {:?}", goblin.position);
+        assert!(!goblin.turn_budget.action);
+        let focused = goblin.ai.borrow().as_ref().and_then(|ai| ai.selected_target.clone());
+        assert_eq!(focused.map(|t| t.borrow().id), Some(target.borrow().id));
+    }
+
+    #[test]
+    fn an_ai_only_strikes_what_is_in_reach_and_prefers_its_target() {
+        let mut messaging = Messaging::init();
+        let (menu, _, _) = crate::gameplay_topics(&mut messaging);
+
+        let goblin = Rc::new(RefCell::new(Initializer::generate_goblin(Initializer::item_named("Basic iron sword").unwrap(), Position { x: 1, y: 1 })));
+        let in_reach = vec![unplayed_member(Position { x: 0, y: 1 }), unplayed_member(Position { x: 2, y: 1 })];
+        let names = in_reach.iter().map(|p| p.borrow().name.clone()).collect::<Vec<String>>();
+
+        for _ in 0..50 {
+            let target = Actions::select_target_console(&in_reach, names.clone(), goblin.clone(), &mut Interaction::new(), &menu).unwrap().unwrap();
+            assert!(in_reach.iter().any(|p| Rc::ptr_eq(p, &target)));
+        }
+
+        goblin.borrow().ai.borrow_mut().as_mut().unwrap().selected_target = Some(in_reach[1].clone());
+        for _ in 0..10 {
+            let target = Actions::select_target_console(&in_reach, names.clone(), goblin.clone(), &mut Interaction::new(), &menu).unwrap();
+            assert!(target.map(|t| Rc::ptr_eq(&t, &in_reach[1])).unwrap_or(false));
+        }
+
+        assert!(Actions::select_target_console(&vec![], vec![], goblin.clone(), &mut Interaction::new(), &menu).unwrap().is_none());
+    }
+
+    #[test]
+    fn only_waiting_members_of_the_same_player_can_be_switched_to() {
+        let mut messaging = Messaging::init();
+        let (menu, _, _) = crate::gameplay_topics(&mut messaging);
+        let members = PartySettings::load("hotseat.ron").unwrap().create_members();
+        let current = members.iter()
+            .find(|member| member.borrow().owner == 1)
+            .unwrap()
+            .clone();
+        let others = members.iter()
+            .filter(|member| member.borrow().owner != 1)
+            .map(|member| (member.clone(), false));
+        let dead = unplayed_member(Position { x: 0, y: 0 });
+        dead.borrow_mut().playable = true;
+        dead.borrow_mut().owner = 1;
+        dead.borrow_mut().life = 0;
+        let goblin = Rc::new(RefCell::new(Initializer::generate_goblin(Initializer::item_named("Basic iron sword").unwrap(), Position { x: 1, y: 0 })));
+        let queue = others.chain(vec![(dead, false), (goblin, false)]).collect::<VecDeque<(Rc<RefCell<Pawn>>, bool)>>();
+
+        assert_eq!(Actions::switch_character(&current, &queue, &menu).unwrap(), None);
     }
 }
\ No newline at end of file
@@ -0,0 +1,242 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use serde::{Deserialize, Serialize};
+use crate::gui::combat_log::LogCategoryEnum;
+use crate::gui::menu::Menu;
+use crate::pawn::faction::{FACTIONS, PLAYERS};
+use crate::pawn::pawn::Pawn;
+use crate::services::dice::Dice;
+use crate::services::initializer::Initializer;
+use crate::services::messaging::MessageContent;
+use crate::services::resources::load_ron;
+use crate::services::rules::Rules;
+
+/// Last entry of every dialogue menu, it ends the conversation.
+pub const LEAVE_OPTION: &str = "Leave";
+
+/// Characteristic checks a dialogue choice can be gated by.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CheckEnum {
+    PERSUASION,
+    INSIGHT,
+}
+
+impl CheckEnum {
+    /// Characteristic the check relies on, with its name.
+    pub fn characteristic(&self, pawn: &Pawn) -> (&'static str, u8) {
+        let characteristics = pawn.total_characteristics();
+        match self {
+            CheckEnum::PERSUASION => ("CHA", characteristics.charisma),
+            CheckEnum::INSIGHT => ("INT", characteristics.intelligence),
+        }
+    }
+}
+
+impl Display for CheckEnum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckEnum::PERSUASION => write!(f, "Persuasion"),
+            CheckEnum::INSIGHT => write!(f, "Insight"),
+        }
+    }
+}
+
+/// What reaching a node changes in the world.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OutcomeEnum {
+    SET_FLAG(String),
+    GIVE_ITEM(String),
+    TURN_HOSTILE,
+    JOIN_PARTY,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Check {
+    pub check: CheckEnum,
+    pub difficulty: u8,
+}
+
+/// An answer of the player, a failed check leads to the failure node instead of the next one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueChoice {
+    pub text: String,
+    #[serde(default)]
+    pub check: Option<Check>,
+    //Shown only once the flag is set
+    #[serde(default)]
+    pub requires_flag: Option<String>,
+    //Hidden once the flag is set
+    #[serde(default)]
+    pub unless_flag: Option<String>,
+    //None ends the conversation
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub failure: Option<String>,
+}
+
+/// A line of the speaker, the conversation ends on a node without choices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueNode {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub outcomes: Vec<OutcomeEnum>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueTree {
+    pub name: String,
+    //Sprite drawn next to the lines in the window
+    pub portrait: String,
+    pub start: String,
+    pub nodes: Vec<DialogueNode>,
+}
+
+impl DialogueTree {
+    pub fn node(&self, id: &str) -> Option<&DialogueNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+}
+
+/// Dialogue trees of an adventure, pawns refer to them by name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DialogueBook {
+    pub trees: Vec<DialogueTree>,
+}
+
+impl DialogueBook {
+    pub fn load(file: &str) -> std::io::Result<Self> {
+        load_ron(file)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DialogueTree> {
+        self.trees.iter().find(|tree| tree.name == name)
+    }
+}
+
+/// What the window shows in its dialogue box, sent on the dialogue topic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueLine {
+    pub speaker: String,
+    pub portrait: String,
+    pub text: String,
+}
+
+pub struct Conversation;
+
+impl Conversation {
+    /// Walk the tree from its start until a node without choices or the player leaves.
+    pub fn run(speaker: &Rc<RefCell<Pawn>>,
+               npc: &Rc<RefCell<Pawn>>,
+               tree: &DialogueTree,
+               flags: &mut HashSet<String>,
+               senders: &HashMap<String, Sender<MessageContent>>,
+               menu: &Menu) -> std::io::Result<()> {
+        let npc_name = npc.borrow().name.clone();
+        let mut current = tree.node(tree.start.as_str());
+
+        while let Some(node) = current {
+            menu.log(LogCategoryEnum::DIALOGUE, format!("{} : {}", npc_name, node.text).as_str())?;
+            Self::send_dialogue_message(senders, Some(DialogueLine {
+                speaker: npc_name.clone(),
+                portrait: tree.portrait.clone(),
+                text: node.text.clone(),
+            }));
+
+            let mut ended = false;
+            for outcome in node.outcomes.iter() {
+                ended |= Self::apply(outcome, speaker, npc, flags, menu)?;
+            }
+
+            let choices = node.choices.iter()
+                .filter(|choice| choice.requires_flag.as_ref().map(|flag| flags.contains(flag)).unwrap_or(true))
+                .filter(|choice| !choice.unless_flag.as_ref().map(|flag| flags.contains(flag)).unwrap_or(false))
+                .collect::<Vec<&DialogueChoice>>();
+            if ended || choices.is_empty() {
+                break;
+            }
+
+            let options = choices.iter()
+                .map(|choice| match &choice.check {
+                    Some(check) => format!("[{}] {}", check.check, choice.text),
+                    None => choice.text.clone()
+                })
+                .chain(std::iter::once(LEAVE_OPTION.to_string()))
+                .collect::<Vec<String>>();
+            let choice = match menu.menu(options)?.and_then(|selected| choices.get(selected)) {
+                Some(choice) => *choice,
+                None => break
+            };
+            menu.log(LogCategoryEnum::DIALOGUE, format!("{} : {}", speaker.borrow().name, choice.text).as_str())?;
+
+            let next = match &choice.check {
+                Some(check) if !Self::roll_check(speaker, check, menu)? => &choice.failure,
+                _ => &choice.next
+            };
+            current = next.as_ref().and_then(|id| tree.node(id.as_str()));
+        }
+
+        Self::send_dialogue_message(senders, None);
+        Ok(())
+    }
+
+    /// d20 plus the characteristic modifier against the difficulty.
+    fn roll_check(speaker: &Rc<RefCell<Pawn>>, check: &Check, menu: &Menu) -> std::io::Result<bool> {
+        let characteristic = check.check.characteristic(&speaker.borrow());
+        let roll = Rules::ability_check(Dice::roll_1d20() as u8, characteristic);
+        let success = roll.hits(check.difficulty);
+        menu.log(LogCategoryEnum::DIALOGUE, format!("{} : {} vs DC {}, {}", check.check, roll, check.difficulty,
+                                                    if success { "success" } else { "failure" }).as_str())?;
+        Ok(success)
+    }
+
+    /// Returns true when the outcome ends the conversation.
+    fn apply(outcome: &OutcomeEnum, speaker: &Rc<RefCell<Pawn>>, npc: &Rc<RefCell<Pawn>>, flags: &mut HashSet<String>, menu: &Menu) -> std::io::Result<bool> {
+        match outcome {
+            OutcomeEnum::SET_FLAG(flag) => {
+                flags.insert(flag.clone());
+            }
+            OutcomeEnum::GIVE_ITEM(name) => {
+                match Initializer::item_named(name) {
+                    Some(item) => {
+                        menu.log(LogCategoryEnum::DIALOGUE, format!("{} receives {}", speaker.borrow().name, item.name).as_str())?;
+                        speaker.borrow_mut().inventory.add(Rc::new(item));
+                    }
+                    None => menu.write_line(format!("There is no item named {}", name).as_str())?
+                }
+            }
+            OutcomeEnum::TURN_HOSTILE => {
+                FACTIONS.provoke(speaker, npc, menu)?;
+                return Ok(true);
+            }
+            OutcomeEnum::JOIN_PARTY => {
+                let mut npc = npc.borrow_mut();
+                npc.dialogue = None;
+                npc.playable = true;
+                npc.owner = speaker.borrow().owner;
+                npc.faction = PLAYERS.to_string();
+                npc.grudges.clear();
+                *npc.ai.borrow_mut() = None;
+                menu.log(LogCategoryEnum::DIALOGUE, format!("{} joins the party", npc.name).as_str())?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// None closes the dialogue box.
+    fn send_dialogue_message(senders: &HashMap<String, Sender<MessageContent>>, line: Option<DialogueLine>) {
+        senders.get("dialogue").unwrap().send(MessageContent {
+            topic: "dialogue".to_string(),
+            content: bincode::serialize(&line).unwrap(),
+        }).unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use crate::services::resources::load_ron;
+
+/// What goes wrong when an attack roll is a natural 1.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FumbleEnum {
+    NOTHING,
+    DROP_WEAPON,
+    HIT_ALLY,
+    LOSE_NEXT_ACTION,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FumbleEntry {
+    pub effect: FumbleEnum,
+    pub weight: u8,
+}
+
+/// Fumbles of an adventure, the heavier an entry the likelier it is drawn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FumbleTable {
+    pub entries: Vec<FumbleEntry>,
+}
+
+impl Default for FumbleTable {
+    fn default() -> Self {
+        Self {
+            entries: vec![
+                FumbleEntry { effect: FumbleEnum::NOTHING, weight: 2 },
+                FumbleEntry { effect: FumbleEnum::DROP_WEAPON, weight: 1 },
+                FumbleEntry { effect: FumbleEnum::HIT_ALLY, weight: 1 },
+                FumbleEntry { effect: FumbleEnum::LOSE_NEXT_ACTION, weight: 1 },
+            ],
+        }
+    }
+}
+
+impl FumbleTable {
+    pub fn load(file: &str) -> std::io::Result<Self> {
+        load_ron(file)
+    }
+
+    pub fn roll(&self) -> FumbleEnum {
+        let total = self.entries.iter().map(|e| e.weight as u32).sum::<u32>();
+        if total == 0 {
+            return FumbleEnum::NOTHING;
+        }
+
+        let mut drawn = (rand::random::<f32>() * total as f32) as u32;
+        for entry in self.entries.iter() {
+            if drawn < entry.weight as u32 {
+                return entry.effect;
+            }
+            drawn -= entry.weight as u32;
+        }
+        FumbleEnum::NOTHING
+    }
+}
@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender};
+use crate::ai::ai::let_ai_or_human_play;
+use crate::gui::menu::Menu;
+use crate::pawn::pawn::Pawn;
+use crate::pawn::turn::ActionCostEnum;
+use crate::services::messaging::MessageContent;
+
+/// Last entry of every menu opened during an action.
+pub const BACK_OPTION: &str = "Back";
+
+/// Steps of an action, any of them can go back to the action menu.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum InteractionStateEnum {
+    STARTED,
+    CHOOSING,
+    TARGETING,
+    CANCELLED,
+}
+
+/// Action being played by a pawn, it keeps what has been spent so a cancelled action costs nothing.
+#[derive(Debug, Clone)]
+pub struct Interaction {
+    state: InteractionStateEnum,
+    spent: Vec<ActionCostEnum>,
+}
+
+impl Interaction {
+    pub fn new() -> Self {
+        Self {
+            state: InteractionStateEnum::STARTED,
+            spent: vec![],
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state == InteractionStateEnum::CANCELLED
+    }
+
+    pub fn spend(&mut self, pawn: &Rc<RefCell<Pawn>>, cost: ActionCostEnum) {
+        pawn.borrow_mut().turn_budget.spend(&cost);
+        self.spent.push(cost);
+    }
+
+    pub fn cancel(&mut self) {
+        self.state = InteractionStateEnum::CANCELLED;
+    }
+
+    /// Nothing could be done, a human player gets back what was spent.
+    /// An AI keeps it spent, it would only pick the same action again.
+    pub fn nothing_done(&mut self, pawn: &Rc<RefCell<Pawn>>) {
+        if pawn.borrow().playable {
+            self.cancel();
+        }
+    }
+
+    /// A menu ending with a back entry, none when the player went back or closed it.
+    /// An AI finding nothing to choose does not cancel, it only did nothing.
+    pub fn choose<F>(&mut self, pawn: &Rc<RefCell<Pawn>>, menu: &Menu, options: &Vec<String>, ai_choice: F) -> std::io::Result<Option<usize>> where F: Fn() -> Option<usize> {
+        self.state = InteractionStateEnum::CHOOSING;
+        let with_back = options.iter()
+            .cloned()
+            .chain(std::iter::once(BACK_OPTION.to_string()))
+            .collect::<Vec<String>>();
+
+        let selected = let_ai_or_human_play(pawn.clone(), || menu.menu(with_back.clone()), || Ok(ai_choice()))?;
+        let selected = selected.filter(|&s| s < options.len());
+        if selected.is_none() && pawn.borrow().playable {
+            self.cancel();
+        }
+        Ok(selected)
+    }
+
+    /// The tile picked in the window, none once cancelled with escape or a right click.
+    pub fn target(&mut self, range: &Vec<Vec<bool>>, senders: &HashMap<String, Sender<MessageContent>>, receivers: &HashMap<String, Receiver<MessageContent>>) -> Option<(u16, u16)> {
+        self.state = InteractionStateEnum::TARGETING;
+        senders.get("targetable").unwrap().send(MessageContent {
+            topic: "targetable".to_string(),
+            content: bincode::serialize(range).unwrap(),
+        }).unwrap();
+
+        self.wait_target(receivers)
+    }
+
+    /// Wait for a tile without showing a range, as when watching.
+    pub fn wait_target(&mut self, receivers: &HashMap<String, Receiver<MessageContent>>) -> Option<(u16, u16)> {
+        self.state = InteractionStateEnum::TARGETING;
+        let info_receiver = receivers.get("info").unwrap();
+        let selected: Option<(u16, u16)> = loop {
+            if let Ok(info) = info_receiver.try_recv() {
+                break bincode::deserialize(info.content.as_slice()).unwrap();
+            }
+        };
+        if selected.is_none() {
+            self.cancel();
+        }
+        selected
+    }
+
+    /// Once the action is over, a cancelled one gives back everything it spent.
+    pub fn settle(self, pawn: &Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<bool> {
+        if !self.is_cancelled() {
+            return Ok(false);
+        }
+        for cost in self.spent.iter() {
+            pawn.borrow_mut().turn_budget.refund(cost);
+        }
+        menu.write_line(format!("{} goes back to the action menu", pawn.borrow().name).as_str())?;
+        Ok(true)
+    }
+}
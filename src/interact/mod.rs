@@ -1 +1,5 @@
-pub mod actions;
\ No newline at end of file
+pub mod actions;
+pub mod dialogue;
+pub mod fumble;
+pub mod interaction;
+pub mod reactions;
@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use crate::ai::ai::let_ai_or_human_play;
+use crate::environment::world::Place;
+use crate::gui::combat_log::LogCategoryEnum;
+use crate::gui::menu::Menu;
+use crate::interact::fumble::FumbleTable;
+use crate::inventory::item::{ItemAttackTypeEnum, Pocketable, Spell};
+use crate::interact::actions::Actions;
+use crate::pawn::faction::FACTIONS;
+use crate::pawn::pawn::{Pawn, Position};
+use crate::pawn::turn::ActionCostEnum;
+use crate::services::a_star::line_of_sight;
+
+/// Armor granted by the shield spell until the start of the caster next turn.
+pub const SHIELD_ARMOR_BONUS: u8 = 5;
+
+/// What a pawn can do out of its own turn, each one spends its reaction.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ReactionEnum {
+    OPPORTUNITY_ATTACK,
+    SHIELD,
+    COUNTERSPELL,
+}
+
+impl Display for ReactionEnum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReactionEnum::OPPORTUNITY_ATTACK => write!(f, "an opportunity attack"),
+            ReactionEnum::SHIELD => write!(f, "a shield"),
+            ReactionEnum::COUNTERSPELL => write!(f, "a counterspell"),
+        }
+    }
+}
+
+pub struct Reactions;
+
+impl Reactions {
+    /// Enemies the pawn is moving away from get a free strike with their melee weapon.
+    pub fn on_leave_reach(mover: &Rc<RefCell<Pawn>>, to: &Position, place: &mut Place, fumbles: &FumbleTable, menu: &Menu) -> std::io::Result<()> {
+        let (mover_id, from) = (mover.borrow().id, mover.borrow().position.clone());
+
+        let reactors = place.pawns.clone();
+        for reactor in reactors.iter() {
+            if mover.borrow().is_dead() {
+                break;
+            }
+            if reactor.borrow().id == mover_id || !Self::is_hostile(&reactor.borrow(), &mover.borrow()) {
+                continue;
+            }
+            let weapon = match Self::melee_weapon(&reactor.borrow()) {
+                Some(weapon) => weapon,
+                None => continue
+            };
+            let reach = weapon.get_range().unwrap_or(1);
+            if Self::distance(&reactor.borrow().position, &from) > reach || Self::distance(&reactor.borrow().position, to) <= reach {
+                continue;
+            }
+
+            let question = format!("{} leaves your reach, attack it?", mover.borrow().name);
+            if !Self::react(reactor, ReactionEnum::OPPORTUNITY_ATTACK, &question, || true, menu)? {
+                continue;
+            }
+            menu.log(LogCategoryEnum::ATTACK, "Roll 1d20 : ")?;
+            Actions::roll_dice_attack(reactor.clone(), weapon, mover.clone(), 0, false, place, fumbles, menu)?;
+        }
+        Ok(())
+    }
+
+    /// The target of a hit may raise a shield, returns the armor it gains.
+    pub fn on_attacked(target: &Rc<RefCell<Pawn>>, attack_result: u8, armor: u8, menu: &Menu) -> std::io::Result<u8> {
+        let spell = match Self::reaction_spell(&target.borrow(), ReactionEnum::SHIELD) {
+            Some(spell) => spell,
+            None => return Ok(0)
+        };
+
+        //The AI only spends its mana when the shield turns the hit into a miss
+        let question = format!("{} is about to be hit ({} vs armor {}), cast {}?", target.borrow().name, attack_result, armor, spell.name);
+        if !Self::react(target, ReactionEnum::SHIELD, &question, || attack_result < armor + SHIELD_ARMOR_BONUS, menu)? {
+            return Ok(0);
+        }
+        Self::cast(target, &spell, menu)?;
+        target.borrow_mut().shield = SHIELD_ARMOR_BONUS;
+        Ok(SHIELD_ARMOR_BONUS)
+    }
+
+    /// Enemies seeing the caster may counter its spell, returns true when the spell is lost.
+    pub fn on_spell_cast(caster: &Rc<RefCell<Pawn>>, spell_name: &str, pawns: &Vec<Rc<RefCell<Pawn>>>, place: &Place, menu: &Menu) -> std::io::Result<bool> {
+        let caster_id = caster.borrow().id;
+        let from = (caster.borrow().position.x, caster.borrow().position.y);
+
+        for reactor in pawns.iter() {
+            if reactor.borrow().id == caster_id || !Self::is_hostile(&reactor.borrow(), &caster.borrow()) {
+                continue;
+            }
+            let spell = match Self::reaction_spell(&reactor.borrow(), ReactionEnum::COUNTERSPELL) {
+                Some(spell) => spell,
+                None => continue
+            };
+            let position = (reactor.borrow().position.x, reactor.borrow().position.y);
+            if Self::distance(&reactor.borrow().position, &caster.borrow().position) > spell.range.unwrap_or(1) ||
+                !line_of_sight(position, from, &place.room) {
+                continue;
+            }
+
+            let question = format!("{} casts {}, counter it?", caster.borrow().name, spell_name);
+            if !Self::react(reactor, ReactionEnum::COUNTERSPELL, &question, || true, menu)? {
+                continue;
+            }
+            Self::cast(reactor, &spell, menu)?;
+            menu.log(LogCategoryEnum::ATTACK, format!("{} of {} is countered", spell_name, caster.borrow().name).as_str())?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Human players are asked through the menu, AI pawns use their own decision.
+    fn react<F>(reactor: &Rc<RefCell<Pawn>>, reaction: ReactionEnum, question: &str, ai_decision: F, menu: &Menu) -> std::io::Result<bool> where F: Fn() -> bool {
+        if !reactor.borrow().turn_budget.can_afford(&ActionCostEnum::REACTION) {
+            return Ok(false);
+        }
+
+        let decision = let_ai_or_human_play(reactor.clone(),
+                                            || -> std::io::Result<bool> {
+                                                //The reactor may belong to another player than the pawn playing
+                                                let playing = menu.owner();
+                                                menu.set_owner(reactor.borrow().owner);
+                                                menu.write_line(question)?;
+                                                let selected = menu.menu(vec!["Yes".to_string(), "No".to_string()]);
+                                                menu.set_owner(playing);
+                                                Ok(selected? == Some(0))
+                                            },
+                                            || Ok(ai_decision()))?;
+        if decision {
+            reactor.borrow_mut().turn_budget.spend(&ActionCostEnum::REACTION);
+            menu.log(LogCategoryEnum::ATTACK, format!("{} reacts with {}", reactor.borrow().name, reaction).as_str())?;
+        }
+        Ok(decision)
+    }
+
+    fn cast(reactor: &Rc<RefCell<Pawn>>, spell: &Rc<Spell>, menu: &Menu) -> std::io::Result<()> {
+        let mut reactor = reactor.borrow_mut();
+        reactor.mana -= spell.mana;
+        menu.log(LogCategoryEnum::ATTACK, format!("{} casts {}", reactor.name, spell.name).as_str())
+    }
+
+    /// A known reaction spell the pawn has enough mana for.
+    fn reaction_spell(pawn: &Pawn, reaction: ReactionEnum) -> Option<Rc<Spell>> {
+        if pawn.is_dead() {
+            return None;
+        }
+        pawn.spell.iter()
+            .find(|spell| spell.reaction == Some(reaction) && spell.mana <= pawn.mana)
+            .cloned()
+    }
+
+    fn melee_weapon(pawn: &Pawn) -> Option<Rc<dyn Pocketable>> {
+        if pawn.is_dead() {
+            return None;
+        }
+        pawn.equipped.right_hand.clone()
+            .filter(|weapon| matches!(weapon.attack_type, Some(ItemAttackTypeEnum::CONTACT)))
+            .map(|weapon| weapon as Rc<dyn Pocketable>)
+    }
+
+    fn is_hostile(pawn: &Pawn, other: &Pawn) -> bool {
+        FACTIONS.is_hostile(pawn, other)
+    }
+
+    fn distance(from: &Position, to: &Position) -> u16 {
+        (from.x as i32 - to.x as i32).abs().max((from.y as i32 - to.y as i32).abs()) as u16
+    }
+}
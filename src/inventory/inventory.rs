@@ -0,0 +1,268 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use crate::inventory::item::{Item, PartToEquiEnum};
+use crate::pawn::pawn::Pawn;
+
+/// Several copies of the same stackable item are kept in one stack.
+#[derive(Debug, Clone)]
+pub struct ItemStack {
+    pub item: Rc<Item>,
+    pub quantity: u16,
+}
+
+impl ItemStack {
+    pub fn weight(&self) -> u32 {
+        self.item.weight as u32 * self.quantity as u32
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum InventorySortEnum {
+    NAME,
+    WEIGHT,
+    PART,
+}
+
+impl InventorySortEnum {
+    pub fn next(&self) -> Self {
+        match self {
+            InventorySortEnum::NAME => InventorySortEnum::WEIGHT,
+            InventorySortEnum::WEIGHT => InventorySortEnum::PART,
+            InventorySortEnum::PART => InventorySortEnum::NAME,
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum InventoryFilterEnum {
+    ALL,
+    WEAPONS,
+    ARMORS,
+    OTHERS,
+}
+
+impl InventoryFilterEnum {
+    pub fn next(&self) -> Self {
+        match self {
+            InventoryFilterEnum::ALL => InventoryFilterEnum::WEAPONS,
+            InventoryFilterEnum::WEAPONS => InventoryFilterEnum::ARMORS,
+            InventoryFilterEnum::ARMORS => InventoryFilterEnum::OTHERS,
+            InventoryFilterEnum::OTHERS => InventoryFilterEnum::ALL,
+        }
+    }
+
+    pub fn accept(&self, item: &Item) -> bool {
+        match self {
+            InventoryFilterEnum::ALL => true,
+            InventoryFilterEnum::WEAPONS => item.attack_type.is_some(),
+            InventoryFilterEnum::ARMORS => item.armor_point > 0,
+            InventoryFilterEnum::OTHERS => item.attack_type.is_none() && item.armor_point == 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    stacks: Vec<ItemStack>,
+}
+
+impl Inventory {
+    pub fn new(items: Vec<Rc<Item>>) -> Self {
+        let mut inventory = Inventory::default();
+        items.into_iter().for_each(|item| inventory.add(item));
+        inventory
+    }
+
+    /// Stackable items join the stack holding an item of the same name.
+    pub fn add(&mut self, item: Rc<Item>) {
+        self.add_stack(item, 1);
+    }
+
+    pub fn add_stack(&mut self, item: Rc<Item>, quantity: u16) {
+        if item.stackable {
+            if let Some(stack) = self.stacks.iter_mut().find(|s| s.item.name == item.name) {
+                stack.quantity = stack.quantity.saturating_add(quantity);
+                return;
+            }
+        }
+        self.stacks.push(ItemStack { item, quantity });
+    }
+
+    /// Take one copy of an item out, its stack is removed once empty.
+    pub fn remove(&mut self, item: &Rc<Item>) -> Option<Rc<Item>> {
+        let index = self.stacks.iter().position(|s| Rc::ptr_eq(&s.item, item))?;
+        let stack = self.stacks.get_mut(index).unwrap();
+        stack.quantity -= 1;
+        let item = stack.item.clone();
+        if stack.quantity == 0 {
+            self.stacks.remove(index);
+        }
+        Some(item)
+    }
+
+    pub fn contains(&self, item: &Rc<Item>) -> bool {
+        self.stacks.iter().any(|s| Rc::ptr_eq(&s.item, item))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&ItemStack> {
+        self.stacks.get(index)
+    }
+
+    pub fn stacks(&self) -> &Vec<ItemStack> {
+        &self.stacks
+    }
+
+    pub fn items(&self) -> Vec<Rc<Item>> {
+        self.stacks.iter().map(|s| s.item.clone()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.stacks.iter().map(|s| s.weight()).sum()
+    }
+
+    /// Indexes of the stacks kept by the filter, in the sort order.
+    pub fn view(&self, sort: InventorySortEnum, filter: InventoryFilterEnum) -> Vec<usize> {
+        let mut indexes = self.stacks.iter()
+            .enumerate()
+            .filter(|(_, s)| filter.accept(&s.item))
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+
+        indexes.sort_by(|&a, &b| {
+            let (a, b) = (&self.stacks[a], &self.stacks[b]);
+            match sort {
+                InventorySortEnum::NAME => a.item.name.cmp(&b.item.name),
+                InventorySortEnum::WEIGHT => b.weight().cmp(&a.weight()),
+                InventorySortEnum::PART => a.item.part_to_equip.to_string().cmp(&b.item.part_to_equip.to_string())
+                    .then(a.item.name.cmp(&b.item.name)),
+            }
+        });
+        indexes
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryEntry {
+    pub index: usize,
+    pub name: String,
+    pub quantity: u16,
+    pub icon: String,
+    pub part: PartToEquiEnum,
+    pub equipped: bool,
+    pub details: String,
+    pub previews: Vec<(PartToEquiEnum, String)>,
+    /// Companion whose bag the item is in, when the party shares its bags.
+    #[serde(default)]
+    pub holder: Option<String>,
+}
+
+impl InventoryEntry {
+    pub fn label(&self) -> String {
+        let quantity = if self.quantity > 1 { format!(" x{}", self.quantity) } else { String::new() };
+        let equipped = if self.equipped { " (equipped)" } else { "" };
+        let holder = self.holder.as_ref().map(|holder| format!(" (from {})", holder)).unwrap_or_default();
+        format!("{}{}{}{}", self.name, quantity, equipped, holder)
+    }
+}
+
+/// What the inventory screen shows of a pawn, sent to the UI thread.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryView {
+    pub owner: String,
+    pub gold: u32,
+    pub weight: u32,
+    pub capacity: u32,
+    pub sort: InventorySortEnum,
+    pub filter: InventoryFilterEnum,
+    pub entries: Vec<InventoryEntry>,
+    pub slots: Vec<(PartToEquiEnum, Option<String>)>,
+}
+
+impl InventoryView {
+    pub fn of(pawn: &Pawn, sort: InventorySortEnum, filter: InventoryFilterEnum) -> Self {
+        let equipped = pawn.equipped.get_all_props();
+        let entries = pawn.inventory.view(sort, filter).into_iter()
+            .map(|index| {
+                let stack = pawn.inventory.get(index).unwrap();
+                let worn = equipped.values().any(|e| e.as_ref().map(|e| Rc::ptr_eq(e, &stack.item)).unwrap_or(false));
+                Self::entry(pawn, index, stack, worn)
+            })
+            .collect::<Vec<InventoryEntry>>();
+
+        let slots = PartToEquiEnum::all().into_iter()
+            .map(|part| {
+                let item = equipped.get(&part).and_then(|e| e.as_ref()).map(|e| e.name.clone());
+                (part, item)
+            })
+            .collect();
+
+        InventoryView {
+            owner: pawn.name.clone(),
+            gold: pawn.gold,
+            weight: pawn.inventory.weight(),
+            capacity: pawn.carry_capacity(),
+            sort,
+            filter,
+            entries,
+            slots,
+        }
+    }
+
+    /// The pawn's own bag followed by the stacks its companions lend, numbered after its own stacks.
+    pub fn pooled(pawn: &Pawn, lent: &Vec<(Rc<RefCell<Pawn>>, usize)>, sort: InventorySortEnum, filter: InventoryFilterEnum) -> Self {
+        let mut view = Self::of(pawn, sort, filter);
+        let first = pawn.inventory.stacks().len();
+        for (offset, (member, index)) in lent.iter().enumerate() {
+            let member = member.borrow();
+            let mut entry = Self::entry(pawn, first + offset, member.inventory.get(*index).unwrap(), false);
+            entry.details = format!("{}\nIn the bag of {}", entry.details, member.name);
+            entry.holder = Some(member.name.clone());
+            view.entries.push(entry);
+        }
+        view
+    }
+
+    fn entry(pawn: &Pawn, index: usize, stack: &ItemStack, equipped: bool) -> InventoryEntry {
+        InventoryEntry {
+            index,
+            name: stack.item.name.clone(),
+            quantity: stack.quantity,
+            icon: stack.item.icon().to_string(),
+            part: stack.item.part_to_equip.clone(),
+            equipped,
+            details: stack.item.details(),
+            previews: stack.item.allowed_parts().into_iter()
+                .map(|part| {
+                    let preview = pawn.equip_preview(&stack.item, part.clone())
+                        .map(|preview| preview.to_string())
+                        .unwrap_or_else(|error| error.to_string());
+                    (part, preview)
+                })
+                .collect(),
+            holder: None,
+        }
+    }
+
+    pub fn header(&self) -> String {
+        format!("{} carries {}/{} ({} gold) - sorted by {:?}, showing {:?}",
+                self.owner, self.weight, self.capacity, self.gold, self.sort, self.filter)
+    }
+}
+
+/// What the player did on the inventory screen.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InventoryCommandEnum {
+    EQUIP(usize, PartToEquiEnum),
+    UNEQUIP(PartToEquiEnum),
+    SORT(InventorySortEnum),
+    FILTER(InventoryFilterEnum),
+    CLOSE,
+}
@@ -1,8 +1,9 @@
 use std::fmt::{Display, Formatter};
 use serde::{Deserialize, Serialize};
+use crate::interact::reactions::ReactionEnum;
 use crate::pawn::pawn::Characteristics;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum DamageTypeEnum {
     PIERCING,
     SLASHING,
@@ -11,15 +12,49 @@ pub enum DamageTypeEnum {
     FIRE,
     ICE,
     HEAL,
+    POISON,
 }
 
-#[derive(Debug, Clone)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AmmunitionEnum {
+    ARROW,
+    BOLT,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ItemAttackTypeEnum {
     CONTACT,
     DISTANCE,
     MAGIC
 }
 
+/// How heavy a worn armor is, the heavier the less dexterity helps to dodge.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ArmorTypeEnum {
+    LIGHT,
+    MEDIUM,
+    HEAVY,
+    SHIELD,
+}
+
+impl ArmorTypeEnum {
+    /// Highest dexterity modifier added to the armor class, none when it is not capped.
+    pub fn dexterity_cap(&self) -> Option<i16> {
+        match self {
+            ArmorTypeEnum::LIGHT | ArmorTypeEnum::SHIELD => None,
+            ArmorTypeEnum::MEDIUM => Some(2),
+            ArmorTypeEnum::HEAVY => Some(0),
+        }
+    }
+}
+
+/// Ranged weapons shoot without penalty up to half of their range.
+pub fn short_range(range: u16) -> u16 {
+    (range / 2).max(1)
+}
+
 pub trait Pocketable {
     fn get_damages(&self) -> u8;
 
@@ -29,6 +64,11 @@ pub trait Pocketable {
 
     fn get_requirements(&self) -> &Characteristics;
 
+    /// Added to the attack rolls made with it.
+    fn get_attack_bonus(&self) -> u8 {
+        0
+    }
+
     fn calculate_usability(&self, pawn_charac: &Characteristics, mana: Option<u8>) -> u8 {
         let adjustment_value = 2;
         let mut result = 0;
@@ -127,7 +167,7 @@ pub trait Pocketable {
 
 }
 #[warn(non_camel_case_types)]
-#[derive(Debug, Clone,Eq, Hash, PartialEq)]
+#[derive(Debug, Clone,Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum PartToEquiEnum {
     HEAD,
     RIGHT_HAND,
@@ -137,6 +177,17 @@ pub enum PartToEquiEnum {
     FEET
 }
 
+impl PartToEquiEnum {
+    pub fn all() -> Vec<PartToEquiEnum> {
+        vec![PartToEquiEnum::HEAD,
+             PartToEquiEnum::RIGHT_HAND,
+             PartToEquiEnum::LEFT_HAND,
+             PartToEquiEnum::BODY,
+             PartToEquiEnum::LEGS,
+             PartToEquiEnum::FEET]
+    }
+}
+
 impl Display for PartToEquiEnum{
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -153,8 +204,93 @@ pub struct Item {
     pub damages_type: Option<DamageTypeEnum>,
     pub part_to_equip: PartToEquiEnum,
     pub armor_point: u8,
+    pub armor_type: Option<ArmorTypeEnum>,
     pub attack_type: Option<ItemAttackTypeEnum>,
-    pub range: Option<u16>
+    pub range: Option<u16>,
+    pub weight: u16,
+    pub stackable: bool,
+    pub two_handed: bool,
+    pub uses_ammunition: Option<AmmunitionEnum>,
+    pub ammunition: Option<AmmunitionEnum>,
+    pub reload: bool,
+    /// Lowers the natural roll needed for a critical hit.
+    pub critical_bonus: u8,
+    pub attack_bonus: u8,
+}
+
+impl Item {
+    /// Sprite shown for the item in the inventory panel.
+    pub fn icon(&self) -> &'static str {
+        if self.ammunition.is_some() {
+            "icon_ammunition"
+        } else if self.attack_type.is_some() {
+            "icon_weapon"
+        } else if self.armor_point > 0 {
+            "icon_armor"
+        } else {
+            "item_bag"
+        }
+    }
+
+    /// One handed weapons can be held in either hand, shields and armors only go where they are made for.
+    pub fn allowed_parts(&self) -> Vec<PartToEquiEnum> {
+        if self.ammunition.is_some() {
+            return vec![];
+        }
+        let in_hand = self.part_to_equip == PartToEquiEnum::RIGHT_HAND || self.part_to_equip == PartToEquiEnum::LEFT_HAND;
+        if in_hand && self.attack_type.is_some() && !self.two_handed {
+            vec![PartToEquiEnum::RIGHT_HAND, PartToEquiEnum::LEFT_HAND]
+        } else {
+            vec![self.part_to_equip.clone()]
+        }
+    }
+
+    pub fn details(&self) -> String {
+        let mut details = vec![self.name.clone()];
+        if let Some(ammunition) = self.ammunition {
+            details.push(format!("Ammunition ({:?})", ammunition));
+        } else {
+            details.push(format!("Worn on {}", self.part_to_equip));
+        }
+        if self.two_handed {
+            details.push("Two handed".to_string());
+        }
+        if let Some(ammunition) = self.uses_ammunition {
+            details.push(format!("Shoots {:?}{}", ammunition, if self.reload { ", reload after each shot" } else { "" }));
+        }
+        if let Some(damages_type) = &self.damages_type {
+            details.push(format!("Damages: {:?}", damages_type));
+        }
+        if let Some(range) = self.range {
+            if self.uses_ammunition.is_some() {
+                details.push(format!("Range: {} (short {})", range, short_range(range)));
+            } else {
+                details.push(format!("Range: {}", range));
+            }
+        }
+        if self.attack_bonus > 0 {
+            details.push(format!("Attack: +{}", self.attack_bonus));
+        }
+        if self.critical_bonus > 0 {
+            details.push(format!("Critical: {}-20", 20 - self.critical_bonus));
+        }
+        if self.armor_point > 0 {
+            match self.armor_type {
+                Some(armor_type) => details.push(format!("Armor: {} ({:?})", self.armor_point, armor_type)),
+                None => details.push(format!("Armor: {}", self.armor_point)),
+            }
+        }
+        let requirements = self.requirements.named()
+            .iter()
+            .filter(|(_, value)| *value > 0)
+            .map(|(name, value)| format!("{} {}", name, value))
+            .collect::<Vec<String>>();
+        if !requirements.is_empty() {
+            details.push(format!("Requires: {}", requirements.join(", ")));
+        }
+        details.push(format!("Weight: {}", self.weight));
+        details.join("\n")
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -169,7 +305,8 @@ pub struct Spell {
     pub power_up: Option<Characteristics>,
     pub damages_type: Option<DamageTypeEnum>,
     pub attack_type: Option<ItemAttackTypeEnum>,
-    pub range: Option<u16>
+    pub range: Option<u16>,
+    pub reaction: Option<ReactionEnum>
 }
 
 impl Pocketable for Item {
@@ -209,6 +346,10 @@ impl Pocketable for Item {
     fn get_range(&self) -> Option<u16> {
         self.range
     }
+
+    fn get_attack_bonus(&self) -> u8 {
+        self.attack_bonus
+    }
 }
 
 impl Pocketable for Spell {
@@ -0,0 +1,22 @@
+use rand::Rng;
+use crate::inventory::item::Item;
+
+/// What a creature carries when it is created, each item has its own chance to be there.
+#[derive(Debug, Clone)]
+pub struct LootTable {
+    pub gold: (u32, u32),
+    pub items: Vec<(Item, f32)>,
+}
+
+impl LootTable {
+    pub fn roll(&self) -> (u32, Vec<Item>) {
+        let mut rng = rand::thread_rng();
+        let gold = rng.gen_range(self.gold.0..=self.gold.1);
+        let items = self.items.iter()
+            .filter(|(_, chance)| rng.gen::<f32>() < *chance)
+            .map(|(item, _)| item.clone())
+            .collect::<Vec<Item>>();
+
+        (gold, items)
+    }
+}
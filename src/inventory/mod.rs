@@ -1 +1,3 @@
-pub mod item;
\ No newline at end of file
+pub mod item;
+pub mod loot;
+pub mod inventory;
\ No newline at end of file
@@ -5,99 +5,134 @@ use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
-use crate::environment::world::World;
-use crate::gui::graphical::sprite::{Layer, ObjectToSprite, Sprite};
+use crate::environment::tile::TILES;
+use crate::environment::world::{Place, RoomTransition, Weather, World};
+use crate::inventory::item::DamageTypeEnum;
+use crate::gui::graphical::sprite::{Layer, ObjectToSprite, PawnStatus, Sprite};
+use crate::gui::console::map;
+use crate::gui::combat_log::LogCategoryEnum;
 use crate::gui::menu::Menu;
 use crate::interact::actions::Actions;
-use crate::pawn::pawn::{Characteristics, Pawn, Position};
+use crate::pawn::party::{Party, PartySettings};
+use crate::pawn::pawn::Pawn;
+use crate::services::dungeon_generator::{DungeonGenerator, DungeonSettings};
 use crate::services::initializer::Initializer;
 use crate::services::messaging::MessageContent;
+use crate::logic::hot_seat::HotSeat;
+use crate::logic::quest::QuestEventEnum;
+
+/// Seeds tried one after the other when a generated dungeon cannot be played.
+const DUNGEON_SEED_ATTEMPTS: u64 = 10;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+pub enum GameMode {
+    ADVENTURE,
+    DUNGEON { seed: u64, dump_layouts: bool },
+}
+
+impl GameMode {
+    /// `--dungeon [seed]` starts a generated dungeon, a random seed is picked when none is given.
+    /// `--dump-layouts` also prints the rooms of the dungeon so designers can start from them.
+    pub fn from_args(args: Vec<String>) -> Self {
+        if let Some(index) = args.iter().position(|arg| arg == "--dungeon") {
+            let seed = args.get(index + 1)
+                .and_then(|seed| seed.parse::<u64>().ok())
+                .unwrap_or_else(rand::random::<u64>);
+            GameMode::DUNGEON { seed, dump_layouts: args.iter().any(|arg| arg == "--dump-layouts") }
+        } else {
+            GameMode::ADVENTURE
+        }
+    }
+}
 
 pub struct GameLoop {}
 
 impl GameLoop {
-    pub fn iterate(senders: HashMap<String, Sender<MessageContent>>, receivers: HashMap<String, Receiver<MessageContent>>, menu: Menu) {
+    pub fn iterate(senders: HashMap<String, Sender<MessageContent>>, receivers: HashMap<String, Receiver<MessageContent>>, menu: Menu, game_mode: GameMode, party_file: String, shared_screen: bool) {
         thread::spawn(move || {
             let menu = menu;
-            let spells = Initializer::generate_spells();
+            let settings = PartySettings::load(party_file.as_str()).unwrap();
+            let members = settings.create_members();
 
+            //Weapons the party carries are not handed out to the creatures
             let mut items = Initializer::generate_items();
-
-            let player1 = Rc::new(RefCell::new(Pawn {
-                id: idgenerator::IdInstance::next_id(),
-                name: "Toto".to_string(),
-                life: 100,
-                spell: vec![spells.get(0).unwrap().clone()],
-                race: "human".to_string(),
-                inventory: vec![Rc::new(items.remove(0))],
-                mana: 100,
-                characteristics: Characteristics {
-                    force: 3,
-                    dexterity: 3,
-                    constitution: 0,
-                    intelligence: 3,
-                    willpower: 0,
-                    charisma: 0,
-                },
-                playable: true,
-                equipped: Default::default(),
-                ai: Rc::new(RefCell::new(None)),
-                position: Position { x: 4, y: 3 },
-            }));
+            items.retain(|item| !members.iter().any(|member| member.borrow().inventory.items().iter().any(|carried| carried.name == item.name)));
 
             let weather_list = Initializer::init_weather();
-            let world = Initializer::init(&weather_list, player1.clone(), &mut items);
+            let mut world = match game_mode {
+                GameMode::ADVENTURE => Initializer::init(&weather_list, &members, settings.party.clone(), &mut items),
+                GameMode::DUNGEON { seed, dump_layouts } => match GameLoop::generate_dungeon(seed, &weather_list, &members, &settings, &menu) {
+                    Ok(world) => {
+                        if dump_layouts {
+                            world.places.iter()
+                                .for_each(|place| println!("{}\n{}", place.name, map::render_room(&place.room)));
+                        }
+                        world
+                    }
+                    Err(error) => {
+                        menu.log(LogCategoryEnum::SYSTEM, format!("No dungeon could be generated: {}", error).as_str()).unwrap();
+                        return;
+                    }
+                }
+            };
+            world.hot_seat.shared_screen = shared_screen;
+            let start_place_id = world.find_pawn_place(members.first().unwrap().borrow().id).unwrap();
 
 
             //Travel threw places
-            GameLoop::loop_handler(&world, senders, receivers,&menu).unwrap();
+            GameLoop::loop_handler(&mut world, start_place_id, senders, receivers,&menu).unwrap();
         });
     }
 
-    fn loop_handler(world: &World,
+    /// A seed giving a broken dungeon is reported and the next one is tried instead.
+    fn generate_dungeon(seed: u64, weathers: &Vec<Rc<Weather>>, members: &Vec<Rc<RefCell<Pawn>>>, settings: &PartySettings, menu: &Menu) -> std::io::Result<World> {
+        let mut attempt = 0;
+        loop {
+            let seed = seed.wrapping_add(attempt);
+            println!("Generating dungeon with seed {}", seed);
+            match DungeonGenerator::new(DungeonSettings::new(seed)).generate(weathers, members, settings.party.clone()) {
+                Ok(world) => return Ok(world),
+                Err(error) if attempt + 1 < DUNGEON_SEED_ATTEMPTS => {
+                    menu.log(LogCategoryEnum::SYSTEM, format!("Dungeon of seed {} cannot be played: {}", seed, error).as_str())?;
+                    attempt += 1;
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    fn loop_handler(world: &mut World,
+                    start_place_id: u8,
                     senders: HashMap<String, Sender<MessageContent>>,
                     receivers: HashMap<String, Receiver<MessageContent>>,
                     menu: &Menu) -> std::io::Result<()> {
         let senders = senders;
-        let mut current_place_id: u8 = 12;
+        let mut current_place_id: u8 = start_place_id;
+        //Quests without a starting flag are given right away
+        world.update_quests(vec![], None, menu)?;
         loop {
             println!("current place id {}", current_place_id);
-            let world_current_place = world.places.iter().find(|&r| r.id == current_place_id).unwrap();
-            menu.write_line(format!("You arrived in {}", world_current_place.name).as_str()).unwrap();
+            let world_current_place = world.get_place(current_place_id).unwrap();
+            menu.log(LogCategoryEnum::MOVEMENT, format!("You arrived in {}", world_current_place.name).as_str()).unwrap();
+            //Hot-seat players only see the place threw the fog
+            #[cfg(not(feature = "graphical_mode"))]
+            if !world.hot_seat.enabled {
+                menu.write_line(map::render_place(world_current_place, &world.hot_seat).as_str())?;
+            }
 
-            let pawns: &Vec<Rc<RefCell<Pawn>>> = &world_current_place.pawns;
+            let pawns: &Vec<Rc<RefCell<Pawn>>> = &world_current_place.pawns.clone();
 
-            let room_tiles = world_current_place.room.iter()
-                .enumerate()
-                .map(|(row, cols)| cols.iter()
-                    .enumerate()
-                    .map(|(col, &el)| Sprite::new(el, col as i32, row as i32, Layer::BACKGROUND))
-                    .collect::<Vec<Sprite>>())
-                .flatten()
-                .collect::<Vec<Sprite>>();
-
-            let pawns_sprites = [pawns.iter().map(|p| {
-                p.clone().borrow().get_world_origin()
-            })
-                .flatten()
-                .collect::<Vec<Sprite>>(),
-                room_tiles.clone()]
-                .concat();
-
-
-            let message_content = MessageContent {
-                topic: "sprite".to_string(),
-                content: bincode::serialize(&pawns_sprites).unwrap(),
-            };
-
-            let sender = senders.get("sprite").unwrap();
-            sender.send(message_content).unwrap();
+            Self::send_place_sprites(world_current_place, &world.hot_seat, &senders);
+            Self::send_fog(world, current_place_id, &senders, menu)?;
 
 
             // loop {
+                //Hot-seat players are only told about the creatures they see
+                let fog = world.fog(current_place_id);
                 let creatures = (&pawns)
                     .iter()
-                    .filter(|e| !e.borrow().playable)
+                    .filter(|e| !Party::is_member(&e.borrow()) && world.hot_seat.shows(fog.as_ref(), &e.borrow().position))
                     .map(|c| {
                         c.borrow().name.clone()
                     })
@@ -108,27 +143,132 @@ impl GameLoop {
                                         creatures.join(", ")
                 ).as_str())?;
 
-                Actions::handle_actions(&Self::order_pawns(pawns)?, world, &mut current_place_id, &receivers, &senders, menu)?;
+                let mut transition = None;
+                Actions::handle_actions(&Self::order_pawns(pawns)?, world, current_place_id, &mut transition, &receivers, &senders, menu)?;
+
+                Self::spread_gas(world, current_place_id, &senders, menu)?;
 
                 menu.clear_line()?;
 
-                let sprites = [pawns.iter()
-                    .map(|p: &Rc<RefCell<Pawn>>| p.clone().borrow().get_world_origin())
-                    .flatten()
-                    .collect::<Vec<Sprite>>(),
-                    room_tiles.clone()
-                ].concat();
+                Self::send_place_sprites(world.get_place(current_place_id).unwrap(), &world.hot_seat, &senders);
 
+                if let Some(transition) = transition {
+                    current_place_id = Self::change_room(world, &transition, &senders, menu)?;
+                }
+            // }
+        }
+    }
 
-                let message_content = MessageContent {
-                    topic: "sprite".to_string(),
-                    content: bincode::serialize(&sprites).unwrap(),
-                };
+    /// Send everything drawn for a place to the UI: room tiles, known traps, ground items and pawns.
+    pub fn send_place_sprites(place: &Place, hot_seat: &HotSeat, senders: &HashMap<String, Sender<MessageContent>>) {
+        let room_tiles = place.room.iter()
+            .enumerate()
+            .map(|(row, cols)| cols.iter()
+                .enumerate()
+                .map(|(col, &el)| Sprite::new(TILES.get(el).texture.as_str(), col as i32, row as i32, Layer::BACKGROUND))
+                .collect::<Vec<Sprite>>())
+            .flatten()
+            .collect::<Vec<Sprite>>();
 
-                let sender = senders.get("sprite").unwrap();
-                sender.send(message_content).unwrap();
-            // }
+        let sprites = [place.pawns.iter()
+            .map(|p: &Rc<RefCell<Pawn>>| p.clone().borrow().get_world_origin())
+            .flatten()
+            .collect::<Vec<Sprite>>(),
+            room_tiles,
+            Self::known_triggers_sprites(place, hot_seat),
+            Self::objects_sprites(place)
+        ].concat();
+
+        senders.get("sprite").unwrap().send(MessageContent {
+            topic: "sprite".to_string(),
+            content: bincode::serialize(&sprites).unwrap(),
+        }).unwrap();
+
+        let statuses = place.pawns.iter()
+            .map(|p| p.borrow().status())
+            .collect::<Vec<PawnStatus>>();
+        senders.get("pawn_status").unwrap().send(MessageContent {
+            topic: "pawn_status".to_string(),
+            content: bincode::serialize(&statuses).unwrap(),
+        }).unwrap();
+    }
+
+    /// What the seated player knows of the place, nothing is sent outside of hot-seat games.
+    /// The console shows the place again as that player knows it.
+    pub fn send_fog(world: &mut World, place_id: u8, senders: &HashMap<String, Sender<MessageContent>>, menu: &Menu) -> std::io::Result<()> {
+        let fog = match world.fog(place_id) {
+            Some(fog) => fog,
+            None => return Ok(())
+        };
+        #[cfg(not(feature = "graphical_mode"))]
+        menu.write_line(map::render_place_in_fog(world.get_place(place_id).unwrap(), &world.hot_seat, &fog).as_str())?;
+        #[cfg(feature = "graphical_mode")]
+        let _ = menu;
+
+        senders.get("fog").unwrap().send(MessageContent {
+            topic: "fog".to_string(),
+            content: bincode::serialize(&fog).unwrap(),
+        }).unwrap();
+        Ok(())
+    }
+
+    /// Life and mana of the party for the party panel, the playing pawn is marked.
+    pub fn send_party_statuses(pawns: &Vec<Rc<RefCell<Pawn>>>, current: i64, senders: &HashMap<String, Sender<MessageContent>>) {
+        senders.get("party").unwrap().send(MessageContent {
+            topic: "party".to_string(),
+            content: bincode::serialize(&Party::statuses(pawns, current)).unwrap(),
+        }).unwrap();
+    }
+
+    fn spread_gas(world: &mut World, place_id: u8, senders: &HashMap<String, Sender<MessageContent>>, menu: &Menu) -> std::io::Result<()> {
+        for (pawn, damages) in world.get_place_mut(place_id).unwrap().tick_gas_clouds() {
+            let pawn = pawn.borrow();
+            Actions::send_damage_type_message(senders, &DamageTypeEnum::POISON, &(pawn.position.x, pawn.position.y));
+            menu.log(LogCategoryEnum::DAMAGE, format!("{} chokes on poison gas and takes {} damages", pawn.name, damages).as_str())?;
         }
+        Ok(())
+    }
+
+    /// Traps are shown once one of the players has spotted them, in hot-seat games one of the seated player's pawns.
+    fn known_triggers_sprites(place: &Place, hot_seat: &HotSeat) -> Vec<Sprite> {
+        let players = place.pawns.iter()
+            .filter(|p| p.borrow().playable && hot_seat.shares_knowledge(&p.borrow()))
+            .map(|p| p.borrow().id)
+            .collect::<Vec<i64>>();
+
+        place.triggers.iter()
+            .filter(|t| t.armed && players.iter().any(|&id| t.is_known_by(id)))
+            .map(|t| Sprite::new("trap", t.position.x as i32, t.position.y as i32, Layer::BACKGROUND))
+            .collect::<Vec<Sprite>>()
+    }
+
+    /// Items lying on the ground, one bag per tile whatever the number of items.
+    fn objects_sprites(place: &Place) -> Vec<Sprite> {
+        let mut positions = place.objects.iter()
+            .map(|(position, _)| (position.x, position.y))
+            .collect::<Vec<(u16, u16)>>();
+        positions.sort();
+        positions.dedup();
+
+        positions.iter()
+            .map(|&(x, y)| Sprite::new("item_bag", x as i32, y as i32, Layer::OBJECTS))
+            .collect::<Vec<Sprite>>()
+    }
+
+    fn change_room(world: &mut World, transition: &RoomTransition, senders: &HashMap<String, Sender<MessageContent>>, menu: &Menu) -> std::io::Result<u8> {
+        world.move_party(transition.pawn_id, transition.from, &transition.exit)?;
+
+        let destination = world.get_place(transition.exit.destination).unwrap();
+        senders.get("change_room").unwrap().send(MessageContent {
+            topic: "change_room".to_string(),
+            content: bincode::serialize(&(transition.from, destination.id, destination.name.clone())).unwrap(),
+        }).unwrap();
+
+        let (destination_id, reached) = (destination.id, QuestEventEnum::REACHED(destination.name.clone()));
+        let traveller = destination.pawns.iter().find(|p| p.borrow().id == transition.pawn_id).cloned();
+        world.update_quests(vec![reached], traveller.as_ref(), menu)?;
+
+        Ok(destination_id)
     }
 
     fn order_pawns(pawns: &Vec<Rc<RefCell<Pawn>>>) -> std::io::Result<Vec<Rc<RefCell<Pawn>>>> {
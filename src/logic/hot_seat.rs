@@ -0,0 +1,202 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use serde::{Deserialize, Serialize};
+use crate::environment::world::Place;
+use crate::gui::menu::Menu;
+use crate::pawn::pawn::{Pawn, Position};
+use crate::services::a_star::line_of_sight;
+use crate::services::messaging::MessageContent;
+
+/// Tiles a pawn sees around it, walls stop the sight.
+pub const VISION_RANGE: u16 = 6;
+/// Only entry of the menu shown while the machine is handed over.
+pub const READY_OPTION: &str = "Ready";
+
+/// What a player knows of a tile, creatures and items are only shown on visible tiles.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FogEnum {
+    UNEXPLORED,
+    EXPLORED,
+    VISIBLE,
+}
+
+/// Humans sharing the machine, each one plays the pawns it owns and only sees what they see.
+/// It stays disabled when every pawn of the party belongs to the same player.
+#[derive(Debug, Clone, Default)]
+pub struct HotSeat {
+    pub enabled: bool,
+    //Over the network every player has its own screen, nothing needs to be hidden
+    pub shared_screen: bool,
+    //Player in front of the machine
+    pub seated: Option<u8>,
+    //Tiles seen so far, by player and place
+    explored: HashMap<(u8, u8), Vec<Vec<bool>>>,
+}
+
+impl HotSeat {
+    pub fn new(members: &Vec<Rc<RefCell<Pawn>>>) -> Self {
+        let mut owners = members.iter()
+            .map(|member| member.borrow().owner)
+            .collect::<Vec<u8>>();
+        owners.sort();
+        owners.dedup();
+        Self {
+            enabled: owners.len() > 1,
+            shared_screen: true,
+            ..Self::default()
+        }
+    }
+
+    /// When the pawn belongs to another player than the seated one, the screen is hidden
+    /// until the next player says it is ready. Players on their own screen take the seat right away.
+    pub fn hand_over(&mut self, pawn: &Rc<RefCell<Pawn>>, senders: &HashMap<String, Sender<MessageContent>>, menu: &Menu) -> std::io::Result<bool> {
+        let owner = pawn.borrow().owner;
+        if !self.enabled || !pawn.borrow().playable || self.seated == Some(owner) {
+            return Ok(false);
+        }
+        if !self.shared_screen {
+            self.seated = Some(owner);
+            return Ok(true);
+        }
+
+        Self::send_hand_off_message(senders, Some(owner));
+        menu.clear_screen()?;
+        menu.write_line(format!("Player {}'s turn, hand the machine over", owner).as_str())?;
+        while menu.menu(vec![READY_OPTION.to_string()])? != Some(0) {}
+        Self::send_hand_off_message(senders, None);
+
+        self.seated = Some(owner);
+        Ok(true)
+    }
+
+    /// What the seated player knows of the place, none outside of hot-seat games.
+    pub fn fog(&mut self, place: &Place) -> Option<Vec<Vec<FogEnum>>> {
+        let seated = self.seated.filter(|_| self.enabled)?;
+        let eyes = place.pawns.iter()
+            .map(|p| p.borrow())
+            .filter(|p| p.playable && p.owner == seated && !p.is_dead())
+            .map(|p| (p.position.x, p.position.y))
+            .collect::<Vec<(u16, u16)>>();
+
+        let explored = self.explored.entry((seated, place.id))
+            .or_insert_with(|| place.room.iter().map(|row| vec![false; row.len()]).collect());
+        let fog = place.room.iter()
+            .enumerate()
+            .map(|(y, row)| (0..row.len())
+                .map(|x| {
+                    let tile = (x as u16, y as u16);
+                    let visible = eyes.iter().any(|&eye| Self::distance(eye, tile) <= VISION_RANGE && line_of_sight(eye, tile, &place.room));
+                    if visible {
+                        explored[y][x] = true;
+                        FogEnum::VISIBLE
+                    } else if explored[y][x] {
+                        FogEnum::EXPLORED
+                    } else {
+                        FogEnum::UNEXPLORED
+                    }
+                })
+                .collect::<Vec<FogEnum>>())
+            .collect();
+        Some(fog)
+    }
+
+    /// Only the pawns of the seated player tell what they found, like spotted traps.
+    pub fn shares_knowledge(&self, pawn: &Pawn) -> bool {
+        !self.enabled || self.seated == Some(pawn.owner)
+    }
+
+    /// What stands on a tile is only shown when the seated player sees it.
+    pub fn shows(&self, fog: Option<&Vec<Vec<FogEnum>>>, position: &Position) -> bool {
+        !self.enabled || fog.and_then(|fog| fog.get(position.y as usize)?.get(position.x as usize)) == Some(&FogEnum::VISIBLE)
+    }
+
+    fn distance(from: (u16, u16), to: (u16, u16)) -> u16 {
+        (from.0 as i32 - to.0 as i32).abs().max((from.1 as i32 - to.1 as i32).abs()) as u16
+    }
+
+    /// Some player hides the screen, none shows it again.
+    fn send_hand_off_message(senders: &HashMap<String, Sender<MessageContent>>, player: Option<u8>) {
+        senders.get("hand_off").unwrap().send(MessageContent {
+            topic: "hand_off".to_string(),
+            content: bincode::serialize(&player).unwrap(),
+        }).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pawn::party::PartySettings;
+    use crate::services::initializer::Initializer;
+
+    /// A corridor of floor with a wall at x 4.
+    fn corridor_of(pawns: &Vec<Rc<RefCell<Pawn>>>) -> Place {
+        let mut room = vec![vec![10u8; 12]];
+        room[0][4] = 20;
+        Place {
+            id: 1,
+            name: "Corridor".to_string(),
+            weather: Initializer::init_weather().remove(0),
+            time: "Day".to_string(),
+            light: 255,
+            adjacent_places: vec![],
+            exits: vec![],
+            pawns: pawns.clone(),
+            objects: vec![],
+            triggers: vec![],
+            gas_clouds: vec![],
+            visited: true,
+            room,
+        }
+    }
+
+    fn row(fog: &Vec<Vec<FogEnum>>) -> String {
+        fog[0].iter()
+            .map(|tile| match tile {
+                FogEnum::UNEXPLORED => ' ',
+                FogEnum::EXPLORED => '~',
+                FogEnum::VISIBLE => 'o',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn there_is_no_fog_outside_of_a_seated_hot_seat_game() {
+        let members = PartySettings::load("hotseat.ron").unwrap().create_members();
+        let place = corridor_of(&members);
+
+        assert!(HotSeat::default().fog(&place).is_none());
+        let mut hot_seat = HotSeat::new(&members);
+        assert!(hot_seat.enabled);
+        assert!(hot_seat.fog(&place).is_none());
+        assert!(!HotSeat::new(&PartySettings::load("party.ron").unwrap().create_members()).enabled);
+    }
+
+    #[test]
+    fn players_see_around_their_own_pawns_and_remember_what_they_saw() {
+        let members = PartySettings::load("hotseat.ron").unwrap().create_members();
+        let (first, second) = (members[0].clone(), members[1].clone());
+        first.borrow_mut().position = Position { x: 0, y: 0 };
+        second.borrow_mut().position = Position { x: 11, y: 0 };
+        let place = corridor_of(&members);
+        let mut hot_seat = HotSeat::new(&members);
+
+        hot_seat.seated = Some(first.borrow().owner);
+        assert_eq!(row(&hot_seat.fog(&place).unwrap()), "ooooo       ");
+        hot_seat.seated = Some(second.borrow().owner);
+        assert_eq!(row(&hot_seat.fog(&place).unwrap()), "     ooooooo");
+
+        //Out of range, the walked corridor stays explored
+        first.borrow_mut().position = Position { x: 11, y: 0 };
+        hot_seat.seated = Some(first.borrow().owner);
+        let fog = hot_seat.fog(&place).unwrap();
+        assert_eq!(row(&fog), "~~~~~ooooooo");
+
+        assert!(hot_seat.shows(Some(&fog), &Position { x: 6, y: 0 }));
+        assert!(!hot_seat.shows(Some(&fog), &Position { x: 2, y: 0 }));
+        assert!(!hot_seat.shows(None, &Position { x: 6, y: 0 }));
+    }
+}
@@ -1 +1,3 @@
-pub mod game_loop;
\ No newline at end of file
+pub mod game_loop;
+pub mod hot_seat;
+pub mod quest;
\ No newline at end of file
@@ -0,0 +1,258 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use crate::gui::combat_log::LogCategoryEnum;
+use crate::gui::menu::Menu;
+use crate::pawn::faction::PLAYERS;
+use crate::pawn::party::Party;
+use crate::pawn::pawn::Pawn;
+use crate::services::initializer::Initializer;
+use crate::services::resources::load_ron;
+
+/// What a quest asks for, creatures are counted by race.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ObjectiveEnum {
+    KILL(String, u8),
+    REACH(String),
+    OBTAIN(String),
+    TALK(String),
+}
+
+impl ObjectiveEnum {
+    pub fn goal(&self) -> u8 {
+        match self {
+            ObjectiveEnum::KILL(_, count) => *count,
+            _ => 1
+        }
+    }
+
+    pub fn matches(&self, event: &QuestEventEnum) -> bool {
+        match (self, event) {
+            (ObjectiveEnum::KILL(race, _), QuestEventEnum::KILLED(killed)) => race == killed,
+            (ObjectiveEnum::REACH(place), QuestEventEnum::REACHED(reached)) => place == reached,
+            (ObjectiveEnum::OBTAIN(item), QuestEventEnum::OBTAINED(obtained)) => item == obtained,
+            (ObjectiveEnum::TALK(pawn), QuestEventEnum::TALKED(talked)) => pawn == talked,
+            _ => false
+        }
+    }
+}
+
+impl Display for ObjectiveEnum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectiveEnum::KILL(race, count) => write!(f, "Kill {} {}", count, race),
+            ObjectiveEnum::REACH(place) => write!(f, "Reach {}", place),
+            ObjectiveEnum::OBTAIN(item) => write!(f, "Obtain {}", item),
+            ObjectiveEnum::TALK(pawn) => write!(f, "Talk to {}", pawn),
+        }
+    }
+}
+
+/// Something that happened in the game, quests move forward on the ones matching their objectives.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum QuestEventEnum {
+    KILLED(String),
+    REACHED(String),
+    OBTAINED(String),
+    TALKED(String),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reward {
+    #[serde(default)]
+    pub experience: u32,
+    #[serde(default)]
+    pub gold: u32,
+    #[serde(default)]
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestDefinition {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    //World flag giving the quest, it is given from the start without one
+    #[serde(default)]
+    pub started_by: Option<String>,
+    pub objectives: Vec<ObjectiveEnum>,
+    #[serde(default)]
+    pub reward: Reward,
+}
+
+/// Quests of an adventure as written in its data.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuestBook {
+    pub quests: Vec<QuestDefinition>,
+}
+
+impl QuestBook {
+    pub fn load(file: &str) -> std::io::Result<Self> {
+        load_ron(file)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&QuestDefinition> {
+        self.quests.iter().find(|quest| quest.id == id)
+    }
+}
+
+/// Progress of a given quest, one counter per objective.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestProgress {
+    pub id: String,
+    pub progress: Vec<u8>,
+    pub completed: bool,
+}
+
+/// A quest as shown in the journal, sent on the journal topic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub name: String,
+    pub description: String,
+    pub objectives: Vec<String>,
+    pub completed: bool,
+}
+
+impl Display for JournalEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}{}", self.name, if self.completed { " (completed)" } else { "" })?;
+        writeln!(f, "  {}", self.description)?;
+        for objective in self.objectives.iter() {
+            writeln!(f, "  - {}", objective)?;
+        }
+        Ok(())
+    }
+}
+
+/// Quests given to the players and how far they went, it is the part of the quests a saved game keeps.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Journal {
+    pub quests: Vec<QuestProgress>,
+}
+
+impl Journal {
+    /// Give the quests whose flag is set, then count the events against the objectives of the quests in progress.
+    /// Rewards of completed quests go to the given pawn.
+    pub fn update(&mut self,
+                  book: &QuestBook,
+                  flags: &HashSet<String>,
+                  events: &Vec<QuestEventEnum>,
+                  rewarded: Option<&Rc<RefCell<Pawn>>>,
+                  menu: &Menu) -> std::io::Result<()> {
+        for quest in book.quests.iter() {
+            let available = quest.started_by.as_ref().map(|flag| flags.contains(flag)).unwrap_or(true);
+            if available && !self.quests.iter().any(|progress| progress.id == quest.id) {
+                self.quests.push(QuestProgress { id: quest.id.clone(), progress: vec![0; quest.objectives.len()], completed: false });
+                menu.log(LogCategoryEnum::SYSTEM, format!("New quest : {}", quest.name).as_str())?;
+            }
+        }
+
+        for progress in self.quests.iter_mut().filter(|progress| !progress.completed) {
+            let quest = match book.get(progress.id.as_str()) {
+                Some(quest) => quest,
+                None => continue
+            };
+            for event in events.iter() {
+                for (objective, count) in quest.objectives.iter().zip(progress.progress.iter_mut()) {
+                    if *count < objective.goal() && objective.matches(event) {
+                        *count += 1;
+                        menu.log(LogCategoryEnum::SYSTEM, format!("{} : {} ({}/{})", quest.name, objective, count, objective.goal()).as_str())?;
+                    }
+                }
+            }
+
+            if quest.objectives.iter().zip(progress.progress.iter()).all(|(objective, count)| *count >= objective.goal()) {
+                progress.completed = true;
+                menu.log(LogCategoryEnum::SYSTEM, format!("Quest completed : {}", quest.name).as_str())?;
+                if let Some(pawn) = rewarded {
+                    Self::give_reward(&quest.reward, pawn, menu)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn give_reward(reward: &Reward, pawn: &Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<()> {
+        let mut pawn = pawn.borrow_mut();
+        pawn.experience += reward.experience;
+        pawn.gold += reward.gold;
+        menu.log(LogCategoryEnum::SYSTEM, format!("{} earns {} experience and {} gold", pawn.name, reward.experience, reward.gold).as_str())?;
+        for name in reward.items.iter() {
+            if let Some(item) = Initializer::item_named(name) {
+                menu.log(LogCategoryEnum::SYSTEM, format!("{} receives {}", pawn.name, item.name).as_str())?;
+                pawn.inventory.add(Rc::new(item));
+            }
+        }
+        Ok(())
+    }
+
+    /// Quests in progress first, then the completed ones.
+    pub fn view(&self, book: &QuestBook) -> Vec<JournalEntry> {
+        let mut entries = self.quests.iter()
+            .filter_map(|progress| book.get(progress.id.as_str()).map(|quest| JournalEntry {
+                name: quest.name.clone(),
+                description: quest.description.clone(),
+                objectives: quest.objectives.iter()
+                    .zip(progress.progress.iter())
+                    .map(|(objective, count)| format!("{} ({}/{})", objective, count, objective.goal()))
+                    .collect(),
+                completed: progress.completed,
+            }))
+            .collect::<Vec<JournalEntry>>();
+        entries.sort_by_key(|entry| entry.completed);
+        entries
+    }
+}
+
+/// What quests watch of the pawns, taken before an action to find out what it changed.
+pub struct QuestSnapshot {
+    dead: Vec<i64>,
+    items: Vec<String>,
+}
+
+impl QuestSnapshot {
+    pub fn take(pawn: &Rc<RefCell<Pawn>>, pawns: &Vec<Rc<RefCell<Pawn>>>) -> Self {
+        Self {
+            dead: pawns.iter()
+                .filter(|p| p.borrow().is_dead())
+                .map(|p| p.borrow().id)
+                .collect(),
+            items: Self::item_names(pawn, pawns),
+        }
+    }
+
+    /// Creatures outside of the party killed since the snapshot, and items obtained by the pawn when it is played by a human.
+    /// Items handed over between companions were already the party's, they are not obtained.
+    pub fn events(&self, pawn: &Rc<RefCell<Pawn>>, pawns: &Vec<Rc<RefCell<Pawn>>>) -> Vec<QuestEventEnum> {
+        let mut events = pawns.iter()
+            .filter(|p| p.borrow().faction != PLAYERS && p.borrow().is_dead() && !self.dead.contains(&p.borrow().id))
+            .map(|p| QuestEventEnum::KILLED(p.borrow().race.clone()))
+            .collect::<Vec<QuestEventEnum>>();
+
+        if pawn.borrow().playable {
+            let mut before = self.items.clone();
+            for name in Self::item_names(pawn, pawns) {
+                match before.iter().position(|item| item == &name) {
+                    Some(index) => {
+                        before.remove(index);
+                    }
+                    None => events.push(QuestEventEnum::OBTAINED(name)),
+                }
+            }
+        }
+        events
+    }
+
+    fn item_names(pawn: &Rc<RefCell<Pawn>>, pawns: &Vec<Rc<RefCell<Pawn>>>) -> Vec<String> {
+        std::iter::once(pawn)
+            .chain(Party::companions(pawn, pawns))
+            .flat_map(|holder| holder.borrow().inventory.stacks().iter()
+                .flat_map(|stack| std::iter::repeat(stack.item.name.clone()).take(stack.quantity as usize))
+                .collect::<Vec<String>>())
+            .collect()
+    }
+}
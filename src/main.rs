@@ -4,10 +4,12 @@ use std::sync::mpsc::{Sender, Receiver};
 use console::Term;
 use dialoguer::Select;
 use dialoguer::theme::ColorfulTheme;
-use crate::logic::game_loop::GameLoop;
+use crate::logic::game_loop::{GameLoop, GameMode};
 use crate::gui::graphical::window;
 use crate::gui::menu::Menu;
+use crate::pawn::party::PartySettings;
 use crate::services::messaging::{MessageContent, Messaging};
+use crate::services::network::{NetworkClient, NetworkMode, NetworkServer, INPUT_TOPICS, OWNER_TOPIC, UI_TOPICS};
 
 mod pawn;
 mod inventory;
@@ -20,8 +22,50 @@ mod ai;
 
 
 fn main() {
+    let args = std::env::args().collect::<Vec<String>>();
+    let network_mode = NetworkMode::from_args(&args);
     let mut messaging = Messaging::init();
 
+    match network_mode {
+        NetworkMode::LOCAL => {
+            let (menu, gameplay_senders, gameplay_receivers) = gameplay_topics(&mut messaging);
+            let ui_receivers = messaging.subscribe_to_topics(&UI_TOPICS);
+            let ui_senders = messaging.create_topics(&INPUT_TOPICS);
+
+            Messaging::start_bus(messaging.incoming_messages, messaging.outcoming_messages).unwrap();
+            GameLoop::iterate(gameplay_senders, gameplay_receivers, menu, GameMode::from_args(args.clone()), PartySettings::file_from_args(&args), true);
+
+            // #[cfg(feature = "graphical_mode")]
+            window::init(ui_receivers, ui_senders).unwrap();
+        }
+        //The server owns the game, the windows are on the clients
+        NetworkMode::SERVER(address) => {
+            let (menu, gameplay_senders, gameplay_receivers) = gameplay_topics(&mut messaging);
+            let server_receiver = messaging.subscribe_to_topics_in_order(&[&UI_TOPICS[..], &[OWNER_TOPIC]].concat());
+            let server_senders = messaging.create_topics(&INPUT_TOPICS);
+
+            Messaging::start_bus(messaging.incoming_messages, messaging.outcoming_messages).unwrap();
+            GameLoop::iterate(gameplay_senders, gameplay_receivers, menu, GameMode::from_args(args.clone()), PartySettings::file_from_args(&args), false);
+
+            let players = PartySettings::load(PartySettings::file_from_args(&args).as_str()).unwrap().players();
+            NetworkServer::run(address.as_str(), players, server_receiver, server_senders).unwrap();
+        }
+        NetworkMode::CLIENT(address, player) => {
+            let ui_receivers = messaging.subscribe_to_topics(&UI_TOPICS);
+            let ui_senders = messaging.create_topics(&INPUT_TOPICS);
+            let client_receivers = messaging.subscribe_to_topics(&INPUT_TOPICS);
+            let client_senders = messaging.create_topics(&UI_TOPICS);
+
+            Messaging::start_bus(messaging.incoming_messages, messaging.outcoming_messages).unwrap();
+            NetworkClient::start(address, player, client_receivers, client_senders);
+
+            window::init(ui_receivers, ui_senders).unwrap();
+        }
+    }
+}
+
+/// Menu of the game thread, the topics it sends on and the ones it reads.
+fn gameplay_topics(messaging: &mut Messaging) -> (Menu, HashMap<String, Sender<MessageContent>>, HashMap<String, Receiver<MessageContent>>) {
     let select = messaging.create_topic();
     let select_response = messaging.subscribe_to_topic("select_response".to_string());
     let stdout = messaging.create_topic();
@@ -43,40 +87,17 @@ fn main() {
     messenger_gameplay_sender.insert("info_response".to_string(), messaging.create_topic());
     messenger_gameplay_sender.insert("gameplay_state".to_string(), messaging.create_topic());
     messenger_gameplay_sender.insert("change_room".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("inventory".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("projectile".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("turn_budget".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("pawn_status".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("dialogue".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("journal".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("party".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("hand_off".to_string(), messaging.create_topic());
+    messenger_gameplay_sender.insert("fog".to_string(), messaging.create_topic());
     messenger_gameplay_receiver.insert("info".to_string(), messaging.subscribe_to_topic("info".to_string()));
+    messenger_gameplay_receiver.insert("inventory_command".to_string(), messaging.subscribe_to_topic("inventory_command".to_string()));
 
-    let mut messenger_ui_map_receiver = HashMap::new();
-    let mut messenger_ui_map_sender = HashMap::new();
-
-    messenger_ui_map_receiver.insert("sprite".to_string(), messaging.subscribe_to_topic("sprite".to_string()));
-
-    messenger_ui_map_receiver.insert("stdout".to_string(), messaging.subscribe_to_topic("stdout".to_string()));
-
-    messenger_ui_map_receiver.insert("end_turn".to_string(), messaging.subscribe_to_topic("end_turn".to_string()));
-
-    messenger_ui_map_receiver.insert("show_damage".to_string(), messaging.subscribe_to_topic("show_damage".to_string()));
-
-    messenger_ui_map_receiver.insert("select".to_string(), messaging.subscribe_to_topic("select".to_string()));
-
-    messenger_ui_map_receiver.insert("targetable".to_string(), messaging.subscribe_to_topic("targetable".to_string()));
-    messenger_ui_map_receiver.insert("current_player".to_string(), messaging.subscribe_to_topic("current_player".to_string()));
-
-    messenger_ui_map_receiver.insert("clear".to_string(), messaging.subscribe_to_topic("clear".to_string()));
-
-    messenger_ui_map_receiver.insert("info_response".to_string(), messaging.subscribe_to_topic("info_response".to_string()));
-
-    messenger_ui_map_receiver.insert("gameplay_state".to_string(), messaging.subscribe_to_topic("gameplay_state".to_string()));
-    messenger_ui_map_receiver.insert("change_room".to_string(), messaging.subscribe_to_topic("change_room".to_string()));
-
-    messenger_ui_map_sender.insert("select_response".to_string(), messaging.create_topic());
-    messenger_ui_map_sender.insert("info".to_string(), messaging.create_topic());
-
-
-    Messaging::start_bus(messaging.incoming_messages, messaging.outcoming_messages).unwrap();
-
-    GameLoop::iterate(messenger_gameplay_sender, messenger_gameplay_receiver, menu);
-
-
-    // #[cfg(feature = "graphical_mode")]
-    window::init(messenger_ui_map_receiver, messenger_ui_map_sender).unwrap();
+    (menu, messenger_gameplay_sender, messenger_gameplay_receiver)
 }
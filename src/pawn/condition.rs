@@ -0,0 +1,32 @@
+use crate::inventory::item::DamageTypeEnum;
+use crate::services::dice::Dice;
+
+/// Lasting states put on a pawn, they are resolved at the start of each of its turns.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConditionEnum {
+    BLEEDING,
+    STUNNED,
+    LOST_ACTION,
+}
+
+impl ConditionEnum {
+    /// What a critical hit of this damage type leaves on the target, with the number of turns it lasts.
+    pub fn from_critical(damage_type: &DamageTypeEnum) -> Option<(ConditionEnum, u8)> {
+        match damage_type {
+            DamageTypeEnum::SLASHING => Some((ConditionEnum::BLEEDING, 3)),
+            DamageTypeEnum::BLUNT => Some((ConditionEnum::STUNNED, 1)),
+            _ => None
+        }
+    }
+
+    pub fn bleeding_damages() -> u8 {
+        Dice::roll_1d4() as u8
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub kind: ConditionEnum,
+    pub turns: u8,
+}
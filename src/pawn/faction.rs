@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use crate::ai::ai::AI;
+use crate::gui::combat_log::LogCategoryEnum;
+use crate::gui::menu::Menu;
+use crate::pawn::pawn::Pawn;
+use crate::services::resources::load_ron;
+
+lazy_static! {
+    pub static ref FACTIONS: FactionRegistry = FactionRegistry::load("factions.ron").unwrap();
+}
+
+/// Faction of the pawns played by humans and of their companions, reputation is their standing with the other factions.
+pub const PLAYERS: &str = "players";
+/// Reputation lost with the faction of a pawn the players attack while it was not hostile to them.
+pub const PROVOCATION_PENALTY: i16 = 25;
+/// Reputation lost with the faction of a pawn the players kill while it was not hostile to them.
+pub const KILL_PENALTY: i16 = 25;
+/// Reputation won with the enemies of the faction of a hostile pawn the players kill.
+pub const KILL_BONUS: i16 = 5;
+/// Once the reputation falls that low the whole faction turns against the players.
+pub const HOSTILE_REPUTATION: i16 = -50;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RelationEnum {
+    HOSTILE,
+    NEUTRAL,
+    ALLIED,
+}
+
+/// How a faction sees the others, the ones it does not list are neutral to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactionDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub relations: HashMap<String, RelationEnum>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FactionRegistry {
+    pub factions: Vec<FactionDefinition>,
+}
+
+impl FactionRegistry {
+    pub fn load(file: &str) -> std::io::Result<Self> {
+        load_ron(file)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FactionDefinition> {
+        self.factions.iter().find(|faction| faction.name == name)
+    }
+
+    /// Members of a faction are allied, otherwise the first faction listing the other one decides.
+    pub fn relation(&self, from: &str, to: &str) -> RelationEnum {
+        if from == to {
+            return RelationEnum::ALLIED;
+        }
+        self.get(from).and_then(|faction| faction.relations.get(to))
+            .or(self.get(to).and_then(|faction| faction.relations.get(from)))
+            .copied()
+            .unwrap_or(RelationEnum::NEUTRAL)
+    }
+
+    /// Pawns fight when their factions are hostile or when one of them holds a grudge against the other.
+    pub fn is_hostile(&self, pawn: &Pawn, other: &Pawn) -> bool {
+        pawn.id != other.id && (pawn.grudges.contains(&other.id) || other.grudges.contains(&pawn.id)
+            || self.relation(pawn.faction.as_str(), other.faction.as_str()) == RelationEnum::HOSTILE)
+    }
+
+    pub fn is_allied(&self, pawn: &Pawn, other: &Pawn) -> bool {
+        !self.is_hostile(pawn, other) && self.relation(pawn.faction.as_str(), other.faction.as_str()) == RelationEnum::ALLIED
+    }
+
+    /// A pawn attacked while it was not hostile holds a grudge against its attacker and fights back.
+    pub fn provoke(&self, attacker: &Rc<RefCell<Pawn>>, victim: &Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<()> {
+        if self.is_hostile(&attacker.borrow(), &victim.borrow()) {
+            return Ok(());
+        }
+        let attacker_id = attacker.borrow().id;
+        victim.borrow_mut().grudges.push(attacker_id);
+        Self::fight_back(victim, attacker);
+        menu.log(LogCategoryEnum::ATTACK, format!("{} turns hostile against {}!", victim.borrow().name, attacker.borrow().name).as_str())?;
+        Ok(())
+    }
+
+    /// Pawns left to the AI watch their new enemy, the ones without AI get one.
+    pub fn fight_back(pawn: &Rc<RefCell<Pawn>>, enemy: &Rc<RefCell<Pawn>>) {
+        let mut pawn = pawn.borrow_mut();
+        if pawn.playable {
+            return;
+        }
+        pawn.dialogue = None;
+        let ai = pawn.ai.clone();
+        let mut ai = ai.borrow_mut();
+        match ai.as_mut() {
+            Some(ai) => ai.add_target_to_watched_target(enemy.clone()),
+            None => *ai = Some(AI {
+                intelligence: 0,
+                seen_target: vec![enemy.clone()],
+                selected_target: None,
+                name: pawn.name.clone(),
+            })
+        }
+    }
+}
+
+/// Standing of the players with each faction, it is kept with the saved game.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reputation {
+    pub standings: HashMap<String, i16>,
+}
+
+impl Reputation {
+    pub fn get(&self, faction: &str) -> i16 {
+        self.standings.get(faction).copied().unwrap_or(0)
+    }
+
+    /// Returns true when the change makes the faction turn against the players.
+    pub fn change(&mut self, faction: &str, amount: i16, menu: &Menu) -> std::io::Result<bool> {
+        let before = self.get(faction);
+        let after = before.saturating_add(amount);
+        self.standings.insert(faction.to_string(), after);
+        menu.log(LogCategoryEnum::SYSTEM, format!("Reputation with {} : {} ({:+})", faction, after, amount).as_str())?;
+        Ok(before > HOSTILE_REPUTATION && after <= HOSTILE_REPUTATION)
+    }
+}
+
+/// Grudges and deaths, taken before an action to find out how it changed the standing of the players.
+pub struct ReputationSnapshot {
+    grudges: Vec<(i64, usize)>,
+    dead: Vec<i64>,
+}
+
+impl ReputationSnapshot {
+    pub fn take(pawns: &Vec<Rc<RefCell<Pawn>>>) -> Self {
+        Self {
+            grudges: pawns.iter()
+                .map(|p| (p.borrow().id, p.borrow().grudges.len()))
+                .collect(),
+            dead: pawns.iter()
+                .filter(|p| p.borrow().is_dead())
+                .map(|p| p.borrow().id)
+                .collect(),
+        }
+    }
+
+    /// Reputation changes by faction, only actions of the players faction count.
+    pub fn changes(&self, pawn: &Rc<RefCell<Pawn>>, pawns: &Vec<Rc<RefCell<Pawn>>>) -> Vec<(String, i16)> {
+        let pawn = pawn.borrow();
+        if pawn.faction != PLAYERS {
+            return vec![];
+        }
+
+        let mut changes = vec![];
+        for other in pawns.iter().map(|p| p.borrow()) {
+            let known_grudges = self.grudges.iter()
+                .find(|(id, _)| *id == other.id)
+                .map(|(_, count)| *count)
+                .unwrap_or(0);
+            let provoked = other.grudges.iter().skip(known_grudges).any(|id| *id == pawn.id);
+            if provoked {
+                changes.push((other.faction.clone(), -PROVOCATION_PENALTY));
+            }
+
+            if other.is_dead() && !self.dead.contains(&other.id) && other.faction != PLAYERS {
+                if FACTIONS.relation(PLAYERS, other.faction.as_str()) == RelationEnum::HOSTILE {
+                    FACTIONS.factions.iter()
+                        .filter(|faction| faction.name != PLAYERS)
+                        .filter(|faction| FACTIONS.relation(faction.name.as_str(), other.faction.as_str()) == RelationEnum::HOSTILE)
+                        .for_each(|faction| changes.push((faction.name.clone(), KILL_BONUS)));
+                } else {
+                    changes.push((other.faction.clone(), -KILL_PENALTY));
+                }
+            }
+        }
+        changes
+    }
+}
@@ -1 +1,5 @@
-pub mod pawn;
\ No newline at end of file
+pub mod condition;
+pub mod faction;
+pub mod party;
+pub mod pawn;
+pub mod turn;
\ No newline at end of file
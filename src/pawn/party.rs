@@ -0,0 +1,367 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use crate::environment::tile::TILES;
+use crate::gui::combat_log::LogCategoryEnum;
+use crate::gui::menu::Menu;
+use crate::inventory::inventory::{Inventory, InventoryFilterEnum, InventorySortEnum};
+use crate::inventory::item::{AmmunitionEnum, Item, ItemAttackTypeEnum};
+use crate::pawn::faction::PLAYERS;
+use crate::pawn::pawn::{Characteristics, Pawn, Position};
+use crate::services::initializer::Initializer;
+use crate::services::resources::load_ron;
+
+/// How the party lines up behind the member going first into a place.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FormationEnum {
+    LINE,
+    COLUMN,
+    WEDGE,
+}
+
+impl FormationEnum {
+    /// Offsets from the leader, the leader being the first one.
+    fn offsets(&self) -> Vec<(i32, i32)> {
+        match self {
+            FormationEnum::LINE => vec![(0, 0), (-1, 0), (1, 0), (-2, 0), (2, 0), (-3, 0), (3, 0)],
+            FormationEnum::COLUMN => vec![(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5), (0, 6)],
+            FormationEnum::WEDGE => vec![(0, 0), (-1, 1), (1, 1), (-2, 2), (2, 2), (0, 2), (0, 1)],
+        }
+    }
+
+    /// One free walkable tile per member around the anchor,
+    /// members whose formation tile is taken get the closest free one.
+    pub fn place(&self, anchor: &Position, count: usize, room: &Vec<Vec<u8>>, occupied: &Vec<Position>) -> Vec<Position> {
+        let mut taken = occupied.clone();
+        let mut positions = vec![];
+        let offsets = self.offsets();
+        for index in 0..count {
+            let (dx, dy) = offsets.get(index).copied().unwrap_or((0, 0));
+            let wanted = (anchor.x as i32 + dx, anchor.y as i32 + dy);
+            let position = Self::free_tile(wanted, room, &taken)
+                .or_else(|| Self::closest_free_tile(anchor, room, &taken))
+                .unwrap_or(anchor.clone());
+            taken.push(position.clone());
+            positions.push(position);
+        }
+        positions
+    }
+
+    fn free_tile((x, y): (i32, i32), room: &Vec<Vec<u8>>, taken: &Vec<Position>) -> Option<Position> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let tile = room.get(y as usize)?.get(x as usize)?;
+        let position = Position { x: x as u16, y: y as u16 };
+        if TILES.is_walkable(*tile) && !taken.contains(&position) {
+            Some(position)
+        } else {
+            None
+        }
+    }
+
+    fn closest_free_tile(anchor: &Position, room: &Vec<Vec<u8>>, taken: &Vec<Position>) -> Option<Position> {
+        room.iter()
+            .enumerate()
+            .flat_map(|(y, row)| (0..row.len()).map(move |x| (x as i32, y as i32)))
+            .filter_map(|tile| Self::free_tile(tile, room, taken))
+            .min_by_key(|tile| (tile.x as i32 - anchor.x as i32).abs().max((tile.y as i32 - anchor.y as i32).abs()))
+    }
+}
+
+/// A character of the party as written in the party file, items and spells are given by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterSheet {
+    pub name: String,
+    pub race: String,
+    pub sprite: String,
+    pub life: u8,
+    #[serde(default)]
+    pub mana: u8,
+    pub characteristics: Characteristics,
+    #[serde(default)]
+    pub proficiencies: Vec<ItemAttackTypeEnum>,
+    #[serde(default)]
+    pub items: Vec<String>,
+    #[serde(default)]
+    pub ammunition: Vec<(AmmunitionEnum, u16)>,
+    #[serde(default)]
+    pub spells: Vec<String>,
+    #[serde(default)]
+    pub gold: u32,
+    //Human player in control of the character, the game is played hot-seat when several are given
+    #[serde(default = "first_player")]
+    pub player: u8,
+}
+
+fn first_player() -> u8 {
+    1
+}
+
+impl CharacterSheet {
+    pub fn create(&self) -> Pawn {
+        let mut inventory = Inventory::default();
+        for name in self.items.iter() {
+            match Initializer::item_named(name) {
+                Some(item) => inventory.add(Rc::new(item)),
+                None => println!("There is no item named {}", name)
+            }
+        }
+        for (kind, quantity) in self.ammunition.iter() {
+            inventory.add_stack(Rc::new(Initializer::generate_ammunition(*kind)), *quantity);
+        }
+
+        Pawn {
+            id: idgenerator::IdInstance::next_id(),
+            name: self.name.clone(),
+            life: self.life,
+            max_life: self.life,
+            mana: self.mana,
+            max_mana: self.mana,
+            characteristics: self.characteristics,
+            inventory,
+            gold: self.gold,
+            experience: 0,
+            equipped: Default::default(),
+            loaded_weapons: vec![],
+            turn_budget: Default::default(),
+            shield: 0,
+            conditions: vec![],
+            proficiencies: self.proficiencies.clone(),
+            spell: Initializer::generate_spells().into_iter()
+                .filter(|spell| self.spells.contains(&spell.name))
+                .collect(),
+            race: self.race.clone(),
+            sprite: self.sprite.clone(),
+            playable: true,
+            owner: self.player,
+            ai: Rc::new(RefCell::new(None)),
+            dialogue: None,
+            faction: PLAYERS.to_string(),
+            grudges: vec![],
+            position: Position { x: 0, y: 0 },
+        }
+    }
+}
+
+/// How the party travels and shares its bags, it is kept with the saved game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Party {
+    pub formation: FormationEnum,
+    //Opening the inventory gathers the bags of the members standing in the same place
+    #[serde(default)]
+    pub shared_inventory: bool,
+}
+
+impl Default for Party {
+    fn default() -> Self {
+        Self {
+            formation: FormationEnum::WEDGE,
+            shared_inventory: false,
+        }
+    }
+}
+
+/// Content of the party file, the characters the adventure starts with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartySettings {
+    pub party: Party,
+    pub members: Vec<CharacterSheet>,
+}
+
+impl PartySettings {
+    pub fn load(file: &str) -> std::io::Result<Self> {
+        load_ron(file)
+    }
+
+    /// `--party <file>` plays with another party file from the resources, `party.ron` otherwise.
+    pub fn file_from_args(args: &Vec<String>) -> String {
+        args.iter()
+            .position(|arg| arg == "--party")
+            .and_then(|index| args.get(index + 1))
+            .cloned()
+            .unwrap_or("party.ron".to_string())
+    }
+
+    /// Human players owning a character of the party.
+    pub fn players(&self) -> Vec<u8> {
+        let mut players = self.members.iter()
+            .map(|sheet| sheet.player)
+            .collect::<Vec<u8>>();
+        players.sort();
+        players.dedup();
+        players
+    }
+
+    pub fn create_members(&self) -> Vec<Rc<RefCell<Pawn>>> {
+        self.members.iter()
+            .map(|sheet| Rc::new(RefCell::new(sheet.create())))
+            .collect()
+    }
+}
+
+impl Party {
+    /// Pawns played by humans and their companions, they travel together.
+    pub fn is_member(pawn: &Pawn) -> bool {
+        pawn.faction == PLAYERS && !pawn.is_dead()
+    }
+
+    /// Other members of the same player in the place, they share their bags with the pawn.
+    pub fn companions<'a>(pawn: &Rc<RefCell<Pawn>>, pawns: &'a Vec<Rc<RefCell<Pawn>>>) -> impl Iterator<Item = &'a Rc<RefCell<Pawn>>> {
+        let (id, owner) = (pawn.borrow().id, pawn.borrow().owner);
+        pawns.iter().filter(move |p| p.borrow().id != id && p.borrow().playable && p.borrow().owner == owner && Self::is_member(&p.borrow()))
+    }
+
+    /// Stacks the companions of the pawn lend from their bags, by companion and stack index, in the order of the inventory screen.
+    /// Worn items and the ammunition of their equipped weapons stay out of the pool.
+    pub fn lent_stacks(pawn: &Rc<RefCell<Pawn>>, pawns: &Vec<Rc<RefCell<Pawn>>>, sort: InventorySortEnum, filter: InventoryFilterEnum) -> Vec<(Rc<RefCell<Pawn>>, usize)> {
+        Self::companions(pawn, pawns)
+            .flat_map(|member| {
+                let indexes = {
+                    let member = member.borrow();
+                    let worn = member.equipped.unique_items().into_iter()
+                        .chain(member.loaded_weapons.iter().cloned())
+                        .collect::<Vec<Rc<Item>>>();
+                    let shot = worn.iter()
+                        .filter_map(|weapon| weapon.uses_ammunition)
+                        .collect::<Vec<AmmunitionEnum>>();
+                    member.inventory.view(sort, filter).into_iter()
+                        .filter(|&index| {
+                            let item = &member.inventory.get(index).unwrap().item;
+                            !worn.iter().any(|worn| Rc::ptr_eq(worn, item)) && !item.ammunition.map_or(false, |kind| shot.contains(&kind))
+                        })
+                        .collect::<Vec<usize>>()
+                };
+                indexes.into_iter().map(move |index| (member.clone(), index))
+            })
+            .collect()
+    }
+
+    /// The pawn takes one item of a lent stack once it uses it.
+    pub fn take_lent(pawn: &Rc<RefCell<Pawn>>, lent: &(Rc<RefCell<Pawn>>, usize), menu: &Menu) -> std::io::Result<Option<Rc<Item>>> {
+        let (member, index) = lent;
+        let item = match member.borrow().inventory.get(*index) {
+            Some(stack) => stack.item.clone(),
+            None => return Ok(None)
+        };
+        member.borrow_mut().inventory.remove(&item);
+        pawn.borrow_mut().inventory.add(item.clone());
+        menu.log(LogCategoryEnum::SYSTEM, format!("{} hands {} over to {}", member.borrow().name, item.name, pawn.borrow().name).as_str())?;
+        Ok(Some(item))
+    }
+
+    /// Life and mana of every playable member, sent on the party topic.
+    pub fn statuses(pawns: &Vec<Rc<RefCell<Pawn>>>, current: i64) -> Vec<PartyMemberStatus> {
+        pawns.iter()
+            .map(|p| p.borrow())
+            .filter(|p| p.playable && p.faction == PLAYERS)
+            .map(|p| PartyMemberStatus {
+                id: p.id,
+                name: p.name.clone(),
+                life: p.life,
+                max_life: p.max_life,
+                mana: p.mana,
+                max_mana: p.max_mana,
+                current: p.id == current,
+            })
+            .collect()
+    }
+}
+
+/// A line of the party panel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyMemberStatus {
+    pub id: i64,
+    pub name: String,
+    pub life: u8,
+    pub max_life: u8,
+    pub mana: u8,
+    pub max_mana: u8,
+    pub current: bool,
+}
+
+impl Display for PartyMemberStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{} {}/{} HP {}/{} MP", if self.current { "> " } else { "  " }, self.name, self.life, self.max_life, self.mana, self.max_mana)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(owners: Vec<u8>) -> Vec<Rc<RefCell<Pawn>>> {
+        owners.into_iter()
+            .map(|owner| {
+                let member = PartySettings::load("party.ron").unwrap().create_members().remove(0);
+                member.borrow_mut().owner = owner;
+                member
+            })
+            .collect()
+    }
+
+    #[test]
+    fn members_line_up_behind_the_leader() {
+        let room = vec![vec![10u8; 5]; 3];
+        let anchor = Position { x: 2, y: 0 };
+        //formation, positions of three members
+        let reference = [
+            (FormationEnum::LINE, [(2, 0), (1, 0), (3, 0)]),
+            (FormationEnum::COLUMN, [(2, 0), (2, 1), (2, 2)]),
+            (FormationEnum::WEDGE, [(2, 0), (1, 1), (3, 1)]),
+        ];
+        for (formation, expected) in reference {
+            let positions = formation.place(&anchor, 3, &room, &vec![]);
+            let expected = expected.iter().map(|&(x, y)| Position { x, y }).collect::<Vec<Position>>();
+            assert_eq!(positions, expected, "{:?}", formation);
+        }
+    }
+
+    #[test]
+    fn members_take_the_closest_free_tile_when_theirs_is_blocked() {
+        let mut room = vec![vec![10u8; 3]; 3];
+        room[1][0] = 20;
+        let occupied = vec![Position { x: 2, y: 1 }];
+
+        let positions = FormationEnum::LINE.place(&Position { x: 1, y: 1 }, 3, &room, &occupied);
+
+        assert_eq!(positions, vec![Position { x: 1, y: 1 }, Position { x: 0, y: 0 }, Position { x: 1, y: 0 }]);
+
+        //More members than tiles in the formation still get a tile each
+        let mut room = vec![vec![10u8; 5]; 3];
+        room[1][0] = 20;
+        let positions = FormationEnum::WEDGE.place(&Position { x: 2, y: 1 }, 8, &room, &occupied);
+        for (index, position) in positions.iter().enumerate() {
+            assert!(TILES.is_walkable(room[position.y as usize][position.x as usize]));
+            assert!(!occupied.contains(position) && !positions[..index].contains(position), "{:?} is given twice", position);
+        }
+    }
+
+    #[test]
+    fn companions_are_the_living_members_of_the_same_player() {
+        let mut pawns = members(vec![1, 1, 2, 1]);
+        pawns[3].borrow_mut().life = 0;
+        let goblin = Initializer::generate_goblin(Initializer::item_named("Basic iron sword").unwrap(), Position { x: 0, y: 0 });
+        pawns.push(Rc::new(RefCell::new(goblin)));
+
+        let companions = Party::companions(&pawns[0], &pawns).collect::<Vec<&Rc<RefCell<Pawn>>>>();
+
+        assert_eq!(companions.len(), 1);
+        assert!(Rc::ptr_eq(companions[0], &pawns[1]));
+    }
+
+    #[test]
+    fn statuses_list_the_playable_members_and_the_current_one() {
+        let pawns = members(vec![1, 2]);
+        pawns[1].borrow_mut().playable = false;
+        let current = pawns[0].borrow().id;
+
+        let statuses = Party::statuses(&pawns, current);
+
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses[0].current);
+        assert!(statuses[0].to_string().starts_with("> "));
+    }
+}
@@ -3,15 +3,22 @@ use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Write};
 use std::ops::Add;
 use std::rc::Rc;
+use serde::{Deserialize, Serialize};
 use crate::ai::ai::AI;
-use crate::gui::graphical::sprite::{Layer, ObjectToSprite, Sprite};
+use crate::gui::graphical::sprite::{Layer, ObjectToSprite, PawnStatus, Sprite};
 use crate::gui::menu::Menu;
-use crate::inventory::item::{Item, ItemAttackTypeEnum, PartToEquiEnum, Pocketable, Spell};
+use crate::inventory::inventory::Inventory;
+use crate::inventory::item::{AmmunitionEnum, Item, ItemAttackTypeEnum, PartToEquiEnum, Pocketable, Spell};
+use crate::pawn::condition::{Condition, ConditionEnum};
+use crate::pawn::turn::TurnBudget;
 use crate::services::dice;
+use crate::services::rules::Rules;
 
 pub const CA: u8 = 10;
+/// Natural roll of a critical hit without any item lowering it.
+pub const CRITICAL_ROLL: u8 = 20;
 
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default, Serialize, Deserialize)]
 pub struct Characteristics {
     pub force: u8,
     pub dexterity: u8,
@@ -36,25 +43,122 @@ impl Add for Characteristics {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+impl Characteristics {
+    pub fn named(&self) -> [(&'static str, u8); 6] {
+        [("FOR", self.force),
+            ("DEX", self.dexterity),
+            ("CON", self.constitution),
+            ("INT", self.intelligence),
+            ("WIL", self.willpower),
+            ("CHA", self.charisma)]
+    }
+
+    /// Requirements the given characteristics do not reach, as `FOR 2`.
+    pub fn missing(&self, available: &Characteristics) -> Vec<String> {
+        self.named().iter()
+            .zip(available.named().iter())
+            .filter(|((_, required), (_, value))| required > value)
+            .map(|((name, required), _)| format!("{} {}", name, required))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub x: u16,
     pub y: u16,
 }
 
+#[derive(Debug)]
+pub enum EquipError {
+    NotInInventory(String),
+    WrongPart(String, PartToEquiEnum),
+    MissingRequirements(String, Vec<String>),
+}
+
+impl Display for EquipError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EquipError::NotInInventory(name) => write!(f, "{} is not in the inventory", name),
+            EquipError::WrongPart(name, part) => write!(f, "{} cannot be equipped on {}", name, part),
+            EquipError::MissingRequirements(name, missing) => write!(f, "{} requires {}", name, missing.join(", ")),
+        }
+    }
+}
+
+/// What would change if an item was equipped, shown before the player commits.
+#[derive(Debug, Clone)]
+pub struct EquipPreview {
+    pub armor: (u8, u8),
+    pub characteristics: (Characteristics, Characteristics),
+    pub unequipped: Vec<String>,
+}
+
+impl Display for EquipPreview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut changes = vec![];
+        if self.armor.0 != self.armor.1 {
+            changes.push(format!("Armor {} -> {}", self.armor.0, self.armor.1));
+        }
+        self.characteristics.0.named().iter()
+            .zip(self.characteristics.1.named().iter())
+            .filter(|((_, before), (_, after))| before != after)
+            .for_each(|((name, before), (_, after))| changes.push(format!("{} {} -> {}", name, before, after)));
+        if !self.unequipped.is_empty() {
+            changes.push(format!("removes {}", self.unequipped.join(", ")));
+        }
+
+        if changes.is_empty() {
+            write!(f, "No change")
+        } else {
+            write!(f, "{}", changes.join(", "))
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EncumbranceEnum {
+    LIGHT,
+    BURDENED,
+    OVERLOADED,
+}
+
 #[derive(Debug, Clone)]
 pub struct Pawn {
     pub id: i64,
     pub name: String,
     pub life: u8,
+    pub max_life: u8,
     pub mana: u8,
+    pub max_mana: u8,
     pub characteristics: Characteristics,
-    pub inventory: Vec<Rc<Item>>,
+    pub inventory: Inventory,
+    pub gold: u32,
+    /// Earned by completing quests.
+    pub experience: u32,
     pub equipped: EquipablePart,
+    pub loaded_weapons: Vec<Rc<Item>>,
+    pub turn_budget: TurnBudget,
+    /// Armor raised as a reaction, it lasts until the start of the pawn next turn.
+    pub shield: u8,
+    pub conditions: Vec<Condition>,
+    /// Kinds of attack the pawn is trained in, they get the proficiency bonus.
+    pub proficiencies: Vec<ItemAttackTypeEnum>,
     pub spell: Vec<Rc<Spell>>,
     pub race: String,
+    /// Name of the sprite in the sprite manifest.
+    pub sprite: String,
     pub playable: bool,
+    /// Human player in control of the pawn in hot-seat games, 0 for the pawns left to the AI.
+    pub owner: u8,
     pub ai: Rc<RefCell<Option<AI>>>,
+    /// Name of the dialogue tree started when a player talks to the pawn.
+    pub dialogue: Option<String>,
+    /// Name of the faction in the factions file, friend or foe is decided by its relations.
+    pub faction: String,
+    /// Pawns it turned hostile against after they attacked it.
+    pub grudges: Vec<i64>,
     pub position: Position,
 }
 
@@ -63,54 +167,230 @@ impl Pawn {
         target.clone().borrow_mut().take_hit(item.clone().get_damages())
     }
 
+    /// Damage dices are rolled twice.
+    pub fn critical_hit(&self, item: Rc<dyn Pocketable>, target: Rc<RefCell<Pawn>>) -> u8 {
+        let damages = item.get_damages().saturating_add(item.get_damages());
+        target.clone().borrow_mut().take_hit(damages)
+    }
+
+    /// Lowest natural roll making a critical hit, equipped items can extend the range.
+    pub fn critical_threshold(&self) -> u8 {
+        let bonus = self.equipped.unique_items()
+            .iter()
+            .map(|item| item.critical_bonus)
+            .sum::<u8>();
+        CRITICAL_ROLL.saturating_sub(bonus).max(2)
+    }
+
     pub fn try_watch(&self, target: Rc<RefCell<Pawn>>) -> String {
         target.clone().borrow().to_string(&self.characteristics, dice::Dice::roll_1d20() as u8)
     }
 
 
     pub fn take_hit(&mut self, damage: u8) -> u8 {
+        let damage = damage.min(self.life);
         self.life -= damage;
         damage
     }
 
-    pub fn equip(&mut self, item: Rc<Item>) {
-        match item.clone().part_to_equip {
-            PartToEquiEnum::HEAD => self.equipped.head = Some(item.clone()),
-            PartToEquiEnum::BODY => self.equipped.body = Some(item.clone()),
-            PartToEquiEnum::LEFT_HAND => self.equipped.left_hand = Some(item.clone()),
-            PartToEquiEnum::RIGHT_HAND => self.equipped.right_hand = Some(item.clone()),
-            PartToEquiEnum::LEGS => self.equipped.legs = Some(item.clone()),
-            PartToEquiEnum::FEET => self.equipped.feet = Some(item.clone()),
+    pub fn can_equip(&self, item: &Rc<Item>, part: &PartToEquiEnum) -> Result<(), EquipError> {
+        if !self.inventory.contains(item) {
+            return Err(EquipError::NotInInventory(item.name.clone()));
+        }
+        if !item.allowed_parts().contains(part) {
+            return Err(EquipError::WrongPart(item.name.clone(), part.clone()));
+        }
+
+        let missing = item.get_requirements().missing(&self.total_characteristics());
+        if !missing.is_empty() {
+            return Err(EquipError::MissingRequirements(item.name.clone(), missing));
+        }
+        Ok(())
+    }
+
+    /// Equip an item on a part, it leaves the slot it was in before.
+    /// Two handed items take both hands, returns what had to be unequipped to make room.
+    pub fn equip(&mut self, item: Rc<Item>, part: PartToEquiEnum) -> Result<Vec<Rc<Item>>, EquipError> {
+        self.can_equip(&item, &part)?;
+        self.unequip_item(&item);
+
+        let parts = if item.two_handed {
+            vec![PartToEquiEnum::RIGHT_HAND, PartToEquiEnum::LEFT_HAND]
+        } else {
+            vec![part]
+        };
+
+        let mut unequipped: Vec<Rc<Item>> = vec![];
+        for part in parts {
+            if let Some(previous) = self.equipped.get(&part) {
+                self.unequip_item(&previous);
+                unequipped.push(previous);
+            }
+            self.equipped.set(&part, Some(item.clone()));
+        }
+        Ok(unequipped)
+    }
+
+    pub fn equip_preview(&self, item: &Rc<Item>, part: PartToEquiEnum) -> Result<EquipPreview, EquipError> {
+        let mut preview = self.clone();
+        let unequipped = preview.equip(item.clone(), part)?;
+
+        Ok(EquipPreview {
+            armor: (self.calculate_armor_points(), preview.calculate_armor_points()),
+            characteristics: (self.total_characteristics(), preview.total_characteristics()),
+            unequipped: unequipped.iter().map(|item| item.name.clone()).collect(),
+        })
+    }
+
+    /// Remove an item from every slot holding it.
+    pub fn unequip_item(&mut self, item: &Rc<Item>) {
+        for part in PartToEquiEnum::all() {
+            if self.equipped.get(&part).map(|e| Rc::ptr_eq(&e, item)).unwrap_or(false) {
+                self.equipped.set(&part, None);
+            }
+        }
+    }
+
+    pub fn find_ammunition(&self, kind: AmmunitionEnum) -> Option<Rc<Item>> {
+        self.inventory.items().into_iter().find(|item| item.ammunition == Some(kind))
+    }
+
+    /// Weapons needing a reload are only ready once loaded, the others as soon as there is ammunition.
+    pub fn is_ready_to_shoot(&self, weapon: &Rc<Item>) -> bool {
+        !weapon.reload || self.loaded_weapons.iter().any(|w| Rc::ptr_eq(w, weapon))
+    }
+
+    pub fn reload(&mut self, weapon: &Rc<Item>) -> Result<(), String> {
+        let kind = weapon.uses_ammunition.ok_or(format!("{} does not need to be loaded", weapon.name))?;
+        if self.is_ready_to_shoot(weapon) {
+            return Err(format!("{} is already loaded", weapon.name));
+        }
+        if self.find_ammunition(kind).is_none() {
+            return Err(format!("There is no {:?} left to load {}", kind, weapon.name));
+        }
+        self.loaded_weapons.push(weapon.clone());
+        Ok(())
+    }
+
+    /// Take the ammunition for a shot out of the inventory, the weapon has to be reloaded afterwards.
+    pub fn shoot(&mut self, weapon: &Rc<Item>) -> Result<Rc<Item>, String> {
+        let kind = weapon.uses_ammunition.ok_or(format!("{} does not shoot anything", weapon.name))?;
+        if !self.is_ready_to_shoot(weapon) {
+            return Err(format!("{} has to be reloaded", weapon.name));
+        }
+        let ammunition = self.find_ammunition(kind).ok_or(format!("There is no {:?} left for {}", kind, weapon.name))?;
+
+        self.loaded_weapons.retain(|w| !Rc::ptr_eq(w, weapon));
+        Ok(self.remove_item(&ammunition).unwrap())
+    }
+
+    /// A different weapon in each hand.
+    pub fn dual_wielding(&self) -> bool {
+        match (&self.equipped.right_hand, &self.equipped.left_hand) {
+            (Some(right), Some(left)) => !Rc::ptr_eq(right, left) && right.attack_type.is_some() && left.attack_type.is_some(),
+            _ => false
+        }
+    }
+
+    pub fn total_characteristics(&self) -> Characteristics {
+        self.characteristics + self.calculate_power_up().unwrap()
+    }
+
+    /// Take one copy of an item out of the inventory, it is unequipped once the last copy is gone.
+    pub fn remove_item(&mut self, item: &Rc<Item>) -> Option<Rc<Item>> {
+        let removed = self.inventory.remove(item)?;
+        if self.inventory.contains(item) {
+            return Some(removed);
+        }
+
+        self.unequip_item(item);
+        Some(removed)
+    }
+
+    /// Weight the pawn can carry without being slowed.
+    pub fn carry_capacity(&self) -> u32 {
+        10 + self.characteristics.force as u32 * 5
+    }
+
+    pub fn encumbrance(&self) -> EncumbranceEnum {
+        let weight = self.inventory.weight();
+        let capacity = self.carry_capacity();
+        if weight > capacity {
+            EncumbranceEnum::OVERLOADED
+        } else if weight * 4 > capacity * 3 {
+            EncumbranceEnum::BURDENED
+        } else {
+            EncumbranceEnum::LIGHT
+        }
+    }
+
+    /// Tiles the pawn can walk in a turn, a heavy load slows it down.
+    pub fn movement(&self) -> u16 {
+        let movement = (self.characteristics.dexterity as u16 + self.characteristics.force as u16) / 3u16;
+        match self.encumbrance() {
+            EncumbranceEnum::LIGHT => movement,
+            EncumbranceEnum::BURDENED => (movement / 2).max(1),
+            EncumbranceEnum::OVERLOADED => 1,
+        }
+    }
+
+    /// A condition already on the pawn lasts for the longest of both durations.
+    pub fn add_condition(&mut self, kind: ConditionEnum, turns: u8) {
+        if kind == ConditionEnum::STUNNED {
+            self.turn_budget.reaction = false;
+        }
+        match self.conditions.iter_mut().find(|c| c.kind == kind) {
+            Some(condition) => condition.turns = condition.turns.max(turns),
+            None => self.conditions.push(Condition { kind, turns }),
+        }
+    }
+
+    /// Refill the turn budget and resolve the conditions, returns what happened to the pawn.
+    pub fn start_turn(&mut self) -> Vec<String> {
+        self.turn_budget = TurnBudget::new(self.movement());
+        self.shield = 0;
+
+        let mut messages = vec![];
+        for condition in self.conditions.iter_mut() {
+            condition.turns = condition.turns.saturating_sub(1);
+        }
+        for kind in self.conditions.iter().map(|c| c.kind).collect::<Vec<ConditionEnum>>() {
+            match kind {
+                ConditionEnum::BLEEDING => {
+                    let damages = self.take_hit(ConditionEnum::bleeding_damages());
+                    messages.push(format!("{} bleeds and loses {} life", self.name, damages));
+                }
+                ConditionEnum::STUNNED => {
+                    self.turn_budget = TurnBudget::default();
+                    self.turn_budget.reaction = false;
+                    messages.push(format!("{} is stunned", self.name));
+                }
+                ConditionEnum::LOST_ACTION => {
+                    self.turn_budget.action = false;
+                    messages.push(format!("{} is still recovering and loses its action", self.name));
+                }
+            }
         }
+        self.conditions.retain(|c| c.turns > 0);
+        messages
     }
 
+    pub fn is_dead(&self) -> bool {
+        self.life == 0
+    }
+
+    /// Empty a slot, a two handed item leaves both hands.
     pub fn de_equip(&mut self, part_to_unequip: PartToEquiEnum) {
-        match part_to_unequip {
-            PartToEquiEnum::HEAD => self.equipped.head = None,
-            PartToEquiEnum::BODY => self.equipped.body = None,
-            PartToEquiEnum::LEFT_HAND => self.equipped.left_hand = None,
-            PartToEquiEnum::RIGHT_HAND => self.equipped.right_hand = None,
-            PartToEquiEnum::LEGS => self.equipped.legs = None,
-            PartToEquiEnum::FEET => self.equipped.feet = None,
+        if let Some(item) = self.equipped.get(&part_to_unequip) {
+            self.unequip_item(&item);
         }
     }
 
     pub fn calculate_power_up(&self) -> Option<Characteristics> {
-        self.equipped.get_all_props()
+        Some(self.equipped.unique_items()
             .iter()
-            .map(|el|
-                if let Some(item) = el.1 {
-                    if let Some(power_up) = item.power_up {
-                        power_up
-                    } else {
-                        Characteristics::default()
-                    }
-                } else {
-                    Characteristics::default()
-                })
-            .reduce(|acc, el|
-                acc + el
-            )
+            .map(|item| item.power_up.unwrap_or_default())
+            .fold(Characteristics::default(), |acc, el| acc + el))
     }
 
     pub fn calculate_usability(&self, damage_dealer_pocketable: Rc<dyn Pocketable>, menu: &Menu) -> std::io::Result<u8> {
@@ -148,43 +428,7 @@ impl Pawn {
     }
 
     pub fn calculate_armor_points(&self) -> u8 {
-        let total_armor = self.pure_armor_points();
-
-        if total_armor > 0 {
-            total_armor + CA
-        } else {
-            self.dex_total() + CA
-        }
-    }
-
-    fn dex_total(&self) -> u8 {
-        let dex_total = self.calculate_power_up()
-            .iter()
-            .map(|char| char.dexterity)
-            .reduce(|acc, e| acc + e)
-            .unwrap();
-        dex_total
-    }
-
-    fn pure_armor_points(&self) -> u8 {
-        let total_armor = self.equipped.get_all_props()
-            .iter()
-            .map(|el| el.1)
-            .filter(|&&el| {
-                if let Some(item) = el {
-                    return item.clone().armor_point > 0;
-                }
-                return false;
-            }
-            )
-            .map(|el| if let Some(e) = el {
-                e.clone().armor_point
-            } else {
-                0
-            })
-            .reduce(|acc, el| acc + el)
-            .unwrap_or(0);
-        total_armor
+        Rules::pawn_armor_class(self) + self.shield
     }
 }
 
@@ -281,13 +525,22 @@ impl Watch for Pawn {
 
 impl ObjectToSprite for Pawn {
     fn get_world_origin(&self) -> Vec<Sprite> {
-        let texture_id = match self.race.as_str() {
-            "human" => 200,
-            "goblin" => 201,
-            _ => 201
-        };
+        vec![Sprite::new(self.sprite.as_str(), self.position.x as i32, self.position.y as i32, Layer::MOVABLES)]
+    }
+}
 
-        vec![Sprite::new(texture_id, self.position.x as i32, self.position.y as i32, Layer::MOVABLES)]
+impl Pawn {
+    /// What the window shows over the pawn sprite.
+    pub fn status(&self) -> PawnStatus {
+        PawnStatus {
+            id: self.id,
+            pos_x: self.position.x as i32,
+            pos_y: self.position.y as i32,
+            life: self.life,
+            max_life: self.max_life,
+            mana: self.mana,
+            max_mana: self.max_mana,
+        }
     }
 }
 
@@ -313,4 +566,37 @@ impl EquipablePart {
 
         result_map
     }
+
+    pub fn get(&self, part: &PartToEquiEnum) -> Option<Rc<Item>> {
+        match part {
+            PartToEquiEnum::HEAD => self.head.clone(),
+            PartToEquiEnum::RIGHT_HAND => self.right_hand.clone(),
+            PartToEquiEnum::LEFT_HAND => self.left_hand.clone(),
+            PartToEquiEnum::BODY => self.body.clone(),
+            PartToEquiEnum::LEGS => self.legs.clone(),
+            PartToEquiEnum::FEET => self.feet.clone(),
+        }
+    }
+
+    pub fn set(&mut self, part: &PartToEquiEnum, item: Option<Rc<Item>>) {
+        match part {
+            PartToEquiEnum::HEAD => self.head = item,
+            PartToEquiEnum::RIGHT_HAND => self.right_hand = item,
+            PartToEquiEnum::LEFT_HAND => self.left_hand = item,
+            PartToEquiEnum::BODY => self.body = item,
+            PartToEquiEnum::LEGS => self.legs = item,
+            PartToEquiEnum::FEET => self.feet = item,
+        }
+    }
+
+    /// Equipped items counted once, a two handed item is in both hands.
+    pub fn unique_items(&self) -> Vec<Rc<Item>> {
+        let mut items: Vec<Rc<Item>> = vec![];
+        for item in PartToEquiEnum::all().iter().filter_map(|part| self.get(part)) {
+            if !items.iter().any(|i| Rc::ptr_eq(i, &item)) {
+                items.push(item);
+            }
+        }
+        items
+    }
 }
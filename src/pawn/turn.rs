@@ -0,0 +1,141 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+
+/// What doing something costs from the turn budget.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ActionCostEnum {
+    FREE,
+    MOVEMENT(u16),
+    ACTION,
+    BONUS_ACTION,
+    REACTION,
+}
+
+/// What a pawn can still do this turn, it is refilled at the start of each of its turns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnBudget {
+    pub movement: u16,
+    pub action: bool,
+    pub bonus_action: bool,
+    pub reaction: bool,
+}
+
+/// Until its first turn a pawn can only react.
+impl Default for TurnBudget {
+    fn default() -> Self {
+        TurnBudget {
+            movement: 0,
+            action: false,
+            bonus_action: false,
+            reaction: true,
+        }
+    }
+}
+
+impl TurnBudget {
+    pub fn new(movement: u16) -> Self {
+        TurnBudget {
+            movement,
+            action: true,
+            bonus_action: true,
+            reaction: true,
+        }
+    }
+
+    pub fn can_afford(&self, cost: &ActionCostEnum) -> bool {
+        match cost {
+            ActionCostEnum::FREE => true,
+            ActionCostEnum::MOVEMENT(points) => self.movement >= *points,
+            ActionCostEnum::ACTION => self.action,
+            ActionCostEnum::BONUS_ACTION => self.bonus_action,
+            ActionCostEnum::REACTION => self.reaction,
+        }
+    }
+
+    pub fn spend(&mut self, cost: &ActionCostEnum) {
+        match cost {
+            ActionCostEnum::FREE => (),
+            ActionCostEnum::MOVEMENT(points) => self.movement = self.movement.saturating_sub(*points),
+            ActionCostEnum::ACTION => self.action = false,
+            ActionCostEnum::BONUS_ACTION => self.bonus_action = false,
+            ActionCostEnum::REACTION => self.reaction = false,
+        }
+    }
+
+    /// Give back what a cancelled action cost.
+    pub fn refund(&mut self, cost: &ActionCostEnum) {
+        match cost {
+            ActionCostEnum::FREE => (),
+            ActionCostEnum::MOVEMENT(points) => self.movement = self.movement.saturating_add(*points),
+            ActionCostEnum::ACTION => self.action = true,
+            ActionCostEnum::BONUS_ACTION => self.bonus_action = true,
+            ActionCostEnum::REACTION => self.reaction = true,
+        }
+    }
+
+    /// The reaction is kept for the other pawns turns, it does not count.
+    pub fn is_spent(&self) -> bool {
+        self.movement == 0 && !self.action && !self.bonus_action
+    }
+}
+
+impl Display for TurnBudget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let available = |available: bool| if available { "yes" } else { "no" };
+        write!(f, "Movement: {} | Action: {} | Bonus action: {} | Reaction: {}",
+               self.movement,
+               available(self.action),
+               available(self.bonus_action),
+               available(self.reaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spending_takes_from_the_budget_and_refunding_gives_it_back() {
+        //cost, still affordable after spending it once
+        let reference = [
+            (ActionCostEnum::FREE, true),
+            (ActionCostEnum::MOVEMENT(4), true),
+            (ActionCostEnum::MOVEMENT(6), false),
+            (ActionCostEnum::ACTION, false),
+            (ActionCostEnum::BONUS_ACTION, false),
+            (ActionCostEnum::REACTION, false),
+        ];
+        for (cost, affordable) in reference {
+            let mut budget = TurnBudget::new(8);
+            assert!(budget.can_afford(&cost), "{:?} on a fresh budget", cost);
+            budget.spend(&cost);
+            assert_eq!(budget.can_afford(&cost), affordable, "{:?} a second time", cost);
+            budget.refund(&cost);
+            assert!(budget.can_afford(&cost), "{:?} after a refund", cost);
+            assert_eq!(budget.movement, 8);
+        }
+    }
+
+    #[test]
+    fn movement_never_goes_below_zero() {
+        let mut budget = TurnBudget::new(3);
+        assert!(!budget.can_afford(&ActionCostEnum::MOVEMENT(5)));
+        budget.spend(&ActionCostEnum::MOVEMENT(5));
+        assert_eq!(budget.movement, 0);
+    }
+
+    #[test]
+    fn the_reaction_does_not_keep_the_turn_going() {
+        let mut budget = TurnBudget::new(2);
+        assert!(!budget.is_spent());
+        for cost in [ActionCostEnum::MOVEMENT(2), ActionCostEnum::ACTION, ActionCostEnum::BONUS_ACTION] {
+            budget.spend(&cost);
+        }
+        assert!(budget.reaction);
+        assert!(budget.is_spent());
+
+        assert!(TurnBudget::default().is_spent());
+        assert!(TurnBudget::default().can_afford(&ActionCostEnum::REACTION));
+    }
+}
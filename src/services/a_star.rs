@@ -1,5 +1,6 @@
 use std::collections::{HashMap, VecDeque};
 use ggez::glam::Vec2;
+use crate::environment::tile::TILES;
 
 pub fn calculate_range(pawn_position: (u16, u16), range: u16, room: &Vec<Vec<u8>>) -> Vec<Vec<bool>> {
     let breath_result = breath_first(pawn_position, room, range);
@@ -8,7 +9,7 @@ pub fn calculate_range(pawn_position: (u16, u16), range: u16, room: &Vec<Vec<u8>
     for j in 0..room.len() {
         let mut row = vec![];
         for i in 0..room.get(0).unwrap().len() {
-            if breath_result.contains(&(i as u16, j as u16)) && room.get(j).unwrap().get(i).unwrap() != &20u8 {
+            if breath_result.contains(&(i as u16, j as u16)) && TILES.is_walkable(*room.get(j).unwrap().get(i).unwrap()) {
                 row.push(true);
             } else {
                 row.push(false);
@@ -20,6 +21,161 @@ pub fn calculate_range(pawn_position: (u16, u16), range: u16, room: &Vec<Vec<u8>
     result
 }
 
+/// Tiles a pawn can walk to while the sum of the tiles movement costs stays in its movement points.
+pub fn calculate_walk_range(pawn_position: (u16, u16), movement: u16, room: &Vec<Vec<u8>>) -> Vec<Vec<bool>> {
+    let (size_x, size_y) = (room.get(0).unwrap().len() as u16, room.len() as u16);
+    let mut cost_so_far = HashMap::new();
+    cost_so_far.insert(pawn_position, 0u16);
+    let mut frontier = VecDeque::new();
+    frontier.push_back(pawn_position);
+
+    while let Some(current) = frontier.pop_front() {
+        let current_cost = *cost_so_far.get(&current).unwrap();
+        for next in get_neighbours((size_x, size_y), current) {
+            let tile = match room.get(next.1 as usize).and_then(|row| row.get(next.0 as usize)) {
+                Some(&tile) if TILES.is_walkable(tile) => tile,
+                _ => continue,
+            };
+
+            let next_cost = current_cost + TILES.movement_cost(tile) as u16;
+            if next_cost <= movement && cost_so_far.get(&next).map(|&cost| next_cost < cost).unwrap_or(true) {
+                cost_so_far.insert(next, next_cost);
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    (0..size_y)
+        .map(|y| (0..size_x)
+            .map(|x| (x, y) != pawn_position && cost_so_far.contains_key(&(x, y)))
+            .collect::<Vec<bool>>())
+        .collect::<Vec<Vec<bool>>>()
+}
+
+/// Cheapest walk from start to goal, the start is not part of the path.
+/// Avoided tiles cost a lot more so they are only crossed when there is no other way.
+pub fn find_path(start: (u16, u16), goal: (u16, u16), room: &Vec<Vec<u8>>, avoid: &Vec<(u16, u16)>) -> Option<Vec<(u16, u16)>> {
+    if start == goal {
+        return Some(vec![]);
+    }
+
+    let (size_x, size_y) = (room.get(0).unwrap().len() as u16, room.len() as u16);
+    let mut cost_so_far = HashMap::new();
+    let mut came_from = HashMap::new();
+    cost_so_far.insert(start, 0u32);
+    let mut frontier = vec![(0u32, start)];
+
+    while !frontier.is_empty() {
+        //Cheapest estimated tile first, estimation being the cost so far plus the manhattan distance
+        let cheapest = frontier.iter()
+            .enumerate()
+            .min_by_key(|(_, (priority, _))| *priority)
+            .map(|(i, _)| i)
+            .unwrap();
+        let (_, current) = frontier.swap_remove(cheapest);
+
+        if current == goal {
+            let mut path = vec![goal];
+            let mut step = goal;
+            while let Some(&previous) = came_from.get(&step) {
+                if previous == start {
+                    break;
+                }
+                path.push(previous);
+                step = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for next in get_neighbours((size_x, size_y), current) {
+            let tile = match room.get(next.1 as usize).and_then(|row| row.get(next.0 as usize)) {
+                Some(&tile) if TILES.is_walkable(tile) && next != current => tile,
+                _ => continue,
+            };
+
+            let penalty = if avoid.contains(&next) && next != goal { 100 } else { 0 };
+            let next_cost = cost_so_far.get(&current).unwrap() + TILES.movement_cost(tile) as u32 + penalty;
+            if cost_so_far.get(&next).map(|&cost| next_cost < cost).unwrap_or(true) {
+                cost_so_far.insert(next, next_cost);
+                came_from.insert(next, current);
+                let heuristic = (next.0 as i32 - goal.0 as i32).unsigned_abs() + (next.1 as i32 - goal.1 as i32).unsigned_abs();
+                frontier.push((next_cost + heuristic, next));
+            }
+        }
+    }
+
+    None
+}
+
+/// Movement points needed to walk a path.
+pub fn path_cost(path: &Vec<(u16, u16)>, room: &Vec<Vec<u8>>) -> u16 {
+    path.iter()
+        .map(|(x, y)| TILES.movement_cost(room[*y as usize][*x as usize]) as u16)
+        .sum()
+}
+
+/// Nothing but transparent tiles between both positions, start and end tiles are not checked.
+pub fn line_of_sight(from: (u16, u16), to: (u16, u16), room: &Vec<Vec<u8>>) -> bool {
+    let (mut x, mut y) = (from.0 as i32, from.1 as i32);
+    let (target_x, target_y) = (to.0 as i32, to.1 as i32);
+    let (dx, dy) = ((target_x - x).abs(), -(target_y - y).abs());
+    let (step_x, step_y) = (if x < target_x { 1 } else { -1 }, if y < target_y { 1 } else { -1 });
+    let mut error = dx + dy;
+
+    loop {
+        if (x, y) == (target_x, target_y) {
+            return true;
+        }
+        if (x, y) != (from.0 as i32, from.1 as i32) {
+            let transparent = room.get(y as usize)
+                .and_then(|row| row.get(x as usize))
+                .map(|&tile| TILES.is_transparent(tile))
+                .unwrap_or(false);
+            if !transparent {
+                return false;
+            }
+        }
+
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Every tile a pawn can walk to from the start, whatever the distance.
+pub fn reachable_tiles(start: (u16, u16), room: &Vec<Vec<u8>>) -> Vec<(u16, u16)> {
+    let walkable = |(x, y): (u16, u16)| room.get(y as usize)
+        .and_then(|row| row.get(x as usize))
+        .map(|&tile| TILES.is_walkable(tile))
+        .unwrap_or(false);
+
+    if !walkable(start) {
+        return vec![];
+    }
+
+    let (size_x, size_y) = (room.get(0).unwrap().len() as u16, room.len() as u16);
+    let mut frontier = VecDeque::new();
+    frontier.push_back(start);
+    let mut visited = vec![start];
+    while let Some(current) = frontier.pop_front() {
+        for next in get_neighbours((size_x, size_y), current) {
+            if walkable(next) && !visited.contains(&next) {
+                frontier.push_back(next);
+                visited.push(next);
+            }
+        }
+    }
+
+    visited
+}
+
 fn get_neighbours(room_size: (u16, u16), position: (u16, u16)) -> Vec<(u16, u16)> {
     let up = calculate_min_value(position.0);
     let left = calculate_min_value(position.1);
@@ -36,7 +192,7 @@ fn get_neighbours(room_size: (u16, u16), position: (u16, u16)) -> Vec<(u16, u16)
 }
 
 fn calculate_max_value(room_size_max: u16, position: u16) -> u16 {
-    if position as i32 + 1 > room_size_max as i32 {
+    if position as i32 + 1 >= room_size_max as i32 {
         room_size_max - 1
     } else {
         position + 1
@@ -62,7 +218,7 @@ fn breath_first(start: (u16, u16), room: &Vec<Vec<u8>>, range: u16) -> Vec<(u16,
     came_from.insert(start, None);
     while !frontier.is_empty() {
         let current = frontier.pop_front().unwrap();
-        let (size_x, size_y) = (room.len() as u16, room.get(0).unwrap().len() as u16);
+        let (size_x, size_y) = (room.get(0).unwrap().len() as u16, room.len() as u16);
 
 
         if calculate_dist(Vec2::new(current.0 as f32, current.1 as f32), Vec2::new(start.0 as f32, start.1 as f32)) > range * range {
@@ -81,4 +237,57 @@ fn breath_first(start: (u16, u16), room: &Vec<Vec<u8>>, range: u16) -> Vec<(u16,
         .filter(|(k, &v)| v != None)
         .map(|(k,v)| k.clone())
         .collect::<Vec<(u16,u16)>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbours_stay_inside_the_room() {
+        //room size, position, next position
+        let reference = [(3, 0, 1), (3, 1, 2), (3, 2, 2), (1, 0, 0)];
+        for (size, position, next) in reference {
+            assert_eq!(calculate_max_value(size, position), next, "after {} in a room of {}", position, size);
+        }
+
+        let (size_x, size_y) = (3, 2);
+        for y in 0..size_y {
+            for x in 0..size_x {
+                for (next_x, next_y) in get_neighbours((size_x, size_y), (x, y)) {
+                    assert!(next_x < size_x && next_y < size_y, "({}, {}) is a neighbour of ({}, {})", next_x, next_y, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn range_spreads_along_the_width_of_a_corridor() {
+        let corridor = vec![vec![10u8; 6]];
+        let range = calculate_range((0, 0), 3, &corridor);
+        assert!(range[0][1] && range[0][3]);
+        assert!(!range[0][5]);
+    }
+
+    #[test]
+    fn nothing_to_walk_when_already_there() {
+        let room = vec![vec![10u8; 3]; 3];
+        assert_eq!(find_path((1, 1), (1, 1), &room, &vec![]), Some(vec![]));
+    }
+
+    #[test]
+    fn avoided_tiles_are_walked_around() {
+        let room = vec![vec![10u8; 3]; 3];
+        let path = find_path((0, 1), (2, 1), &room, &vec![(1, 1)]).unwrap();
+        assert!(!path.contains(&(1, 1)));
+        assert_eq!(path.len(), 4);
+        assert_eq!(path.last(), Some(&(2, 1)));
+
+        //Without a way around, the avoided tile is crossed
+        let corridor = vec![vec![10u8; 3]];
+        assert_eq!(find_path((0, 0), (2, 0), &corridor, &vec![(1, 0)]), Some(vec![(1, 0), (2, 0)]));
+
+        //An avoided goal is still reached
+        assert_eq!(find_path((0, 1), (1, 1), &room, &vec![(1, 1)]), Some(vec![(1, 1)]));
+    }
 }
\ No newline at end of file
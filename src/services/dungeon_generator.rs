@@ -0,0 +1,492 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::environment::world::{Exit, Place, Weather, World, WorldError};
+use crate::environment::tile::{TileTriggerEnum, TriggerTile};
+use crate::interact::dialogue::DialogueBook;
+use crate::interact::fumble::FumbleTable;
+use crate::logic::hot_seat::HotSeat;
+use crate::logic::quest::{Journal, QuestBook};
+use crate::inventory::item::{DamageTypeEnum, Item};
+use crate::pawn::faction::Reputation;
+use crate::pawn::party::Party;
+use crate::pawn::pawn::{Pawn, Position};
+use crate::services::a_star::reachable_tiles;
+use crate::services::initializer::Initializer;
+
+const FLOOR: u8 = 10;
+const DOOR: u8 = 13;
+const WALL: u8 = 20;
+
+const MIN_LEAF_SIZE: usize = 5;
+const MIN_ROOM_SIZE: usize = 3;
+const CAVE_FILL_PERCENT: f32 = 0.45;
+const CAVE_SMOOTHING_STEPS: u8 = 5;
+const CAVE_ATTEMPTS: u8 = 20;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutTypeEnum {
+    BSP,
+    CELLULAR_AUTOMATA,
+    MIXED,
+}
+
+#[derive(Debug, Clone)]
+pub struct DungeonSettings {
+    pub seed: u64,
+    pub depth: u8,
+    pub width: usize,
+    pub height: usize,
+    pub layout: LayoutTypeEnum,
+    pub fumbles: FumbleTable,
+}
+
+impl DungeonSettings {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            depth: 4,
+            width: 20,
+            height: 14,
+            layout: LayoutTypeEnum::MIXED,
+            fumbles: FumbleTable::default(),
+        }
+    }
+}
+
+/// Doors of a level, the arrival is the floor tile next to the door where pawns coming threw it are put.
+struct Level {
+    room: Vec<Vec<u8>>,
+    entrance: Option<(Position, Position)>,
+    exit: Option<(Position, Position)>,
+    start: Position,
+}
+
+pub struct DungeonGenerator {
+    settings: DungeonSettings,
+    rng: StdRng,
+}
+
+impl DungeonGenerator {
+    pub fn new(settings: DungeonSettings) -> Self {
+        let rng = StdRng::seed_from_u64(settings.seed);
+        Self {
+            settings,
+            rng,
+        }
+    }
+
+    /// Build a world of `depth` places chained by doors, the party starts in the first one.
+    /// Monsters and loot get more numerous and stronger the deeper the place is.
+    pub fn generate(&mut self, weathers: &Vec<Rc<Weather>>, members: &Vec<Rc<RefCell<Pawn>>>, party: Party) -> Result<World, WorldError> {
+        let levels = (0..self.settings.depth)
+            .map(|depth| self.generate_level(depth))
+            .collect::<Vec<Level>>();
+
+        let first = levels.first().unwrap();
+        let doors = first.exit.iter().map(|(door, _)| door.clone()).collect::<Vec<Position>>();
+        let positions = party.formation.place(&first.start, members.len(), &first.room, &doors);
+        for (member, position) in members.iter().zip(positions) {
+            member.borrow_mut().position = position;
+        }
+
+        let mut places = vec![];
+        for (depth, level) in levels.iter().enumerate() {
+            let mut occupied = vec![level.start.clone()];
+            if depth == 0 {
+                occupied.extend(members.iter().map(|p| p.borrow().position.clone()));
+            }
+            occupied.extend(level.entrance.iter().flat_map(|(door, arrival)| vec![door.clone(), arrival.clone()]));
+            occupied.extend(level.exit.iter().flat_map(|(door, arrival)| vec![door.clone(), arrival.clone()]));
+
+            let mut pawns = self.generate_monsters(depth as u8, &level.room, &mut occupied);
+            if depth == 0 {
+                pawns.extend(members.iter().cloned());
+            }
+
+            places.push(Place {
+                id: Self::place_id(depth),
+                name: format!("Dungeon level {}", depth + 1),
+                weather: Rc::clone(weathers.get(3).unwrap_or(weathers.get(0).unwrap())),
+                time: "Night".to_string(),
+                light: 64,
+                adjacent_places: vec![],
+                exits: vec![],
+                pawns,
+                objects: self.generate_loot(depth as u8, &level.room, &mut occupied),
+                triggers: self.generate_triggers(depth as u8, &level.room, &mut occupied),
+                gas_clouds: vec![],
+                visited: depth == 0,
+                room: level.room.clone(),
+            });
+        }
+
+        let mut world = World {
+            name: format!("Dungeon {}", self.settings.seed),
+            places,
+            day: 0,
+            fumbles: self.settings.fumbles.clone(),
+            dialogues: DialogueBook::default(),
+            flags: HashSet::new(),
+            quests: QuestBook::default(),
+            journal: Journal::default(),
+            reputation: Reputation::default(),
+            party,
+            hot_seat: HotSeat::new(members),
+        };
+
+        for depth in 1..levels.len() {
+            let (previous, next) = (levels.get(depth - 1).unwrap(), levels.get(depth).unwrap());
+            let (exit_door, exit_arrival) = previous.exit.clone().ok_or(WorldError::Disconnected(Self::place_id(depth - 1)))?;
+            let (entrance_door, entrance_arrival) = next.entrance.clone().ok_or(WorldError::Disconnected(Self::place_id(depth)))?;
+
+            world.link(Self::place_id(depth - 1), Exit {
+                position: exit_door,
+                destination: Self::place_id(depth),
+                spawn: entrance_arrival,
+                description: format!("Stairs down to dungeon level {}", depth + 1),
+            })?;
+            world.link(Self::place_id(depth), Exit {
+                position: entrance_door,
+                destination: Self::place_id(depth - 1),
+                spawn: exit_arrival,
+                description: format!("Stairs up to dungeon level {}", depth),
+            })?;
+        }
+
+        world.check_connectivity(Self::place_id(0))?;
+        Ok(world)
+    }
+
+    fn place_id(depth: usize) -> u8 {
+        depth as u8 + 1
+    }
+
+    fn generate_level(&mut self, depth: u8) -> Level {
+        let is_last = depth + 1 == self.settings.depth;
+        let layout = match self.settings.layout {
+            LayoutTypeEnum::MIXED if self.rng.gen_bool(0.5) => LayoutTypeEnum::BSP,
+            LayoutTypeEnum::MIXED => LayoutTypeEnum::CELLULAR_AUTOMATA,
+            layout => layout,
+        };
+
+        let mut room = match layout {
+            LayoutTypeEnum::CELLULAR_AUTOMATA => self.cellular_automata_layout().unwrap_or_else(|| self.bsp_layout()),
+            _ => self.bsp_layout(),
+        };
+
+        let floors = Self::tiles_of(&room, FLOOR);
+
+        //Doors are put on floor tiles touching another floor tile, that one is the arrival
+        let door_candidates = floors.iter()
+            .filter_map(|door| Self::floor_neighbour(&room, door).map(|arrival| (door.clone(), arrival)))
+            .collect::<Vec<(Position, Position)>>();
+
+        let entrance = if depth > 0 {
+            Some(door_candidates.get(self.rng.gen_range(0..door_candidates.len())).unwrap().clone())
+        } else {
+            None
+        };
+
+        let start = match &entrance {
+            Some((_, arrival)) => arrival.clone(),
+            None => floors.get(self.rng.gen_range(0..floors.len())).unwrap().clone(),
+        };
+
+        //The way further is the door the farthest from where we come from
+        let exit = if is_last {
+            None
+        } else {
+            door_candidates.iter()
+                .filter(|(door, arrival)| Some(door) != entrance.as_ref().map(|e| &e.0) &&
+                    Some(arrival) != entrance.as_ref().map(|e| &e.0) &&
+                    door != &start)
+                .max_by_key(|(door, _)| Self::distance(door, &start))
+                .cloned()
+        };
+
+        for (door, _) in entrance.iter().chain(exit.iter()) {
+            room[door.y as usize][door.x as usize] = DOOR;
+        }
+
+        Level {
+            room,
+            entrance,
+            exit,
+            start,
+        }
+    }
+
+    fn bsp_layout(&mut self) -> Vec<Vec<u8>> {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let mut room = vec![vec![WALL; width]; height];
+        self.split(&mut room, 1, 1, width - 2, height - 2);
+        room
+    }
+
+    /// Split the area in two until it is too small, carve a room in each leaf
+    /// and join both halves with a corridor. Returns the center of one of the carved rooms.
+    fn split(&mut self, room: &mut Vec<Vec<u8>>, x: usize, y: usize, w: usize, h: usize) -> (usize, usize) {
+        let can_split_vertically = w >= 2 * MIN_LEAF_SIZE;
+        let can_split_horizontally = h >= 2 * MIN_LEAF_SIZE;
+
+        if !can_split_vertically && !can_split_horizontally {
+            let room_w = self.rng.gen_range(MIN_ROOM_SIZE.min(w)..=w);
+            let room_h = self.rng.gen_range(MIN_ROOM_SIZE.min(h)..=h);
+            let room_x = x + self.rng.gen_range(0..=w - room_w);
+            let room_y = y + self.rng.gen_range(0..=h - room_h);
+            for row in room.iter_mut().skip(room_y).take(room_h) {
+                for tile in row.iter_mut().skip(room_x).take(room_w) {
+                    *tile = FLOOR;
+                }
+            }
+            return (room_x + room_w / 2, room_y + room_h / 2);
+        }
+
+        let vertical = can_split_vertically && (!can_split_horizontally || w >= h);
+        let (first, second) = if vertical {
+            let cut = self.rng.gen_range(MIN_LEAF_SIZE..=w - MIN_LEAF_SIZE);
+            (self.split(room, x, y, cut, h), self.split(room, x + cut, y, w - cut, h))
+        } else {
+            let cut = self.rng.gen_range(MIN_LEAF_SIZE..=h - MIN_LEAF_SIZE);
+            (self.split(room, x, y, w, cut), self.split(room, x, y + cut, w, h - cut))
+        };
+
+        Self::carve_corridor(room, first, second);
+
+        if self.rng.gen_bool(0.5) {
+            first
+        } else {
+            second
+        }
+    }
+
+    fn carve_corridor(room: &mut Vec<Vec<u8>>, from: (usize, usize), to: (usize, usize)) {
+        for x in from.0.min(to.0)..=from.0.max(to.0) {
+            room[from.1][x] = FLOOR;
+        }
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            room[y][to.0] = FLOOR;
+        }
+    }
+
+    /// Caves too small to be played are thrown away, none is returned when every attempt fails.
+    fn cellular_automata_layout(&mut self) -> Option<Vec<Vec<u8>>> {
+        let (width, height) = (self.settings.width, self.settings.height);
+        for _ in 0..CAVE_ATTEMPTS {
+            let mut room = (0..height)
+                .map(|y| (0..width)
+                    .map(|x| {
+                        let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+                        if border || self.rng.gen::<f32>() < CAVE_FILL_PERCENT {
+                            WALL
+                        } else {
+                            FLOOR
+                        }
+                    })
+                    .collect::<Vec<u8>>())
+                .collect::<Vec<Vec<u8>>>();
+
+            for _ in 0..CAVE_SMOOTHING_STEPS {
+                room = Self::smooth(&room);
+            }
+
+            //Only the biggest cave is kept so everything is reachable
+            let mut biggest_cave: Vec<(u16, u16)> = vec![];
+            let mut seen: Vec<(u16, u16)> = vec![];
+            for floor in Self::tiles_of(&room, FLOOR) {
+                let floor = (floor.x, floor.y);
+                if seen.contains(&floor) {
+                    continue;
+                }
+                let cave = reachable_tiles(floor, &room);
+                seen.extend(cave.iter());
+                if cave.len() > biggest_cave.len() {
+                    biggest_cave = cave;
+                }
+            }
+
+            if biggest_cave.len() < width * height / 4 {
+                continue;
+            }
+
+            for (y, row) in room.iter_mut().enumerate() {
+                for (x, tile) in row.iter_mut().enumerate() {
+                    if !biggest_cave.contains(&(x as u16, y as u16)) {
+                        *tile = WALL;
+                    }
+                }
+            }
+            return Some(room);
+        }
+        None
+    }
+
+    fn smooth(room: &Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        let (width, height) = (room.get(0).unwrap().len() as i32, room.len() as i32);
+        (0..height)
+            .map(|y| (0..width)
+                .map(|x| {
+                    if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                        return WALL;
+                    }
+                    let walls = (-1..=1)
+                        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                        .filter(|&(dx, dy)| (dx, dy) != (0, 0) && room[(y + dy) as usize][(x + dx) as usize] == WALL)
+                        .count();
+                    if walls >= 5 {
+                        WALL
+                    } else {
+                        FLOOR
+                    }
+                })
+                .collect::<Vec<u8>>())
+            .collect::<Vec<Vec<u8>>>()
+    }
+
+    fn generate_monsters(&mut self, depth: u8, room: &Vec<Vec<u8>>, occupied: &mut Vec<Position>) -> Vec<Rc<RefCell<Pawn>>> {
+        let weapons = Initializer::generate_items();
+        let count = 1 + depth / 2;
+
+        let mut monsters = vec![];
+        for _ in 0..count {
+            if let Some(position) = self.free_floor(room, occupied) {
+                let weapon = weapons.get(self.rng.gen_range(0..weapons.len())).unwrap().clone();
+                let mut goblin = Initializer::generate_goblin(weapon, position);
+                goblin.life = goblin.life.saturating_add(depth.saturating_mul(15));
+                goblin.max_life = goblin.life;
+                goblin.characteristics.force = goblin.characteristics.force.saturating_add(depth / 2);
+                goblin.characteristics.dexterity = goblin.characteristics.dexterity.saturating_add(depth / 2);
+                goblin.gold += depth as u32 * 5;
+                monsters.push(Rc::new(RefCell::new(goblin)));
+            }
+        }
+        monsters
+    }
+
+    fn generate_loot(&mut self, depth: u8, room: &Vec<Vec<u8>>, occupied: &mut Vec<Position>) -> Vec<(Position, Rc<Item>)> {
+        let items = Initializer::generate_items();
+        let count = 1 + depth / 3;
+
+        let mut loot = vec![];
+        for _ in 0..count {
+            if let Some(position) = self.free_floor(room, occupied) {
+                let item = items.get(self.rng.gen_range(0..items.len())).unwrap().clone();
+                loot.push((position, Rc::new(item)));
+            }
+        }
+        loot
+    }
+
+    /// Deeper places hide more traps, and harder to spot and to disarm.
+    fn generate_triggers(&mut self, depth: u8, room: &Vec<Vec<u8>>, occupied: &mut Vec<Position>) -> Vec<TriggerTile> {
+        let mut triggers = vec![];
+        for _ in 0..depth {
+            if let Some(position) = self.free_floor(room, occupied) {
+                let (effect, detection, disarm) = match self.rng.gen_range(0..5) {
+                    0 => (TileTriggerEnum::DAMAGE(DamageTypeEnum::PIERCING, 5 + depth * 2), 8 + depth, 10 + depth),
+                    1 => (TileTriggerEnum::POISON_GAS { radius: 1, turns: 3, damage: 2 + depth }, 10 + depth, 12 + depth),
+                    2 => (TileTriggerEnum::PIT(5 + depth * 3), 10 + depth, 0),
+                    3 => (TileTriggerEnum::HEAL(10 + depth * 5), 0, 0),
+                    _ => match self.free_floor(room, occupied) {
+                        Some(destination) => (TileTriggerEnum::TELEPORT(destination), 0, 0),
+                        None => continue,
+                    },
+                };
+                triggers.push(TriggerTile::new(position, effect, detection, disarm));
+            }
+        }
+        triggers
+    }
+
+    fn free_floor(&mut self, room: &Vec<Vec<u8>>, occupied: &mut Vec<Position>) -> Option<Position> {
+        let free = Self::tiles_of(room, FLOOR).into_iter()
+            .filter(|tile| !occupied.contains(tile))
+            .collect::<Vec<Position>>();
+        if free.is_empty() {
+            return None;
+        }
+
+        let position = free.get(self.rng.gen_range(0..free.len())).unwrap().clone();
+        occupied.push(position.clone());
+        Some(position)
+    }
+
+    fn tiles_of(room: &Vec<Vec<u8>>, tile_id: u8) -> Vec<Position> {
+        room.iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter()
+                .enumerate()
+                .filter(|(_, &tile)| tile == tile_id)
+                .map(move |(x, _)| Position { x: x as u16, y: y as u16 }))
+            .collect::<Vec<Position>>()
+    }
+
+    fn floor_neighbour(room: &Vec<Vec<u8>>, position: &Position) -> Option<Position> {
+        let (x, y) = (position.x as i32, position.y as i32);
+        [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)].iter()
+            .filter(|(x, y)| *x >= 0 && *y >= 0)
+            .map(|&(x, y)| Position { x: x as u16, y: y as u16 })
+            .find(|neighbour| room.get(neighbour.y as usize)
+                .and_then(|row| row.get(neighbour.x as usize))
+                .map(|&tile| tile == FLOOR)
+                .unwrap_or(false))
+    }
+
+    fn distance(a: &Position, b: &Position) -> u32 {
+        (a.x as i32 - b.x as i32).unsigned_abs() + (a.y as i32 - b.y as i32).unsigned_abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pawn::party::PartySettings;
+
+    fn generate(settings: DungeonSettings) -> Result<World, WorldError> {
+        let party = PartySettings::load("party.ron").unwrap();
+        DungeonGenerator::new(settings).generate(&Initializer::init_weather(), &party.create_members(), party.party.clone())
+    }
+
+    /// Everything a seed decides: the rooms, the doors and where pawns, loot and traps are put.
+    fn layout(world: &World) -> Vec<String> {
+        world.places.iter()
+            .map(|place| format!("{:?} {:?} {:?} {:?} {:?}",
+                                 place.room,
+                                 place.exits,
+                                 place.pawns.iter().map(|p| (p.borrow().name.clone(), p.borrow().position.clone())).collect::<Vec<(String, Position)>>(),
+                                 place.objects.iter().map(|(position, item)| (position.clone(), item.name.clone())).collect::<Vec<(Position, String)>>(),
+                                 place.triggers.iter().map(|t| (t.position.clone(), t.detection, t.disarm)).collect::<Vec<(Position, u8, u8)>>()))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_world() {
+        for seed in [0, 1, 42, 1234, u64::MAX] {
+            let first = generate(DungeonSettings::new(seed)).unwrap();
+            let second = generate(DungeonSettings::new(seed)).unwrap();
+            assert_eq!(layout(&first), layout(&second), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn every_place_can_be_reached_whatever_the_layout() {
+        for layout_type in [LayoutTypeEnum::BSP, LayoutTypeEnum::CELLULAR_AUTOMATA, LayoutTypeEnum::MIXED] {
+            for seed in 0..30 {
+                let settings = DungeonSettings { layout: layout_type, ..DungeonSettings::new(seed) };
+                let world = generate(settings).unwrap_or_else(|e| panic!("{:?} with seed {}: {}", layout_type, seed, e));
+                assert_eq!(world.places.len(), 4);
+                assert!(world.check_connectivity(1).is_ok(), "{:?} with seed {}", layout_type, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn a_cave_too_small_is_given_up() {
+        let settings = DungeonSettings { width: 3, height: 3, ..DungeonSettings::new(7) };
+        assert!(DungeonGenerator::new(settings).cellular_automata_layout().is_none());
+    }
+}
@@ -1,9 +1,20 @@
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use crate::ai::ai::AI;
-use crate::environment::world::{Place, Weather, World};
-use crate::inventory::item::{DamageTypeEnum, Item, ItemAttackTypeEnum, PartToEquiEnum, Spell};
-use crate::inventory::item::DamageTypeEnum::{BLUNT, SLASHING};
+use crate::environment::world::{Exit, Place, Weather, World};
+use crate::inventory::item::{AmmunitionEnum, ArmorTypeEnum, DamageTypeEnum, Item, ItemAttackTypeEnum, PartToEquiEnum, Spell};
+use crate::inventory::item::DamageTypeEnum::{BLUNT, PIERCING, SLASHING};
+use crate::environment::tile::{TileTriggerEnum, TriggerTile};
+use crate::inventory::inventory::Inventory;
+use crate::inventory::loot::LootTable;
+use crate::interact::dialogue::DialogueBook;
+use crate::interact::fumble::FumbleTable;
+use crate::logic::hot_seat::HotSeat;
+use crate::logic::quest::{Journal, QuestBook};
+use crate::interact::reactions::ReactionEnum;
+use crate::pawn::faction::{Reputation, PLAYERS};
+use crate::pawn::party::Party;
 use crate::pawn::pawn::{Characteristics, Pawn, Position};
 use crate::services::dice::Dice;
 
@@ -43,11 +54,13 @@ impl Initializer {
         ]
     }
 
-    pub fn init(weathers: &Vec<Rc<Weather>>, player: Rc<RefCell<Pawn>>, items: &mut Vec<Item>) -> World {
+    /// The party starts in the shire, lined up around its first member.
+    pub fn init(weathers: &Vec<Rc<Weather>>, members: &Vec<Rc<RefCell<Pawn>>>, party: Party, items: &mut Vec<Item>) -> World {
         let mut pawns = Self::generate_non_player_pawns(items);
-        pawns.push(player);
+        pawns.extend(members.iter().cloned());
+        pawns.push(Rc::new(RefCell::new(Self::generate_squire(Self::item_named("Basic iron sword").unwrap(), Position { x: 2, y: 4 }))));
         // pawns.push(player);
-        World {
+        let mut world = World {
             name: "totoland".to_string(),
             places: vec![
                 Place {
@@ -56,8 +69,16 @@ impl Initializer {
                     weather: Rc::clone(weathers.get(0).unwrap()),
                     time: "Day".to_string(),
                     light: 255,
-                    adjacent_places: vec![11],
+                    adjacent_places: vec![],
+                    exits: vec![],
                     pawns,
+                    objects: vec![],
+                    triggers: vec![
+                        TriggerTile::new(Position { x: 3, y: 2 }, TileTriggerEnum::DAMAGE(PIERCING, 8), 12, 12),
+                        TriggerTile::new(Position { x: 1, y: 5 }, TileTriggerEnum::POISON_GAS { radius: 1, turns: 3, damage: 3 }, 14, 13),
+                    ],
+                    gas_clouds: vec![],
+                    visited: true,
                     room: vec![vec![20,20,11,20,20,20],
                                vec![20,10,10 ,10,10,20],
                                vec![20,10,10 ,10,10,20],
@@ -73,8 +94,17 @@ impl Initializer {
                     weather: Rc::clone(weathers.get(0).unwrap()),
                     time: "Day".to_string(),
                     light: 255,
-                    adjacent_places: vec![10],
-                    pawns: vec![],
+                    adjacent_places: vec![],
+                    exits: vec![],
+                    pawns: vec![Rc::new(RefCell::new(Self::generate_hermit(Self::item_named("Basic wood club").unwrap(), Position { x: 1, y: 1 })))],
+                    objects: vec![],
+                    triggers: vec![
+                        TriggerTile::new(Position { x: 5, y: 0 }, TileTriggerEnum::HEAL(20), 0, 0),
+                        TriggerTile::new(Position { x: 4, y: 2 }, TileTriggerEnum::PIT(10), 10, 0),
+                        TriggerTile::new(Position { x: 0, y: 4 }, TileTriggerEnum::TELEPORT(Position { x: 5, y: 4 }), 0, 0),
+                    ],
+                    gas_clouds: vec![],
+                    visited: false,
                     room: vec![vec![10,10,10 ,10,10,10],
                                vec![10,10,10 ,10,10,10],
                                vec![10,10,10 ,10,10,10],
@@ -83,7 +113,33 @@ impl Initializer {
                 }
             ],
             day: 0,
-        }
+            fumbles: FumbleTable::load("fumbles.ron").unwrap(),
+            dialogues: DialogueBook::load("dialogues.ron").unwrap(),
+            flags: HashSet::new(),
+            quests: QuestBook::load("quests.ron").unwrap(),
+            journal: Journal::default(),
+            reputation: Reputation::default(),
+            party,
+            hot_seat: HotSeat::new(members),
+        };
+
+        world.link(12, Exit {
+            position: Position { x: 2, y: 0 },
+            destination: 11,
+            spawn: Position { x: 2, y: 3 },
+            description: "Path to Pays de Dun".to_string(),
+        }).unwrap();
+        world.link(11, Exit {
+            position: Position { x: 2, y: 4 },
+            destination: 12,
+            spawn: Position { x: 2, y: 1 },
+            description: "Path to La comté".to_string(),
+        }).unwrap();
+
+        let leader = members.first().map(|p| p.borrow().id).unwrap_or_default();
+        world.form_party(12, &Position { x: 4, y: 3 }, leader).unwrap();
+
+        world
     }
 
     pub fn generate_items() -> Vec<Item> {
@@ -103,8 +159,17 @@ impl Initializer {
             damages_type: Some(SLASHING),
             part_to_equip: PartToEquiEnum::RIGHT_HAND,
             armor_point: 0,
+            armor_type: None,
             attack_type: Some(ItemAttackTypeEnum::CONTACT),
-            range: Some(1)
+            range: Some(1),
+            weight: 3,
+            stackable: false,
+            two_handed: false,
+            uses_ammunition: None,
+            ammunition: None,
+            reload: false,
+            critical_bonus: 0,
+            attack_bonus: 0,
         },
             Item {
                 name: "Basic wood club".to_string(),
@@ -122,50 +187,378 @@ impl Initializer {
                 damages_type: Some(BLUNT),
                 part_to_equip: PartToEquiEnum::RIGHT_HAND,
                 armor_point: 0,
+                armor_type: None,
                 attack_type: Some(ItemAttackTypeEnum::CONTACT),
-                range: Some(1)
+                range: Some(1),
+                weight: 4,
+                stackable: false,
+                two_handed: false,
+                uses_ammunition: None,
+                ammunition: None,
+                reload: false,
+                critical_bonus: 0,
+                attack_bonus: 0,
+            },
+            Item {
+                name: "Short bow".to_string(),
+                damages: || Dice::roll_1d4() as u8,
+                requirements: Characteristics {
+                    force: 0,
+                    dexterity: 2,
+                    constitution: 0,
+                    intelligence: 0,
+                    willpower: 0,
+                    charisma: 0,
+                },
+                resistances: None,
+                power_up: None,
+                damages_type: Some(PIERCING),
+                part_to_equip: PartToEquiEnum::RIGHT_HAND,
+                armor_point: 0,
+                armor_type: None,
+                attack_type: Some(ItemAttackTypeEnum::DISTANCE),
+                range: Some(6),
+                weight: 2,
+                stackable: false,
+                two_handed: true,
+                uses_ammunition: Some(AmmunitionEnum::ARROW),
+                ammunition: None,
+                reload: false,
+                critical_bonus: 0,
+                attack_bonus: 0,
+            },
+            Item {
+                name: "Light crossbow".to_string(),
+                damages: || (Dice::roll_1d4() + Dice::roll_1d4()) as u8,
+                requirements: Characteristics {
+                    force: 1,
+                    dexterity: 1,
+                    constitution: 0,
+                    intelligence: 0,
+                    willpower: 0,
+                    charisma: 0,
+                },
+                resistances: None,
+                power_up: None,
+                damages_type: Some(PIERCING),
+                part_to_equip: PartToEquiEnum::RIGHT_HAND,
+                armor_point: 0,
+                armor_type: None,
+                attack_type: Some(ItemAttackTypeEnum::DISTANCE),
+                range: Some(8),
+                weight: 5,
+                stackable: false,
+                two_handed: true,
+                uses_ammunition: Some(AmmunitionEnum::BOLT),
+                ammunition: None,
+                reload: true,
+                critical_bonus: 0,
+                attack_bonus: 0,
             }
         ]
     }
 
+    pub fn generate_ammunition(kind: AmmunitionEnum) -> Item {
+        let name = match kind {
+            AmmunitionEnum::ARROW => "Arrow",
+            AmmunitionEnum::BOLT => "Bolt",
+        };
+        Item {
+            name: name.to_string(),
+            damages: || 0,
+            requirements: Characteristics::default(),
+            resistances: None,
+            power_up: None,
+            damages_type: None,
+            part_to_equip: PartToEquiEnum::RIGHT_HAND,
+            armor_point: 0,
+            armor_type: None,
+            attack_type: None,
+            range: None,
+            weight: 0,
+            stackable: true,
+            two_handed: false,
+            uses_ammunition: None,
+            ammunition: Some(kind),
+            reload: false,
+            critical_bonus: 0,
+            attack_bonus: 0,
+        }
+    }
+
+    /// Any item the adventure knows, by name, for what is given outside of loot.
+    pub fn item_named(name: &str) -> Option<Item> {
+        Self::generate_items().into_iter()
+            .chain(Self::generate_loot_tables().into_values().flat_map(|table| table.items.into_iter().map(|(item, _)| item)))
+            .find(|item| item.name == name)
+    }
+
+    /// What creatures of each race may carry, keyed by race.
+    pub fn generate_loot_tables() -> HashMap<String, LootTable> {
+        let mut loot_tables = HashMap::new();
+        loot_tables.insert("Goblin".to_string(), LootTable {
+            gold: (2, 12),
+            items: vec![
+                (Item {
+                    name: "Rusty dagger".to_string(),
+                    damages: || Dice::roll_1d4() as u8,
+                    requirements: Characteristics {
+                        force: 0,
+                        dexterity: 1,
+                        constitution: 0,
+                        intelligence: 0,
+                        willpower: 0,
+                        charisma: 0,
+                    },
+                    resistances: None,
+                    power_up: None,
+                    damages_type: Some(PIERCING),
+                    part_to_equip: PartToEquiEnum::RIGHT_HAND,
+                    armor_point: 0,
+                    armor_type: None,
+                    attack_type: Some(ItemAttackTypeEnum::CONTACT),
+                    range: Some(1),
+                    weight: 1,
+                    stackable: false,
+                    two_handed: false,
+                    uses_ammunition: None,
+                    ammunition: None,
+                    reload: false,
+                    critical_bonus: 0,
+                    attack_bonus: 0,
+                }, 0.3),
+                (Item {
+                    name: "Leather cap".to_string(),
+                    damages: || 0,
+                    requirements: Characteristics::default(),
+                    resistances: None,
+                    power_up: None,
+                    damages_type: None,
+                    part_to_equip: PartToEquiEnum::HEAD,
+                    armor_point: 1,
+                    armor_type: Some(ArmorTypeEnum::LIGHT),
+                    attack_type: None,
+                    range: None,
+                    weight: 1,
+                    stackable: false,
+                    two_handed: false,
+                    uses_ammunition: None,
+                    ammunition: None,
+                    reload: false,
+                    critical_bonus: 0,
+                    attack_bonus: 0,
+                }, 0.5),
+                (Item {
+                    name: "Wooden shield".to_string(),
+                    damages: || 0,
+                    requirements: Characteristics {
+                        force: 1,
+                        dexterity: 0,
+                        constitution: 0,
+                        intelligence: 0,
+                        willpower: 0,
+                        charisma: 0,
+                    },
+                    resistances: None,
+                    power_up: None,
+                    damages_type: None,
+                    part_to_equip: PartToEquiEnum::LEFT_HAND,
+                    armor_point: 2,
+                    armor_type: Some(ArmorTypeEnum::SHIELD),
+                    attack_type: None,
+                    range: None,
+                    weight: 5,
+                    stackable: false,
+                    two_handed: false,
+                    uses_ammunition: None,
+                    ammunition: None,
+                    reload: false,
+                    critical_bonus: 0,
+                    attack_bonus: 0,
+                }, 0.2),
+                (Item {
+                    name: "Notched greataxe".to_string(),
+                    damages: || (Dice::roll_1d4() + Dice::roll_1d4()) as u8,
+                    requirements: Characteristics {
+                        force: 4,
+                        dexterity: 0,
+                        constitution: 0,
+                        intelligence: 0,
+                        willpower: 0,
+                        charisma: 0,
+                    },
+                    resistances: None,
+                    power_up: None,
+                    damages_type: Some(SLASHING),
+                    part_to_equip: PartToEquiEnum::RIGHT_HAND,
+                    armor_point: 0,
+                    armor_type: None,
+                    attack_type: Some(ItemAttackTypeEnum::CONTACT),
+                    range: Some(1),
+                    weight: 8,
+                    stackable: false,
+                    two_handed: true,
+                    uses_ammunition: None,
+                    ammunition: None,
+                    reload: false,
+                    critical_bonus: 1,
+                    attack_bonus: 0,
+                }, 0.1),
+                (Self::generate_ammunition(AmmunitionEnum::BOLT), 0.3),
+            ],
+        });
+        loot_tables
+    }
+
     fn generate_non_player_pawns(item: &mut Vec<Item>) -> Vec<Rc<RefCell<Pawn>>> {
         let x = (rand::random::<f32>() * 1.0).ceil() as u8;
 
-        std::iter::repeat_with(|| {
-            let mut pawn = Pawn {
-                id: idgenerator::IdInstance::next_id(),
-                name: "bad".to_string(),
-                life: 100,
-                mana: 0,
-                characteristics: Characteristics {
-                    force: 5,
-                    dexterity: 1,
-                    constitution: 1,
-                    intelligence: 0,
-                    willpower: 0,
-                    charisma: 0,
-                },
-                inventory: vec![Rc::new(item.remove(0))],
-                equipped: Default::default(),
-                spell: vec![],
-                race: "Goblin".to_string(),
-                playable: false,
-                ai: Rc::new(RefCell::new(Some(AI {
-                    intelligence: 0,
-                    selected_target: None,
-                    seen_target: vec![],
-                    name: "bad".to_string(),
-                }))),
-                position: Position { x: 4, y: 4 },
-            };
-
-            pawn.equipped.right_hand = Some(pawn.inventory.get(0).unwrap().clone());
-            Rc::new(RefCell::new(pawn))
-        })
+        std::iter::repeat_with(|| Rc::new(RefCell::new(Self::generate_goblin(item.remove(0), Position { x: 4, y: 4 }))))
             .take(x as usize)
             .collect::<Vec<Rc<RefCell<Pawn>>>>()
     }
 
+    /// Keeps to himself until talked to, a failed persuasion turns him against the speaker.
+    pub fn generate_hermit(weapon: Item, position: Position) -> Pawn {
+        let mut pawn = Pawn {
+            id: idgenerator::IdInstance::next_id(),
+            name: "Old hermit".to_string(),
+            life: 60,
+            max_life: 60,
+            mana: 0,
+            max_mana: 0,
+            characteristics: Characteristics {
+                force: 2,
+                dexterity: 1,
+                constitution: 1,
+                intelligence: 4,
+                willpower: 2,
+                charisma: 2,
+            },
+            inventory: Inventory::new(vec![Rc::new(weapon)]),
+            gold: 5,
+            experience: 0,
+            equipped: Default::default(),
+            loaded_weapons: vec![],
+            turn_budget: Default::default(),
+            shield: 0,
+            conditions: vec![],
+            proficiencies: vec![ItemAttackTypeEnum::CONTACT],
+            spell: vec![],
+            race: "human".to_string(),
+            sprite: "hermit".to_string(),
+            playable: false,
+            owner: 0,
+            ai: Rc::new(RefCell::new(None)),
+            dialogue: Some("hermit".to_string()),
+            faction: "villagers".to_string(),
+            grudges: vec![],
+            position,
+        };
+
+        pawn.equipped.right_hand = Some(pawn.inventory.get(0).unwrap().item.clone());
+        pawn
+    }
+
+    /// Companion of the players, left to the AI.
+    pub fn generate_squire(weapon: Item, position: Position) -> Pawn {
+        let mut pawn = Pawn {
+            id: idgenerator::IdInstance::next_id(),
+            name: "Squire".to_string(),
+            life: 50,
+            max_life: 50,
+            mana: 0,
+            max_mana: 0,
+            characteristics: Characteristics {
+                force: 3,
+                dexterity: 2,
+                constitution: 1,
+                intelligence: 1,
+                willpower: 1,
+                charisma: 1,
+            },
+            inventory: Inventory::new(vec![Rc::new(weapon)]),
+            gold: 0,
+            experience: 0,
+            equipped: Default::default(),
+            loaded_weapons: vec![],
+            turn_budget: Default::default(),
+            shield: 0,
+            conditions: vec![],
+            proficiencies: vec![ItemAttackTypeEnum::CONTACT],
+            spell: vec![],
+            race: "human".to_string(),
+            sprite: "squire".to_string(),
+            playable: false,
+            owner: 0,
+            ai: Rc::new(RefCell::new(Some(AI {
+                intelligence: 0,
+                selected_target: None,
+                seen_target: vec![],
+                name: "Squire".to_string(),
+            }))),
+            dialogue: None,
+            faction: PLAYERS.to_string(),
+            grudges: vec![],
+            position,
+        };
+
+        pawn.equipped.right_hand = Some(pawn.inventory.get(0).unwrap().item.clone());
+        pawn
+    }
+
+    pub fn generate_goblin(weapon: Item, position: Position) -> Pawn {
+        let mut pawn = Pawn {
+            id: idgenerator::IdInstance::next_id(),
+            name: "bad".to_string(),
+            life: 100,
+            max_life: 100,
+            mana: 0,
+            max_mana: 0,
+            characteristics: Characteristics {
+                force: 5,
+                dexterity: 1,
+                constitution: 1,
+                intelligence: 0,
+                willpower: 0,
+                charisma: 0,
+            },
+            inventory: Inventory::new(vec![Rc::new(weapon)]),
+            gold: 0,
+            experience: 0,
+            equipped: Default::default(),
+            loaded_weapons: vec![],
+            turn_budget: Default::default(),
+            shield: 0,
+            conditions: vec![],
+            proficiencies: vec![ItemAttackTypeEnum::CONTACT],
+            spell: vec![],
+            race: "Goblin".to_string(),
+            sprite: "goblin".to_string(),
+            playable: false,
+            owner: 0,
+            ai: Rc::new(RefCell::new(Some(AI {
+                intelligence: 0,
+                selected_target: None,
+                seen_target: vec![],
+                name: "bad".to_string(),
+            }))),
+            dialogue: None,
+            faction: "goblins".to_string(),
+            grudges: vec![],
+            position,
+        };
+
+        pawn.equipped.right_hand = Some(pawn.inventory.get(0).unwrap().item.clone());
+
+        if let Some(loot_table) = Self::generate_loot_tables().get(&pawn.race) {
+            let (gold, items) = loot_table.roll();
+            pawn.gold += gold;
+            items.into_iter().for_each(|item| pawn.inventory.add(Rc::new(item)));
+        }
+        pawn
+    }
+
     pub fn generate_spells() -> Vec<Rc<Spell>> {
         vec![Rc::new(Spell {
             name: "Fireball".to_string(),
@@ -185,7 +578,48 @@ impl Initializer {
             power_up: None,
             damages_type: Some(DamageTypeEnum::FIRE),
             attack_type: Some(ItemAttackTypeEnum::MAGIC),
-            range: Some(5)
+            range: Some(5),
+            reaction: None
+        }), Rc::new(Spell {
+            name: "Shield".to_string(),
+            damages: || 0,
+            mana: 5,
+            passive: false,
+            requirements: Characteristics {
+                force: 0,
+                dexterity: 0,
+                constitution: 0,
+                intelligence: 1,
+                willpower: 0,
+                charisma: 0,
+            },
+            effect_time_turns: 1,
+            resistances: None,
+            power_up: None,
+            damages_type: None,
+            attack_type: None,
+            range: None,
+            reaction: Some(ReactionEnum::SHIELD)
+        }), Rc::new(Spell {
+            name: "Counterspell".to_string(),
+            damages: || 0,
+            mana: 10,
+            passive: false,
+            requirements: Characteristics {
+                force: 0,
+                dexterity: 0,
+                constitution: 0,
+                intelligence: 2,
+                willpower: 0,
+                charisma: 0,
+            },
+            effect_time_turns: 0,
+            resistances: None,
+            power_up: None,
+            damages_type: None,
+            attack_type: None,
+            range: Some(6),
+            reaction: Some(ReactionEnum::COUNTERSPELL)
         })]
     }
 }
\ No newline at end of file
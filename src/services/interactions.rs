@@ -2,19 +2,25 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use console::Term;
 use crate::gui::menu::Menu;
-use crate::inventory::item::Pocketable;
-use crate::pawn::pawn::Pawn;
+use crate::interact::interaction::Interaction;
+use crate::inventory::item::{short_range, Item, Pocketable, Spell};
+use crate::pawn::pawn::{Pawn, Position};
 use crate::services::dice::{Dice, RollDiceResult};
 
+pub const OFF_HAND_PENALTY: u8 = 4;
+pub const MAIN_HAND_PENALTY: u8 = 2;
+pub const LONG_RANGE_PENALTY: u8 = 4;
+
 pub struct Attack;
 
 impl Attack{
 
-    pub fn select_item_to_attack_with( player: Rc<RefCell<Pawn>>, action: Option<usize>, menu: &Menu) -> std::io::Result<Option<Rc<dyn Pocketable>>> {
+    pub fn select_item_to_attack_with( player: Rc<RefCell<Pawn>>, action: Option<usize>, interaction: &mut Interaction, menu: &Menu) -> std::io::Result<Option<Rc<dyn Pocketable>>> {
         return if let Some(act) = action {
             match act {
                 0 => Self::get_weapon(player.clone()),
-                1 => Self::get_spell(player.clone(), menu),
+                1 => Self::get_spell(player.clone(), interaction, menu),
+                2 => Self::get_off_hand_weapon(player.clone()),
                 _ => Ok(None)
             }
         } else {
@@ -31,32 +37,79 @@ impl Attack{
         }
     }
 
-    fn get_spell( player: Rc<RefCell<Pawn>>, menu: &Menu) -> std::io::Result<Option<Rc<dyn Pocketable>>> {
-        let borrowed_player = player.borrow();
-        if borrowed_player.spell.is_empty() {
+    /// The item held for a weapon attack, right hand for the main attack and left one for the off-hand attack.
+    pub fn weapon_in_hand(player: &Pawn, action: Option<usize>) -> Option<Rc<Item>> {
+        match action {
+            Some(0) => player.equipped.right_hand.clone(),
+            Some(2) => player.equipped.left_hand.clone(),
+            _ => None
+        }
+    }
+
+    /// Shots beyond the short range of the weapon are harder.
+    pub fn range_penalty(from: &Position, to: &Position, range: u16) -> u8 {
+        let distance = (from.x as i32 - to.x as i32).abs().max((from.y as i32 - to.y as i32).abs()) as u16;
+        if distance > short_range(range) {
+            LONG_RANGE_PENALTY
+        } else {
+            0
+        }
+    }
+
+    fn get_off_hand_weapon(player: Rc<RefCell<Pawn>>) -> std::io::Result<Option<Rc<dyn Pocketable>>> {
+        if !player.borrow().dual_wielding() {
+            return Ok(None);
+        }
+        let option = player.borrow().equipped.left_hand.clone();
+        Ok(option.map(|item| item as Rc<dyn Pocketable>))
+    }
+
+    /// Attack roll malus when a weapon is held in each hand, the off hand is the worst.
+    pub fn hand_penalty(player: &Pawn, off_hand: bool) -> u8 {
+        if !player.dual_wielding() {
+            0
+        } else if off_hand {
+            OFF_HAND_PENALTY
+        } else {
+            MAIN_HAND_PENALTY
+        }
+    }
+
+    fn get_spell( player: Rc<RefCell<Pawn>>, interaction: &mut Interaction, menu: &Menu) -> std::io::Result<Option<Rc<dyn Pocketable>>> {
+        //Reaction spells are only cast out of turn
+        let spells = player.borrow().spell.iter()
+            .filter(|spell| spell.reaction.is_none())
+            .cloned()
+            .collect::<Vec<Rc<Spell>>>();
+        if spells.is_empty() {
             menu.write_line("You have no spell to cast.")?;
             return Ok(None);
         }
 
         //Select spell
-        return if let Some(s) = menu.menu(borrowed_player
-                            .spell
-                            .iter()
-                            .map(|x| x.get_name().to_string())
-                            .collect::<Vec<String>>())? {
-            Ok(Some(borrowed_player.spell.get(s).unwrap().clone()))
+        let names = spells.iter()
+            .map(|x| x.get_name().to_string())
+            .collect::<Vec<String>>();
+        return if let Some(s) = interaction.choose(&player, menu, &names, || Some(0))? {
+            Ok(Some(spells.get(s).unwrap().clone()))
         } else {
-            menu.write_line("You have no spell to cast.")?;
             Ok(None)
         };
     }
 
-    pub fn roll_attack() -> RollDiceResult {
-        let dice = Dice::roll_1d20();
+    pub fn roll_attack(critical_threshold: u8) -> RollDiceResult {
+        Self::attack_result(Dice::roll_1d20(), critical_threshold)
+    }
+
+    /// Two dices are rolled and the worst one is kept.
+    pub fn roll_attack_with_disadvantage(critical_threshold: u8) -> RollDiceResult {
+        Self::attack_result(Dice::roll_1d20().min(Dice::roll_1d20()), critical_threshold)
+    }
 
+    fn attack_result(dice: u32, critical_threshold: u8) -> RollDiceResult {
         return if dice == 1 {
             RollDiceResult::Fumble
-        } else if dice == 20 {
+        } else if dice >= critical_threshold as u32 {
             RollDiceResult::Critical
         } else {
             RollDiceResult::Normal(dice as u8)
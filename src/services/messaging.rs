@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
@@ -33,6 +34,29 @@ impl Messaging {
         channel_client_receiver
     }
 
+    /// One sender by topic name.
+    pub fn create_topics(&mut self, topics: &[&str]) -> HashMap<String, Sender<MessageContent>> {
+        topics.iter()
+            .map(|topic| (topic.to_string(), self.create_topic()))
+            .collect()
+    }
+
+    /// A single receiver for all the topics, messages sent on the same channel come in the order they were sent.
+    pub fn subscribe_to_topics_in_order(&mut self, topics: &[&str]) -> Receiver<MessageContent> {
+        let (bus_sender, channel_client_receiver) = mpsc::channel();
+        for topic in topics {
+            self.outcoming_messages.push((topic.to_string(), bus_sender.clone()));
+        }
+        channel_client_receiver
+    }
+
+    /// One receiver by topic name.
+    pub fn subscribe_to_topics(&mut self, topics: &[&str]) -> HashMap<String, Receiver<MessageContent>> {
+        topics.iter()
+            .map(|topic| (topic.to_string(), self.subscribe_to_topic(topic.to_string())))
+            .collect()
+    }
+
 
     pub fn start_bus(incoming_messages: Vec<Receiver<MessageContent>>, outcoming_messages: Vec<(String, Sender<MessageContent>)>) -> thread::Result<()> {
         println!("Bus is starting");
@@ -3,4 +3,7 @@ pub mod interactions;
 pub mod dice;
 pub mod messaging;
 pub mod a_star;
-pub mod animator;
\ No newline at end of file
+pub mod dungeon_generator;
+pub mod resources;
+pub mod rules;
+pub mod network;
\ No newline at end of file
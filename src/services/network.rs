@@ -0,0 +1,562 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::from_utf8;
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::gui::combat_log::{LogCategoryEnum, LogEntry};
+use crate::interact::actions::Actions;
+use crate::inventory::inventory::InventoryCommandEnum;
+use crate::services::messaging::MessageContent;
+
+/// Raised whenever a frame changes, clients and servers only play together on the same version.
+pub const PROTOCOL_VERSION: u16 = 2;
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+/// Topics the game sends to the window.
+pub const UI_TOPICS: [&str; 20] = ["sprite", "stdout", "end_turn", "show_damage", "select", "targetable",
+    "current_player", "clear", "info_response", "gameplay_state", "change_room", "inventory", "projectile",
+    "turn_budget", "pawn_status", "dialogue", "journal", "party", "hand_off", "fog"];
+/// Topics the window answers the game on.
+pub const INPUT_TOPICS: [&str; 3] = ["select_response", "info", "inventory_command"];
+/// Player the next menus are for, the pawn playing or a pawn reacting. The server only takes orders from that one.
+pub const OWNER_TOPIC: &str = "current_owner";
+//Only the player of the pawn playing sees its menus, targets and fog
+const PRIVATE_TOPICS: [&str; 11] = ["select", "targetable", "info_response", "gameplay_state", "end_turn",
+    "inventory", "turn_budget", "dialogue", "journal", "fog", "hand_off"];
+//Things that happened once, a player joining later does not need them
+const EVENT_TOPICS: [&str; 5] = ["end_turn", "show_damage", "clear", "change_room", "projectile"];
+//Lines of the log sent again to a player joining the game
+const LOG_HISTORY: usize = 100;
+//A broken peer could announce any size
+const MAX_FRAME_SIZE: u32 = 16 * 1024 * 1024;
+const POLL_DELAY: Duration = Duration::from_millis(5);
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NetworkMode {
+    LOCAL,
+    SERVER(String),
+    CLIENT(String, u8),
+}
+
+impl NetworkMode {
+    /// `--server [address]` plays the game for remote players, `--connect [address] --player <n>` joins one as player n.
+    pub fn from_args(args: &Vec<String>) -> Self {
+        let address = |index: usize| args.get(index + 1)
+            .filter(|arg| !arg.starts_with("--"))
+            .cloned()
+            .unwrap_or(DEFAULT_ADDRESS.to_string());
+        if let Some(index) = args.iter().position(|arg| arg == "--server") {
+            NetworkMode::SERVER(address(index))
+        } else if let Some(index) = args.iter().position(|arg| arg == "--connect") {
+            let player = args.iter()
+                .position(|arg| arg == "--player")
+                .and_then(|index| args.get(index + 1))
+                .and_then(|player| player.parse::<u8>().ok())
+                .unwrap_or(1);
+            NetworkMode::CLIENT(address(index), player)
+        } else {
+            NetworkMode::LOCAL
+        }
+    }
+}
+
+/// What goes threw a connection, each frame is its size followed by its bincode.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FrameEnum {
+    /// First frame of a client, with the player it plays.
+    HELLO { version: u16, player: u8 },
+    WELCOME { version: u16 },
+    /// The server refused the connection or an order, the reason is shown to the player.
+    REJECTED(String),
+    MESSAGE { topic: String, content: Vec<u8> },
+    /// Another connection still plays that player, the client tries again later.
+    SEAT_TAKEN(u8),
+}
+
+impl FrameEnum {
+    pub fn write(&self, stream: &mut impl Write) -> std::io::Result<()> {
+        let bytes = bincode::serialize(self).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+        stream.write_all(&bytes)?;
+        stream.flush()
+    }
+
+    pub fn read(stream: &mut impl Read) -> std::io::Result<Self> {
+        let mut size = [0; 4];
+        stream.read_exact(&mut size)?;
+        let size = u32::from_be_bytes(size);
+        if size > MAX_FRAME_SIZE {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Frame of {} bytes refused", size)));
+        }
+        let mut bytes = vec![0; size as usize];
+        stream.read_exact(&mut bytes)?;
+        bincode::deserialize(&bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    fn message(message: &MessageContent) -> Self {
+        FrameEnum::MESSAGE {
+            topic: message.topic.clone(),
+            content: message.content.clone(),
+        }
+    }
+}
+
+/// Players connected to the server and what it remembers to show a joining player the game as it is.
+#[derive(Default)]
+struct ServerState {
+    //Players owning a pawn of the party, the only ones allowed in
+    players: Vec<u8>,
+    //Connection of each seated player, numbered to tell a player coming back from the connection it left
+    clients: HashMap<u8, (u64, TcpStream)>,
+    connections: u64,
+    //Player of the pawn playing, 0 while the AI plays
+    owner: u8,
+    //Last message of each topic with the player it went to, none when everybody got it
+    snapshot: HashMap<String, (Option<u8>, MessageContent)>,
+    log: VecDeque<MessageContent>,
+    //Options of the menu waiting for an answer
+    menu_size: Option<usize>,
+    //The game waits for a tile
+    target_awaited: bool,
+    //A watched tile is described until the player closes it
+    description_shown: bool,
+    inventory_open: bool,
+}
+
+impl ServerState {
+    /// A player owning no pawn is refused, a seat is only given back once its connection is lost.
+    fn seat(&self, player: u8) -> Result<(), FrameEnum> {
+        if !self.players.contains(&player) {
+            return Err(FrameEnum::REJECTED(format!("There is no player {} in this game", player)));
+        }
+        if self.clients.contains_key(&player) {
+            return Err(FrameEnum::SEAT_TAKEN(player));
+        }
+        Ok(())
+    }
+
+    /// The log and the last state of every topic are sent before anything new, a player coming back takes its seat again.
+    fn join(&mut self, player: u8, mut stream: TcpStream) -> std::io::Result<u64> {
+        for message in self.log.iter() {
+            FrameEnum::message(message).write(&mut stream)?;
+        }
+        for topic in UI_TOPICS.iter() {
+            if let Some((to, message)) = self.snapshot.get(*topic) {
+                if to.map_or(true, |to| to == player) {
+                    FrameEnum::message(message).write(&mut stream)?;
+                }
+            }
+        }
+        self.connections += 1;
+        self.clients.insert(player, (self.connections, stream));
+        Ok(self.connections)
+    }
+
+    /// The seat is freed, unless the player already came back on another connection.
+    fn leave(&mut self, player: u8, connection: u64) {
+        if self.clients.get(&player).map(|(id, _)| *id) == Some(connection) {
+            self.clients.remove(&player);
+        }
+    }
+
+    /// Send a message of the game to the players allowed to see it, the ones not answering are dropped.
+    fn relay(&mut self, message: MessageContent) {
+        let topic = message.topic.as_str();
+        if topic == OWNER_TOPIC {
+            self.owner = bincode::deserialize(message.content.as_slice()).unwrap_or_default();
+            return;
+        }
+        //What the game now waits for, the same way the window reads it
+        match topic {
+            "select" => self.menu_size = from_utf8(message.content.as_slice()).ok().map(|options| options.split(":").count()),
+            "targetable" => self.target_awaited = bincode::deserialize::<Vec<Vec<bool>>>(message.content.as_slice())
+                .map_or(false, |range| range.iter().flatten().any(|&cell| cell)),
+            "gameplay_state" => if bincode::deserialize::<Actions>(message.content.as_slice()).ok() == Some(Actions::WATCH) {
+                self.target_awaited = true;
+            },
+            "info_response" => self.description_shown = true,
+            "inventory" => self.inventory_open = true,
+            _ => ()
+        }
+
+        let to = if PRIVATE_TOPICS.contains(&topic) { Some(self.owner) } else { None };
+        if topic == "stdout" {
+            self.log.push_back(message.clone());
+            if self.log.len() > LOG_HISTORY {
+                self.log.pop_front();
+            }
+        } else if !EVENT_TOPICS.contains(&topic) {
+            self.snapshot.insert(topic.to_string(), (to, message.clone()));
+        }
+
+        let frame = FrameEnum::message(&message);
+        self.clients.retain(|player, (_, stream)| {
+            if to.map_or(false, |to| to != *player) {
+                return true;
+            }
+            match frame.write(stream) {
+                Ok(()) => true,
+                Err(e) => {
+                    println!("Player {} left : {}", player, e);
+                    false
+                }
+            }
+        });
+    }
+
+    /// Only the player of the pawn playing gives orders, and only among what it was offered.
+    /// Nothing reaches the game that it could not read.
+    fn validate(&mut self, player: u8, message: &MessageContent) -> Result<(), String> {
+        if !INPUT_TOPICS.contains(&message.topic.as_str()) {
+            return Err(format!("Players can not send on {}", message.topic));
+        }
+        if player != self.owner {
+            return Err(format!("Wait for your turn, player {} is playing", self.owner));
+        }
+        match message.topic.as_str() {
+            "select_response" => self.validate_choice(message.content.as_slice()),
+            "info" => self.validate_tile(message.content.as_slice()),
+            _ => self.validate_inventory_command(message.content.as_slice()),
+        }
+    }
+
+    fn validate_choice(&mut self, content: &[u8]) -> Result<(), String> {
+        let index = bincode::deserialize::<usize>(content).map_err(|_| "This is not a choice".to_string())?;
+        match self.menu_size {
+            Some(size) if index < size => {
+                self.menu_size = None;
+                self.snapshot.remove("select");
+                Ok(())
+            }
+            Some(_) => Err(format!("There is no option {}", index + 1)),
+            None => Err("No choice is awaited".to_string()),
+        }
+    }
+
+    /// A tile, or none to cancel, while the game waits for one. Anything closes a description.
+    fn validate_tile(&mut self, content: &[u8]) -> Result<(), String> {
+        let tile = bincode::deserialize::<Option<(u16, u16)>>(content);
+        if self.description_shown {
+            if !content.is_empty() && tile.is_err() {
+                return Err("This is not a tile".to_string());
+            }
+            self.description_shown = false;
+            self.snapshot.remove("info_response");
+            return Ok(());
+        }
+        if !self.target_awaited {
+            return Err("No tile is awaited".to_string());
+        }
+        tile.map_err(|_| "This is not a tile".to_string())?;
+        self.target_awaited = false;
+        self.snapshot.remove("targetable");
+        Ok(())
+    }
+
+    fn validate_inventory_command(&mut self, content: &[u8]) -> Result<(), String> {
+        let command = bincode::deserialize::<InventoryCommandEnum>(content).map_err(|_| "This is not an inventory command".to_string())?;
+        if !self.inventory_open {
+            return Err("The inventory is not open".to_string());
+        }
+        if matches!(command, InventoryCommandEnum::CLOSE) {
+            self.inventory_open = false;
+            self.snapshot.remove("inventory");
+        }
+        Ok(())
+    }
+
+    fn send_to(&mut self, player: u8, frame: &FrameEnum) {
+        if let Some((_, stream)) = self.clients.get_mut(&player) {
+            if frame.write(stream).is_err() {
+                self.clients.remove(&player);
+            }
+        }
+    }
+}
+
+/// Owns the game and its rules, the players only send orders and see what it relays.
+pub struct NetworkServer {}
+
+impl NetworkServer {
+    /// Accept players on the address and relay the game between the bus and them until the game stops.
+    pub fn run(address: &str, players: Vec<u8>, receiver: Receiver<MessageContent>, senders: HashMap<String, Sender<MessageContent>>) -> std::io::Result<()> {
+        let listener = TcpListener::bind(address)?;
+        println!("Server listening on {} with protocol {}", listener.local_addr()?, PROTOCOL_VERSION);
+        let state = Arc::new(Mutex::new(ServerState {
+            players,
+            ..ServerState::default()
+        }));
+
+        let accepting = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let state = accepting.clone();
+                        let senders = senders.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = Self::serve(stream, state, senders) {
+                                println!("Connection closed : {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => println!("Connection failed : {}", e),
+                }
+            }
+        });
+
+        //Messages are relayed in the order the game sent them, a menu always comes after the player it is for
+        while let Ok(message) = receiver.recv() {
+            state.lock().unwrap().relay(message);
+        }
+        Ok(())
+    }
+
+    /// Greet a player, send it the game as it is then take its orders until it leaves.
+    fn serve(mut stream: TcpStream, state: Arc<Mutex<ServerState>>, senders: HashMap<String, Sender<MessageContent>>) -> std::io::Result<()> {
+        let player = match FrameEnum::read(&mut stream)? {
+            FrameEnum::HELLO { version, player } if version == PROTOCOL_VERSION => player,
+            FrameEnum::HELLO { version, .. } => {
+                let reason = format!("The server speaks protocol {}, the client {}", PROTOCOL_VERSION, version);
+                return FrameEnum::REJECTED(reason).write(&mut stream);
+            }
+            _ => return FrameEnum::REJECTED("A connection starts with a hello".to_string()).write(&mut stream),
+        };
+        let connection = {
+            let mut state = state.lock().unwrap();
+            if let Err(refusal) = state.seat(player) {
+                return refusal.write(&mut stream);
+            }
+            FrameEnum::WELCOME { version: PROTOCOL_VERSION }.write(&mut stream)?;
+            state.join(player, stream.try_clone()?)?
+        };
+        println!("Player {} joined from {}", player, stream.peer_addr()?);
+
+        let orders = Self::take_orders(&mut stream, player, &state, &senders);
+        state.lock().unwrap().leave(player, connection);
+        println!("Player {} left", player);
+        orders
+    }
+
+    fn take_orders(stream: &mut TcpStream, player: u8, state: &Arc<Mutex<ServerState>>, senders: &HashMap<String, Sender<MessageContent>>) -> std::io::Result<()> {
+        loop {
+            let message = match FrameEnum::read(stream)? {
+                FrameEnum::MESSAGE { topic, content } => MessageContent { topic, content },
+                _ => continue
+            };
+            let mut state = state.lock().unwrap();
+            match state.validate(player, &message) {
+                Ok(()) => senders.get(&message.topic)
+                    .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("The game does not read {}", message.topic)))?
+                    .send(message)
+                    .map_err(|e| Error::new(ErrorKind::BrokenPipe, e.to_string()))?,
+                Err(reason) => state.send_to(player, &FrameEnum::REJECTED(reason)),
+            }
+        }
+    }
+}
+
+/// Plays on a server, the window is fed with what the server relays and its answers go back to it.
+pub struct NetworkClient {}
+
+impl NetworkClient {
+    /// Connect to the server as the player, and connect again whenever the connection is lost.
+    pub fn start(address: String, player: u8, receivers: HashMap<String, Receiver<MessageContent>>, senders: HashMap<String, Sender<MessageContent>>) {
+        thread::spawn(move || {
+            //Orders not delivered yet, sent again once connected back
+            let mut pending = vec![];
+            loop {
+                match Self::connect(address.as_str(), player) {
+                    Ok(stream) => {
+                        Self::notify(&senders, format!("Connected to {} as player {}", address, player));
+                        if let Err(e) = Self::play(stream, &receivers, &senders, &mut pending) {
+                            Self::notify(&senders, format!("Connection to {} lost : {}", address, e));
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                        Self::notify(&senders, format!("The server refused to play : {}", e));
+                        return;
+                    }
+                    Err(e) => println!("Can not join {} : {}", address, e),
+                }
+                thread::sleep(RECONNECT_DELAY);
+            }
+        });
+    }
+
+    fn connect(address: &str, player: u8) -> std::io::Result<TcpStream> {
+        let mut stream = TcpStream::connect(address)?;
+        FrameEnum::HELLO { version: PROTOCOL_VERSION, player }.write(&mut stream)?;
+        match FrameEnum::read(&mut stream)? {
+            FrameEnum::WELCOME { .. } => Ok(stream),
+            FrameEnum::REJECTED(reason) => Err(Error::new(ErrorKind::PermissionDenied, reason)),
+            FrameEnum::SEAT_TAKEN(player) => Err(Error::new(ErrorKind::AddrInUse, format!("Player {} is still seated", player))),
+            frame => Err(Error::new(ErrorKind::InvalidData, format!("Unexpected {:?}", frame))),
+        }
+    }
+
+    fn play(stream: TcpStream,
+            receivers: &HashMap<String, Receiver<MessageContent>>,
+            senders: &HashMap<String, Sender<MessageContent>>,
+            pending: &mut Vec<MessageContent>) -> std::io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        //Frames are read on their own thread, it stops with the connection
+        let (frames_sender, frames) = mpsc::channel();
+        let mut reader = stream;
+        thread::spawn(move || loop {
+            let frame = FrameEnum::read(&mut reader);
+            let lost = frame.is_err();
+            if frames_sender.send(frame).is_err() || lost {
+                break;
+            }
+        });
+
+        loop {
+            for receiver in receivers.values() {
+                while let Ok(message) = receiver.try_recv() {
+                    pending.push(message);
+                }
+            }
+            while !pending.is_empty() {
+                FrameEnum::message(&pending[0]).write(&mut writer)?;
+                pending.remove(0);
+            }
+
+            while let Ok(frame) = frames.try_recv() {
+                match frame? {
+                    FrameEnum::MESSAGE { topic, content } => {
+                        if let Some(sender) = senders.get(&topic) {
+                            let _ = sender.send(MessageContent { topic, content });
+                        }
+                    }
+                    FrameEnum::REJECTED(reason) => Self::notify(senders, reason),
+                    _ => ()
+                }
+            }
+            thread::sleep(POLL_DELAY);
+        }
+    }
+
+    /// Connection news go to the log of the window.
+    fn notify(senders: &HashMap<String, Sender<MessageContent>>, text: String) {
+        println!("{}", text);
+        let _ = senders.get("stdout").unwrap().send(MessageContent {
+            topic: "stdout".to_string(),
+            content: bincode::serialize(&LogEntry { category: LogCategoryEnum::SYSTEM, text }).unwrap(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(topic: &str, content: Vec<u8>) -> MessageContent {
+        MessageContent { topic: topic.to_string(), content }
+    }
+
+    #[test]
+    fn frames_go_threw_a_localhost_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        let frames = [
+            FrameEnum::HELLO { version: PROTOCOL_VERSION, player: 2 },
+            FrameEnum::MESSAGE { topic: "select_response".to_string(), content: bincode::serialize(&1usize).unwrap() },
+            FrameEnum::REJECTED("Wait for your turn".to_string()),
+        ];
+        for frame in frames.iter() {
+            frame.write(&mut client).unwrap();
+        }
+        for frame in frames.iter() {
+            assert_eq!(&FrameEnum::read(&mut server).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn only_the_owner_answers_among_the_options() {
+        let mut state = ServerState::default();
+        state.relay(message(OWNER_TOPIC, bincode::serialize(&2u8).unwrap()));
+        state.relay(message("select", "Attack:Walk:End turn".as_bytes().to_vec()));
+
+        assert!(state.validate(1, &message("select_response", bincode::serialize(&0usize).unwrap())).is_err());
+        assert!(state.validate(2, &message("select_response", bincode::serialize(&3usize).unwrap())).is_err());
+        assert!(state.validate(2, &message("sprite", vec![])).is_err());
+        assert!(state.validate(2, &message("select_response", bincode::serialize(&2usize).unwrap())).is_ok());
+        assert!(state.validate(2, &message("select_response", bincode::serialize(&2usize).unwrap())).is_err());
+    }
+
+    #[test]
+    fn a_seat_is_given_to_a_known_player_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut state = ServerState { players: vec![1, 2], ..ServerState::default() };
+
+        assert!(matches!(state.seat(3), Err(FrameEnum::REJECTED(_))));
+        assert!(state.seat(2).is_ok());
+        let first = state.join(2, stream.try_clone().unwrap()).unwrap();
+        assert_eq!(state.seat(2), Err(FrameEnum::SEAT_TAKEN(2)));
+
+        //The old connection leaving late does not free the seat taken back
+        state.leave(2, first);
+        let second = state.join(2, stream).unwrap();
+        state.leave(2, first);
+        assert_eq!(state.seat(2), Err(FrameEnum::SEAT_TAKEN(2)));
+        state.leave(2, second);
+        assert!(state.seat(2).is_ok());
+    }
+
+    #[test]
+    fn payloads_are_read_before_reaching_the_game() {
+        let mut state = ServerState::default();
+        state.relay(message(OWNER_TOPIC, bincode::serialize(&1u8).unwrap()));
+        let tile = message("info", bincode::serialize(&Some((2u16, 3u16))).unwrap());
+
+        assert!(state.validate(1, &tile).is_err());
+        state.relay(message("targetable", bincode::serialize(&vec![vec![false, true]]).unwrap()));
+        assert!(state.validate(1, &message("info", vec![7])).is_err());
+        assert!(state.validate(1, &tile).is_ok());
+        assert!(state.validate(1, &tile).is_err());
+
+        let close = message("inventory_command", bincode::serialize(&InventoryCommandEnum::CLOSE).unwrap());
+        assert!(state.validate(1, &close).is_err());
+        state.relay(message("inventory", vec![]));
+        assert!(state.validate(1, &message("inventory_command", vec![42, 0, 0, 0])).is_err());
+        assert!(state.validate(1, &close).is_ok());
+        assert!(state.validate(1, &close).is_err());
+    }
+
+    #[test]
+    fn a_reaction_hands_the_menu_to_its_player() {
+        let mut state = ServerState::default();
+        let answer = message("select_response", bincode::serialize(&0usize).unwrap());
+        //A goblin plays, the shield of player 1 is asked for
+        state.relay(message(OWNER_TOPIC, bincode::serialize(&0u8).unwrap()));
+        state.relay(message(OWNER_TOPIC, bincode::serialize(&1u8).unwrap()));
+        state.relay(message("select", "Yes:No".as_bytes().to_vec()));
+        assert_eq!(state.snapshot.get("select").map(|(to, _)| *to), Some(Some(1)));
+        assert!(state.validate(2, &answer).is_err());
+        assert!(state.validate(1, &answer).is_ok());
+
+        //Back to the goblin, player 1 gives no more orders
+        state.relay(message(OWNER_TOPIC, bincode::serialize(&0u8).unwrap()));
+        state.relay(message("select", "Yes:No".as_bytes().to_vec()));
+        assert!(state.validate(1, &answer).is_err());
+    }
+
+    #[test]
+    fn arguments_pick_the_network_mode() {
+        let args = |line: &str| line.split(' ').map(|arg| arg.to_string()).collect::<Vec<String>>();
+        assert_eq!(NetworkMode::from_args(&args("baston")), NetworkMode::LOCAL);
+        assert_eq!(NetworkMode::from_args(&args("baston --server")), NetworkMode::SERVER(DEFAULT_ADDRESS.to_string()));
+        assert_eq!(NetworkMode::from_args(&args("baston --connect 10.0.0.2:7878 --player 2")),
+                   NetworkMode::CLIENT("10.0.0.2:7878".to_string(), 2));
+    }
+}
@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+/// Path of a file of the resources folder, looked up the same way ggez does:
+/// next to the manifest when launched with cargo, next to the executable otherwise.
+pub fn resource_path(file: &str) -> PathBuf {
+    let root = std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+            .unwrap_or_default());
+
+    root.join("resources").join(file)
+}
+
+pub fn load_ron<T: serde::de::DeserializeOwned>(file: &str) -> std::io::Result<T> {
+    let content = std::fs::read_to_string(resource_path(file))?;
+    ron::from_str(content.as_str())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} : {}", file, e)))
+}
@@ -0,0 +1,202 @@
+use std::fmt::{Display, Formatter};
+use crate::inventory::item::{ArmorTypeEnum, ItemAttackTypeEnum, Pocketable};
+use crate::pawn::pawn::{Characteristics, Pawn, CA};
+
+/// Bonus added to attack rolls made with a kind of attack the pawn is trained in.
+pub const PROFICIENCY_BONUS: u8 = 2;
+
+/// One term of a roll, as `FOR 2` or `penalty -4`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RollTerm {
+    pub name: String,
+    pub value: i16,
+}
+
+/// An attack roll with everything added to the dice, kept to be printed in the combat log.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AttackRoll {
+    pub dice: u8,
+    pub terms: Vec<RollTerm>,
+}
+
+impl AttackRoll {
+    pub fn total(&self) -> u8 {
+        let total = self.dice as i16 + self.terms.iter().map(|t| t.value).sum::<i16>();
+        total.clamp(0, u8::MAX as i16) as u8
+    }
+
+    pub fn hits(&self, armor_class: u8) -> bool {
+        self.total() >= armor_class
+    }
+
+    /// `d20 12 + FOR 2 + prof 2 = 16 vs AC 14`
+    pub fn breakdown(&self, armor_class: u8) -> String {
+        format!("{} vs AC {}", self, armor_class)
+    }
+}
+
+impl Display for AttackRoll {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "d20 {}", self.dice)?;
+        for term in self.terms.iter() {
+            if term.value < 0 {
+                write!(f, " - {} {}", term.name, -term.value)?;
+            } else {
+                write!(f, " + {} {}", term.name, term.value)?;
+            }
+        }
+        write!(f, " = {}", self.total())
+    }
+}
+
+pub struct Rules;
+
+impl Rules {
+    /// Every two points of a characteristic give one point of modifier.
+    pub fn modifier(value: u8) -> i16 {
+        (value / 2) as i16
+    }
+
+    /// Characteristic an attack relies on, with its name.
+    pub fn attack_characteristic(attack_type: &ItemAttackTypeEnum, characteristics: &Characteristics) -> (&'static str, u8) {
+        match attack_type {
+            ItemAttackTypeEnum::CONTACT => ("FOR", characteristics.force),
+            ItemAttackTypeEnum::DISTANCE => ("DEX", characteristics.dexterity),
+            ItemAttackTypeEnum::MAGIC => ("WIL", characteristics.willpower),
+        }
+    }
+
+    /// Characteristic modifier, proficiency and weapon bonus are added to the dice, the penalty is taken off.
+    pub fn attack_roll(dice: u8, characteristic: (&str, u8), proficient: bool, weapon_bonus: u8, penalty: u8) -> AttackRoll {
+        let mut terms = vec![RollTerm { name: characteristic.0.to_string(), value: Self::modifier(characteristic.1) }];
+        if proficient {
+            terms.push(RollTerm { name: "prof".to_string(), value: PROFICIENCY_BONUS as i16 });
+        }
+        if weapon_bonus > 0 {
+            terms.push(RollTerm { name: "weapon".to_string(), value: weapon_bonus as i16 });
+        }
+        if penalty > 0 {
+            terms.push(RollTerm { name: "penalty".to_string(), value: -(penalty as i16) });
+        }
+        AttackRoll { dice, terms }
+    }
+
+    /// Attack roll of a pawn using an item, an item without attack type only adds the dice.
+    pub fn pawn_attack_roll(pawn: &Pawn, item: &dyn Pocketable, dice: u8, penalty: u8) -> AttackRoll {
+        match item.get_attack_type() {
+            Some(attack_type) => {
+                let characteristic = Self::attack_characteristic(&attack_type, &pawn.total_characteristics());
+                let proficient = pawn.proficiencies.contains(&attack_type);
+                Self::attack_roll(dice, characteristic, proficient, item.get_attack_bonus(), penalty)
+            }
+            None => Self::attack_roll(dice, ("none", 0), false, 0, penalty)
+        }
+    }
+
+    /// Roll against a difficulty with only the characteristic modifier added, as dialogue checks.
+    pub fn ability_check(dice: u8, characteristic: (&str, u8)) -> AttackRoll {
+        Self::attack_roll(dice, characteristic, false, 0, 0)
+    }
+
+    /// Dexterity modifier kept by the heaviest worn armor.
+    pub fn dexterity_bonus(dexterity: u8, armor_types: &[ArmorTypeEnum]) -> i16 {
+        let modifier = Self::modifier(dexterity);
+        armor_types.iter()
+            .filter_map(|armor_type| armor_type.dexterity_cap())
+            .min()
+            .map(|cap| modifier.min(cap))
+            .unwrap_or(modifier)
+    }
+
+    /// Base armor class, plus armor points of everything worn and the capped dexterity modifier.
+    pub fn armor_class(armor_points: u8, armor_types: &[ArmorTypeEnum], dexterity: u8) -> u8 {
+        let armor_class = CA as i16 + armor_points as i16 + Self::dexterity_bonus(dexterity, armor_types);
+        armor_class.clamp(0, u8::MAX as i16) as u8
+    }
+
+    pub fn pawn_armor_class(pawn: &Pawn) -> u8 {
+        let worn = pawn.equipped.unique_items();
+        let armor_points = worn.iter().map(|item| item.armor_point).sum::<u8>();
+        let armor_types = worn.iter()
+            .filter_map(|item| item.armor_type)
+            .collect::<Vec<ArmorTypeEnum>>();
+        Self::armor_class(armor_points, &armor_types, pawn.total_characteristics().dexterity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_follow_reference_table() {
+        let reference = [(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (5, 2), (6, 3), (7, 3), (8, 4), (9, 4), (10, 5)];
+        for (value, modifier) in reference {
+            assert_eq!(Rules::modifier(value), modifier, "modifier of {}", value);
+        }
+    }
+
+    #[test]
+    fn dexterity_is_capped_by_the_heaviest_armor() {
+        use ArmorTypeEnum::*;
+        //dexterity, worn armors, kept bonus
+        let reference: [(u8, &[ArmorTypeEnum], i16); 8] = [
+            (6, &[], 3),
+            (6, &[LIGHT], 3),
+            (6, &[MEDIUM], 2),
+            (2, &[MEDIUM], 1),
+            (6, &[HEAVY], 0),
+            (6, &[LIGHT, MEDIUM], 2),
+            (6, &[MEDIUM, HEAVY], 0),
+            (6, &[SHIELD], 3),
+        ];
+        for (dexterity, armors, bonus) in reference {
+            assert_eq!(Rules::dexterity_bonus(dexterity, armors), bonus, "dexterity {} with {:?}", dexterity, armors);
+        }
+    }
+
+    #[test]
+    fn armor_class_follows_reference_table() {
+        use ArmorTypeEnum::*;
+        //armor points, worn armors, dexterity, armor class
+        let reference: [(u8, &[ArmorTypeEnum], u8, u8); 6] = [
+            (0, &[], 0, 10),
+            (0, &[], 3, 11),
+            (1, &[LIGHT], 4, 13),
+            (3, &[MEDIUM], 8, 15),
+            (6, &[HEAVY], 8, 16),
+            (8, &[HEAVY, SHIELD], 2, 18),
+        ];
+        for (armor_points, armors, dexterity, armor_class) in reference {
+            assert_eq!(Rules::armor_class(armor_points, armors, dexterity), armor_class,
+                       "{} armor points with {:?} and DEX {}", armor_points, armors, dexterity);
+        }
+    }
+
+    #[test]
+    fn attack_totals_follow_reference_table() {
+        //dice, characteristic, proficient, weapon bonus, penalty, total
+        let reference = [
+            (12, 4, true, 0, 0, 16),
+            (12, 4, false, 0, 0, 14),
+            (10, 3, true, 1, 0, 14),
+            (10, 3, true, 0, 4, 9),
+            (2, 0, false, 0, 4, 0),
+        ];
+        for (dice, characteristic, proficient, weapon_bonus, penalty, total) in reference {
+            let roll = Rules::attack_roll(dice, ("FOR", characteristic), proficient, weapon_bonus, penalty);
+            assert_eq!(roll.total(), total, "{}", roll);
+        }
+    }
+
+    #[test]
+    fn breakdown_lists_every_term() {
+        let roll = Rules::attack_roll(12, ("FOR", 4), true, 0, 0);
+        assert_eq!(roll.breakdown(14), "d20 12 + FOR 2 + prof 2 = 16 vs AC 14");
+        assert!(roll.hits(14));
+
+        let roll = Rules::attack_roll(9, ("DEX", 3), true, 1, 4);
+        assert_eq!(roll.breakdown(12), "d20 9 + DEX 1 + prof 2 + weapon 1 - penalty 4 = 9 vs AC 12");
+        assert!(!roll.hits(12));
+    }
+}